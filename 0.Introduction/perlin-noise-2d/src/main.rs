@@ -2,6 +2,9 @@
 //! http://natureofcode.com/
 //!
 //! Introduction - Two-dimensional Perlin noise.
+//!
+//! The noise is colour graded, tone mapped then vignetted: 1, 2 and 3 toggle
+//! these passes, R reverses their order.
 
 extern crate piston_app;
 
@@ -24,17 +27,28 @@ struct App {
     base_hue: Scalar,
     color_offset: Scalar,
     time: Scalar,
+    post: PostProcessing,
     pipeline: Option<PistonPipeline<noise::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
 impl App {
     fn new() -> Self {
-        let mut rng = thread_rng();
         App {
-            base_hue: rng.gen(),
-            color_offset: rng.gen(),
+            base_hue: 0.0,
+            color_offset: 0.0,
             time: 0.0,
+            post: PostProcessing::new()
+                .pass(PostPass::ColorGrade {
+                    brightness: 0.02,
+                    contrast: 1.2,
+                    saturation: 1.4,
+                })
+                .pass(PostPass::ToneMap { exposure: 1.6 })
+                .pass(PostPass::Vignette {
+                    radius: 0.4,
+                    strength: 0.8,
+                }),
             pipeline: None,
             renderer: None,
         }
@@ -44,13 +58,36 @@ impl App {
         self.pipeline.as_ref().unwrap()
     }
 
-    fn renderer(&self) -> &PistonRenderer {
-        self.renderer.as_ref().unwrap()
+    fn configure_post_processing(&mut self, state: &PistonAppState) {
+        const KEYS: [Key; 3] = [Key::D1, Key::D2, Key::D3];
+        let mut changed = false;
+        for (index, &key) in KEYS.iter().enumerate() {
+            if state.key_hit(key) {
+                self.post.toggle(index);
+                changed = true;
+            }
+        }
+        if state.key_hit(Key::R) {
+            for index in 1..self.post.len() {
+                self.post.move_pass(index, 0);
+            }
+            changed = true;
+        }
+        if changed {
+            let passes: Vec<String> = (0..self.post.len())
+                .filter(|&index| self.post.is_enabled(index))
+                .map(|index| format!("{:?}", self.post.get(index).unwrap()))
+                .collect();
+            println!("Passes: [{}]", passes.join(", "));
+        }
     }
 }
 
 impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, _: &PistonAppState) {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        let mut rng = state.rng();
+        self.base_hue = rng.gen();
+        self.color_offset = rng.gen();
         let (pipeline, renderer) = PistonPipelineBuilder::new()
             .vertex_shader(include_bytes!("noise_150_core.glslv"))
             .fragment_shader(include_bytes!("noise_150_core.glslf"))
@@ -68,17 +105,21 @@ impl PistonApp for App {
             Vertex { pos: [1.0, 1.0] },
         ];
         const INDICES: &[u16] = &[0, 1, 2, 2, 3, 0];
+        self.configure_post_processing(state);
         self.color_offset += 1e-3;
         self.time += 0.00666;
-        self.renderer()
-            .draw(window, self.pipeline(), VERTICES, INDICES, |vbuf, out| {
-                noise::Data {
-                    vbuf: vbuf,
-                    color: state.noise_color(self.base_hue, self.color_offset, Some(1.0)),
-                    time: self.time as f32,
-                    out: out,
-                }
-            });
+        let renderer = self.renderer.as_ref().unwrap();
+        renderer.bind_target(Some(self.post.begin(window, state).unwrap()));
+        renderer.draw(window, self.pipeline(), VERTICES, INDICES, |vbuf, out| {
+            noise::Data {
+                vbuf: vbuf,
+                color: state.noise_color(self.base_hue, self.color_offset, Some(1.0)),
+                time: self.time as f32,
+                out: out,
+            }
+        });
+        renderer.bind_target(None);
+        self.post.end(window).unwrap();
     }
 }
 
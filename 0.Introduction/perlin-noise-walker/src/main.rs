@@ -16,8 +16,8 @@ struct Walker {
 }
 
 impl Walker {
-    fn new() -> Self {
-        let mut rng = thread_rng();
+    fn new(state: &PistonAppState) -> Self {
+        let mut rng = state.rng();
         Walker {
             base_hue: rng.gen(),
             color_offset: rng.gen(),
@@ -51,23 +51,33 @@ impl Walker {
 
 #[derive(Debug)]
 struct App {
-    walker: Walker,
+    walker: Option<Walker>,
 }
 
 impl App {
     fn new() -> Self {
-        App {
-            walker: Walker::new(),
-        }
+        App { walker: None }
+    }
+
+    fn walker(&self) -> &Walker {
+        self.walker.as_ref().unwrap()
+    }
+
+    fn walker_mut(&mut self) -> &mut Walker {
+        self.walker.as_mut().unwrap()
     }
 }
 
 impl PistonApp for App {
+    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+        self.walker = Some(Walker::new(state));
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
-        self.walker.update();
+        self.walker_mut().update();
         window.draw_2d(state.event(), |context, gfx| {
             clear(color::WHITE, gfx);
-            self.walker.draw(state, context, gfx);
+            self.walker().draw(state, context, gfx);
         });
     }
 }
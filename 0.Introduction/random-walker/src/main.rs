@@ -33,7 +33,7 @@ impl Walker {
     }
 
     fn update(&mut self, state: &PistonAppState) {
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         if state.mouse_button_pressed(MouseButton::Left) && rng.gen() {
             self.x += rng.gen::<Scalar>() * (state.mouse_x() - self.x).signum();
             self.y += rng.gen::<Scalar>() * (state.mouse_y() - self.y).signum();
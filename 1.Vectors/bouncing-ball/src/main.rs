@@ -16,8 +16,8 @@ struct Ball {
 }
 
 impl Ball {
-    fn new() -> Self {
-        let mut rng = thread_rng();
+    fn new(state: &PistonAppState) -> Self {
+        let mut rng = state.rng();
         Ball {
             base_hue: rng.gen(),
             color_offset: rng.gen(),
@@ -53,21 +53,33 @@ impl Ball {
 
 #[derive(Debug)]
 struct App {
-    ball: Ball,
+    ball: Option<Ball>,
 }
 
 impl App {
     fn new() -> Self {
-        App { ball: Ball::new() }
+        App { ball: None }
+    }
+
+    fn ball(&self) -> &Ball {
+        self.ball.as_ref().unwrap()
+    }
+
+    fn ball_mut(&mut self) -> &mut Ball {
+        self.ball.as_mut().unwrap()
     }
 }
 
 impl PistonApp for App {
+    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+        self.ball = Some(Ball::new(state));
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
-        self.ball.update(state);
+        self.ball_mut().update(state);
         window.draw_2d(state.event(), |context, gfx| {
             clear(color::WHITE, gfx);
-            self.ball.draw(state, context, gfx);
+            self.ball().draw(state, context, gfx);
         });
     }
 }
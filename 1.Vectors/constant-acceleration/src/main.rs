@@ -7,30 +7,31 @@ extern crate piston_app;
 
 use piston_app::*;
 
+const MAX_VELOCITY: Scalar = 9.0;
+
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
+    body: physics::Mover,
 }
 
 impl Mover {
     fn new(state: &PistonAppState) -> Self {
         Mover {
             color: color::TRANSPARENT,
-            position: [state.width() / 2.0, state.height() / 2.0],
-            velocity: [0.0, 0.0],
-            acceleration: [-3e-3, 0.01],
+            body: physics::Mover::new(state.width() / 2.0, state.height() / 2.0, 1.0)
+                .with_max_speed(MAX_VELOCITY)
+                .with_edges(physics::Edges::Wrap),
         }
     }
 
     fn draw(&self, context: Context, gfx: &mut G2d) {
+        let position = self.body.position();
         Ellipse::new_border(color::BLACK, 1.0)
             .resolution(32)
             .color(self.color)
             .draw(
-                ellipse::circle(self.position[0], self.position[1], 32.0),
+                ellipse::circle(position.x, position.y, 32.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
@@ -38,29 +39,12 @@ impl Mover {
     }
 
     fn update(&mut self, state: &PistonAppState) {
-        const MAX_VELOCITY: Scalar = 9.0;
-        self.velocity =
-            vec2_limit(vec2_add(self.velocity, self.acceleration), MAX_VELOCITY);
-        self.position = vec2_add(self.position, self.velocity);
-        self.check_edges(state);
-        let hue = state.map_range(vec2_len(self.velocity), 0.0, MAX_VELOCITY, 0.0, 120.0);
+        self.body.accelerate(Vector2::new(-3e-3, 0.01));
+        self.body.update(state);
+        let speed = self.body.velocity().mag();
+        let hue = state.map_range(speed, 0.0, MAX_VELOCITY, 0.0, 120.0);
         self.color = state.color_from_hsv(hue, 1.0, 2.0 / 3.0, 1.0);
     }
-
-    fn check_edges(&mut self, state: &PistonAppState) {
-        let (x, y) = (self.position[0], self.position[1]);
-        let (width, height) = (state.width(), state.height());
-        if x > width {
-            self.position[0] = 0.0;
-        } else if x < 0.0 {
-            self.position[0] = width;
-        }
-        if y > height {
-            self.position[1] = 0.0;
-        } else if y < 0.0 {
-            self.position[1] = height;
-        }
-    }
 }
 
 #[derive(Debug)]
@@ -10,22 +10,22 @@ use piston_app::*;
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
+    position: Vector2,
+    velocity: Vector2,
+    acceleration: Vector2,
 }
 
 impl Mover {
     fn new(state: &PistonAppState) -> Self {
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         Mover {
             color: state.random_color(Some(2.0 / 3.0)),
-            position: [
+            position: Vector2::new(
                 rng.gen_range(0.0, state.width()),
                 rng.gen_range(0.0, state.height()),
-            ],
-            velocity: [0.0, 0.0],
-            acceleration: [0.0, 0.0],
+            ),
+            velocity: Vector2::zero(),
+            acceleration: Vector2::zero(),
         }
     }
 
@@ -34,7 +34,7 @@ impl Mover {
             .resolution(32)
             .color(self.color)
             .draw(
-                ellipse::circle(self.position[0], self.position[1], 32.0),
+                ellipse::circle(self.position.x, self.position.y, 32.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
@@ -43,11 +43,10 @@ impl Mover {
 
     fn update(&mut self, state: &PistonAppState) {
         const MAX_VELOCITY: Scalar = 4.2;
-        let direction = vec2_sub([state.mouse_x(), state.mouse_y()], self.position);
-        self.acceleration = vec2_scale(vec2_normalized(direction), 0.5);
-        self.velocity =
-            vec2_limit(vec2_add(self.velocity, self.acceleration), MAX_VELOCITY);
-        self.position = vec2_add(self.position, self.velocity);
+        let mouse = Vector2::new(state.mouse_x(), state.mouse_y());
+        self.acceleration = (mouse - self.position).set_mag(0.5);
+        self.velocity = (self.velocity + self.acceleration).limit(MAX_VELOCITY);
+        self.position += self.velocity;
     }
 }
 
@@ -39,8 +39,10 @@ impl Mover {
     fn update(&mut self, state: &PistonAppState) {
         const MAX_VELOCITY: Scalar = 9.0;
         const MAX_ACCELERATION: Scalar = 2.0;
-        self.acceleration =
-            vec2_scale(vec2_random(), thread_rng().gen_range(0.0, MAX_ACCELERATION));
+        self.acceleration = vec2_scale(
+            vec2_random(&mut state.rng()),
+            state.rng().gen_range(0.0, MAX_ACCELERATION),
+        );
         self.velocity =
             vec2_limit(vec2_add(self.velocity, self.acceleration), MAX_VELOCITY);
         self.position = vec2_add(self.position, self.velocity);
@@ -17,7 +17,7 @@ struct Mover {
 impl Mover {
     fn new(state: &PistonAppState) -> Self {
         const MAX_VELOCITY: Scalar = 6.0;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let uniform = Uniform::new_inclusive(-MAX_VELOCITY, MAX_VELOCITY);
         Mover {
             color: state.random_color(Some(1.0)),
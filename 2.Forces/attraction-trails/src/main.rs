@@ -2,6 +2,9 @@
 //! http://natureofcode.com/
 //!
 //! Forces - Render trails produced by gravitational attraction.
+//!
+//! The trails accumulate in an offscreen render target, faded a little on
+//! every frame.
 
 extern crate piston_app;
 
@@ -92,15 +95,25 @@ impl Mover {
 struct App {
     attractors: Vec<Attractor>,
     movers: Vec<Mover>,
+    trails: Option<PistonRenderTarget>,
 }
 
 impl App {
+    const TRAIL_FADE: ColorComponent = 0.02;
+
     fn new() -> Self {
         App {
             attractors: vec![],
             movers: vec![],
+            trails: None,
         }
     }
+
+    fn create_trails(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        let trails = PistonRenderTarget::from_viewport(window, state).unwrap();
+        trails.clear(window, color::WHITE);
+        self.trails = Some(trails);
+    }
 }
 
 impl PistonApp for App {
@@ -108,7 +121,7 @@ impl PistonApp for App {
         const MAX_G: Scalar = 0.8;
         const MAX_ATTRACTORS: usize = 4;
         const MAX_MOVERS: usize = 32;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let (width, height) = (state.width(), state.height());
         self.attractors = (0..MAX_ATTRACTORS)
             .map(|_| {
@@ -130,9 +143,7 @@ impl PistonApp for App {
                 )
             })
             .collect();
-        window.draw_2d(state.event(), |_, gfx| {
-            clear(color::WHITE, gfx);
-        });
+        self.create_trails(window, state);
     }
 
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
@@ -143,11 +154,24 @@ impl PistonApp for App {
             }
             mover.update();
         }
-        window.draw_2d(state.event(), |context, gfx| {
+        if !self.trails.as_ref().unwrap().matches_viewport(state) {
+            self.create_trails(window, state);
+        }
+        let trails = self.trails.as_ref().unwrap();
+        trails.draw_2d(window, |context, gfx| {
+            rectangle(
+                [1.0, 1.0, 1.0, Self::TRAIL_FADE],
+                [0.0, 0.0, trails.width(), trails.height()],
+                context.transform,
+                gfx,
+            );
             for mover in &self.movers {
                 mover.draw(context, gfx);
             }
         });
+        window.draw_2d(state.event(), |context, gfx| {
+            trails.draw(context.transform, gfx);
+        });
     }
 }
 
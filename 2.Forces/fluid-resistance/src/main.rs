@@ -10,25 +10,21 @@ use piston_app::*;
 #[derive(Debug)]
 struct Liquid {
     rect: types::Rectangle,
-    drag_coeff: Scalar,
+    drag: physics::Drag,
 }
 
 impl Liquid {
     fn new<R: Into<types::Rectangle>>(rect: R, drag_coeff: Scalar) -> Self {
+        let rect = rect.into();
         Liquid {
-            rect: rect.into(),
-            drag_coeff: drag_coeff,
+            rect: rect,
+            drag: physics::Drag::new(drag_coeff).area(rect),
         }
     }
 
     #[inline]
-    fn rect(&self) -> types::Rectangle {
-        self.rect
-    }
-
-    #[inline]
-    fn drag_coeff(&self) -> Scalar {
-        self.drag_coeff
+    fn drag(&self) -> physics::Drag {
+        self.drag
     }
 
     fn draw(&self, context: Context, gfx: &mut G2d) {
@@ -44,72 +40,34 @@ impl Liquid {
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
-    mass: Scalar,
+    body: physics::Mover,
 }
 
 impl Mover {
     fn new(color: Color, x: Scalar, y: Scalar, mass: Scalar) -> Self {
         Mover {
             color: color,
-            position: [x, y],
-            velocity: [0.0, 0.0],
-            acceleration: [0.0, 0.0],
-            mass: mass,
+            body: physics::Mover::new(x, y, mass).with_edges(physics::Edges::Bounce(1.0)),
         }
     }
 
-    fn mass(&self) -> Scalar {
-        self.mass
-    }
-
-    fn is_inside(&self, liquid: &Liquid) -> bool {
-        let (x, y) = (self.position[0], self.position[1]);
-        let ref rect = liquid.rect();
-        x > rect[0] && x < rect[0] + rect[2] && y > rect[1] && y < rect[1] + rect[3]
-    }
-
     fn draw(&self, context: Context, gfx: &mut G2d) {
+        let (position, mass) = (self.body.position(), self.body.mass());
         Ellipse::new_border(color::BLACK, 1.0)
-            .resolution(self.mass as Resolution * 16)
+            .resolution(mass as Resolution * 16)
             .color(self.color)
             .draw(
-                ellipse::circle(self.position[0], self.position[1], self.mass * 8.0),
+                ellipse::circle(position.x, position.y, mass * 8.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
             );
     }
+}
 
-    fn apply_drag(&mut self, liquid: &Liquid) {
-        let drag = vec2_scale(
-            vec2_normalized(self.velocity),
-            -1.0 * liquid.drag_coeff() * vec2_square_len(self.velocity),
-        );
-        self.apply_force(drag);
-    }
-
-    fn apply_force(&mut self, force: Vec2d) {
-        self.acceleration =
-            vec2_add(self.acceleration, vec2_scale(force, 1.0 / self.mass));
-    }
-
-    fn update(&mut self, state: &PistonAppState) {
-        let (x, y) = (self.position[0], self.position[1]);
-        let (width, height) = (state.width(), state.height());
-        if x > width || x < 0.0 {
-            self.position[0] = x.max(0.0).min(width);
-            self.velocity[0] *= -1.0;
-        }
-        if y > height || y < 0.0 {
-            self.position[1] = y.max(0.0).min(height);
-            self.velocity[1] *= -1.0;
-        }
-        self.velocity = vec2_add(self.velocity, self.acceleration);
-        self.position = vec2_add(self.position, self.velocity);
-        self.acceleration = [0.0, 0.0];
+impl AsMut<physics::Mover> for Mover {
+    fn as_mut(&mut self) -> &mut physics::Mover {
+        &mut self.body
     }
 }
 
@@ -117,6 +75,7 @@ impl Mover {
 struct App {
     liquids: Vec<Liquid>,
     movers: Vec<Mover>,
+    forces: physics::ForceRegistry,
 }
 
 impl App {
@@ -124,6 +83,7 @@ impl App {
         App {
             liquids: vec![],
             movers: vec![],
+            forces: physics::ForceRegistry::new(),
         }
     }
 }
@@ -134,8 +94,14 @@ impl PistonApp for App {
         let (width, height) = (state.width(), state.height());
         self.liquids
             .push(Liquid::new([0.0, height / 2.0, width, height / 2.0], 0.1));
+        self.forces.clear();
+        self.forces
+            .add(physics::Gravity::new(0.0, 0.1), physics::BodySet::All);
+        for liquid in &self.liquids {
+            self.forces.add(liquid.drag(), physics::BodySet::All);
+        }
         let gap = width / MAX_MOVERS as Scalar;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         self.movers = (0..MAX_MOVERS)
             .map(|i| {
                 Mover::new(
@@ -149,15 +115,9 @@ impl PistonApp for App {
     }
 
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        self.forces.apply(&mut self.movers);
         for mover in &mut self.movers {
-            let gravity = [0.0, 0.1 * mover.mass()];
-            mover.apply_force(gravity);
-            for liquid in &self.liquids {
-                if mover.is_inside(liquid) {
-                    mover.apply_drag(liquid);
-                }
-            }
-            mover.update(state);
+            mover.body.update(state);
         }
         window.draw_2d(state.event(), |context, gfx| {
             clear(color::WHITE, gfx);
@@ -10,94 +10,68 @@ use piston_app::*;
 #[derive(Debug)]
 struct Attractor {
     color: Color,
-    position: Vec2d,
-    mass: Scalar,
-    g: Scalar,
+    attraction: physics::Attraction,
 }
 
 impl Attractor {
     fn new(color: Color, x: Scalar, y: Scalar, mass: Scalar, g: Scalar) -> Self {
         Attractor {
             color: color,
-            position: [x, y],
-            mass: mass,
-            g: g,
+            attraction: physics::Attraction::new(Vector2::new(x, y), mass, g)
+                .distance(5.0, 25.0),
         }
     }
 
+    #[inline]
+    fn attraction(&self) -> physics::Attraction {
+        self.attraction
+    }
+
     fn draw(&self, context: Context, gfx: &mut G2d, alpha: ColorComponent) {
-        Ellipse::new_border([0.0, 0.0, 0.0, alpha], 2.0 + 4.0 * self.g)
-            .resolution(self.mass as Resolution)
+        let (position, mass) = (self.attraction.position(), self.attraction.mass());
+        Ellipse::new_border([0.0, 0.0, 0.0, alpha], 2.0 + 4.0 * self.attraction.g())
+            .resolution(mass as Resolution)
             .color([self.color[0], self.color[1], self.color[2], alpha])
             .draw(
-                ellipse::circle(self.position[0], self.position[1], self.mass * 2.0),
+                ellipse::circle(position.x, position.y, mass * 2.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
             );
     }
-
-    fn attract(&self, mover: &Mover) -> Vec2d {
-        let force = vec2_sub(self.position, mover.position());
-        let distance = vec2_len(force).max(5.0).min(25.0);
-        vec2_scale(
-            vec2_normalized(force),
-            (self.g * self.mass * mover.mass()) / (distance * distance),
-        )
-    }
 }
 
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
-    mass: Scalar,
+    body: physics::Mover,
 }
 
 impl Mover {
     fn new(color: Color, x: Scalar, y: Scalar, mass: Scalar) -> Self {
         Mover {
             color: color,
-            position: [x, y],
-            velocity: [0.0, 0.0],
-            acceleration: [0.0, 0.0],
-            mass: mass,
+            body: physics::Mover::new(x, y, mass),
         }
     }
 
-    #[inline]
-    fn position(&self) -> Vec2d {
-        self.position
-    }
-
-    #[inline]
-    fn mass(&self) -> Scalar {
-        self.mass
-    }
-
     fn draw(&self, context: Context, gfx: &mut G2d) {
+        let (position, mass) = (self.body.position(), self.body.mass());
         Ellipse::new_border(color::BLACK, 1.0)
-            .resolution(self.mass as Resolution * 12)
+            .resolution(mass as Resolution * 12)
             .color(self.color)
             .draw(
-                ellipse::circle(self.position[0], self.position[1], self.mass * 8.0),
+                ellipse::circle(position.x, position.y, mass * 8.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
             );
     }
+}
 
-    fn apply_force(&mut self, force: Vec2d) {
-        self.acceleration =
-            vec2_add(self.acceleration, vec2_scale(force, 1.0 / self.mass));
-    }
-
-    fn update(&mut self) {
-        self.velocity = vec2_add(self.velocity, self.acceleration);
-        self.position = vec2_add(self.position, self.velocity);
-        self.acceleration = [0.0, 0.0];
+impl AsMut<physics::Mover> for Mover {
+    fn as_mut(&mut self) -> &mut physics::Mover {
+        &mut self.body
     }
 }
 
@@ -106,6 +80,7 @@ struct App {
     attractors_alpha: ColorComponent,
     attractors: Vec<Attractor>,
     movers: Vec<Mover>,
+    forces: physics::ForceRegistry,
 }
 
 impl App {
@@ -114,6 +89,7 @@ impl App {
             attractors_alpha: 0.0,
             attractors: vec![],
             movers: vec![],
+            forces: physics::ForceRegistry::new(),
         }
     }
 
@@ -124,14 +100,12 @@ impl App {
         }
         self.attractors_alpha = (self.attractors_alpha + delta).max(0.0).min(1.0);
     }
-}
 
-impl PistonApp for App {
-    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+    fn reset(&mut self, state: &PistonAppState) {
         const MAX_G: Scalar = 0.8;
         const MAX_ATTRACTORS: usize = 4;
         const MAX_MOVERS: usize = 32;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let (width, height) = (state.width(), state.height());
         self.attractors = (0..MAX_ATTRACTORS)
             .map(|_| {
@@ -144,6 +118,11 @@ impl PistonApp for App {
                 )
             })
             .collect();
+        self.forces.clear();
+        for attractor in &self.attractors {
+            self.forces
+                .add(attractor.attraction(), physics::BodySet::All);
+        }
         self.movers = (0..MAX_MOVERS)
             .map(|_| {
                 Mover::new(
@@ -155,16 +134,26 @@ impl PistonApp for App {
             })
             .collect();
     }
+}
 
-    fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+impl PistonApp for App {
+    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+        self.reset(state);
+    }
+
+    fn setup_headless(&mut self, state: &PistonAppState) {
+        self.reset(state);
+    }
+
+    fn update(&mut self, _: Scalar, state: &PistonAppState) {
         self.handle_mouse(state);
+        self.forces.apply(&mut self.movers);
         for mover in &mut self.movers {
-            for attractor in &self.attractors {
-                let force = attractor.attract(mover);
-                mover.apply_force(force);
-            }
-            mover.update();
+            mover.body.integrate();
         }
+    }
+
+    fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         window.draw_2d(state.event(), |context, gfx| {
             clear(color::WHITE, gfx);
             for attractor in &self.attractors {
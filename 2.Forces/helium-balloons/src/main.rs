@@ -17,7 +17,7 @@ struct Mover {
 
 impl Mover {
     fn new(state: &PistonAppState) -> Self {
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         Mover {
             color: state.random_color(Some(2.0 / 3.0)),
             position: [
@@ -10,10 +10,7 @@ use piston_app::*;
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
-    mass: Scalar,
+    body: physics::Mover,
     g: Scalar,
 }
 
@@ -21,54 +18,48 @@ impl Mover {
     fn new(color: Color, x: Scalar, y: Scalar, mass: Scalar, g: Scalar) -> Self {
         Mover {
             color: color,
-            position: [x, y],
-            velocity: [0.0, 0.0],
-            acceleration: [0.0, 0.0],
-            mass: mass,
+            body: physics::Mover::new(x, y, mass),
             g: g,
         }
     }
 
     fn draw(&self, context: Context, gfx: &mut G2d) {
+        let (position, mass) = (self.body.position(), self.body.mass());
         Ellipse::new_border(color::BLACK, 1.0 + 3.0 * self.g)
-            .resolution(self.mass as Resolution * 12)
+            .resolution(mass as Resolution * 12)
             .color(self.color)
             .draw(
-                ellipse::circle(self.position[0], self.position[1], self.mass * 8.0),
+                ellipse::circle(position.x, position.y, mass * 8.0),
                 &context.draw_state,
                 context.transform,
                 gfx,
             );
     }
 
-    fn attract(&self, other: &Self) -> Vec2d {
-        let force = vec2_sub(self.position, other.position);
-        let distance = vec2_len(force).max(1.0).min(27.0);
-        vec2_scale(
-            vec2_normalized(force),
-            (self.g * self.mass * other.mass) / (distance * distance),
+    fn attract(&self, other: &Self) -> Vector2 {
+        let force = self.body.position() - other.body.position();
+        let distance = force.mag().clamp(1.0, 27.0);
+        force.set_mag(
+            (self.g * self.body.mass() * other.body.mass()) / (distance * distance),
         )
     }
 
-    fn apply_force(&mut self, force: Vec2d) {
-        self.acceleration =
-            vec2_add(self.acceleration, vec2_scale(force, 1.0 / self.mass));
-    }
-
     fn update(&mut self, state: &PistonAppState) {
-        let (x, y) = (self.position[0], self.position[1]);
+        let (position, velocity) = (self.body.position(), self.body.velocity());
         let (width, height) = (state.width(), state.height());
+        let (mut x, mut y) = (position.x, position.y);
+        let (mut vx, mut vy) = (velocity.x, velocity.y);
         if x > width || x < 0.0 {
-            self.position[0] = x.max(0.0).min(width);
-            self.velocity[0] *= -0.42;
+            x = x.max(0.0).min(width);
+            vx *= -0.42;
         }
         if y > height || y < 0.0 {
-            self.position[1] = y.max(0.0).min(height);
-            self.velocity[1] *= -0.42;
+            y = y.max(0.0).min(height);
+            vy *= -0.42;
         }
-        self.velocity = vec2_add(self.velocity, self.acceleration);
-        self.position = vec2_add(self.position, self.velocity);
-        self.acceleration = [0.0, 0.0];
+        self.body.set_position(Vector2::new(x, y));
+        self.body.set_velocity(Vector2::new(vx, vy));
+        self.body.integrate();
     }
 }
 
@@ -87,7 +78,7 @@ impl PistonApp for App {
     fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
         const MAX_G: Scalar = 0.8;
         const MAX_MOVERS: usize = 12;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let (width, height) = (state.width(), state.height());
         self.movers = (0..MAX_MOVERS)
             .map(|_| {
@@ -107,7 +98,7 @@ impl PistonApp for App {
             for j in 0..self.movers.len() {
                 if i != j {
                     let force = self.movers[j].attract(&self.movers[i]);
-                    self.movers[i].apply_force(force);
+                    self.movers[i].body.apply_force(force);
                 }
             }
             self.movers[i].update(state);
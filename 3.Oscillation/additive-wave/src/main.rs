@@ -36,18 +36,12 @@ struct App {
 
 impl App {
     fn new() -> Self {
-        const MAX_WAVES: usize = 6;
-        let mut rng = thread_rng();
         App {
             node_texture: None,
-            base_hue: rng.gen(),
-            color_offset: rng.gen(),
+            base_hue: 0.0,
+            color_offset: 0.0,
             theta: 0.0,
-            waves: (0..MAX_WAVES)
-                .map(|_| {
-                    Wave::new(rng.gen_range(12.0, 42.0), rng.gen_range(120.0, 240.0))
-                })
-                .collect(),
+            waves: vec![],
             y_values: vec![],
         }
     }
@@ -58,7 +52,14 @@ impl App {
 }
 
 impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, _: &PistonAppState) {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        const MAX_WAVES: usize = 6;
+        let mut rng = state.rng();
+        self.base_hue = rng.gen();
+        self.color_offset = rng.gen();
+        self.waves = (0..MAX_WAVES)
+            .map(|_| Wave::new(rng.gen_range(12.0, 42.0), rng.gen_range(120.0, 240.0)))
+            .collect();
         self.node_texture = Some(
             Texture::from_path(
                 &mut window.factory,
@@ -50,50 +50,47 @@ impl Attractor {
 #[derive(Debug)]
 struct Mover {
     color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
-    angle: Scalar,
-    angular_velocity: Scalar,
-    angular_acceleration: Scalar,
-    mass: Scalar,
+    body: physics::Mover,
 }
 
 impl Mover {
-    fn new(color: Color, x: Scalar, y: Scalar, mass: Scalar) -> Self {
-        let mut rng = thread_rng();
+    fn new(
+        state: &PistonAppState,
+        color: Color,
+        x: Scalar,
+        y: Scalar,
+        mass: Scalar,
+    ) -> Self {
+        let mut rng = state.rng();
         let uniform = Uniform::new_inclusive(-1.0, 1.0);
         Mover {
             color: color,
-            position: [x, y],
-            velocity: [rng.sample(uniform), rng.sample(uniform)],
-            acceleration: [0.0, 0.0],
-            angle: 0.0,
-            angular_velocity: 0.0,
-            angular_acceleration: 0.0,
-            mass: mass,
+            body: physics::Mover::new(x, y, mass)
+                .with_velocity(Vector2::new(rng.sample(uniform), rng.sample(uniform)))
+                .with_max_angular_speed(0.1),
         }
     }
 
     #[inline]
     fn position(&self) -> Vec2d {
-        self.position
+        self.body.position().into()
     }
 
     #[inline]
     fn mass(&self) -> Scalar {
-        self.mass
+        self.body.mass()
     }
 
     fn draw(&self, context: Context, gfx: &mut G2d) {
+        let position = self.body.position();
         let transform = context
             .transform
-            .trans(self.position[0], self.position[1])
-            .rot_rad(self.angle);
+            .trans(position.x, position.y)
+            .rot_rad(self.body.angle());
         Rectangle::new_border(color::BLACK, 1.0)
             .color(self.color)
             .draw(
-                rectangle::centered_square(0.0, 0.0, self.mass * 16.0),
+                rectangle::centered_square(0.0, 0.0, self.mass() * 16.0),
                 &context.draw_state,
                 transform,
                 gfx,
@@ -101,19 +98,13 @@ impl Mover {
     }
 
     fn apply_force(&mut self, force: Vec2d) {
-        self.acceleration =
-            vec2_add(self.acceleration, vec2_scale(force, 1.0 / self.mass));
+        self.body.apply_force(force.into());
     }
 
     fn update(&mut self) {
-        self.velocity = vec2_add(self.velocity, self.acceleration);
-        self.position = vec2_add(self.position, self.velocity);
-        self.angular_acceleration = self.acceleration[0] / 10.0;
-        self.angular_velocity = (self.angular_velocity + self.angular_acceleration)
-            .max(-0.1)
-            .min(0.1);
-        self.angle += self.angular_velocity;
-        self.acceleration = [0.0, 0.0];
+        let torque = self.body.acceleration().x / 10.0;
+        self.body.apply_angular_acceleration(torque);
+        self.body.integrate();
     }
 }
 
@@ -135,7 +126,7 @@ impl App {
 impl PistonApp for App {
     fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
         const MAX_MOVERS: usize = 16;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let (width, height) = (state.width(), state.height());
         self.attractors.push(Attractor::new(
             state.random_color(Some(1.0)),
@@ -145,6 +136,7 @@ impl PistonApp for App {
         self.movers = (0..MAX_MOVERS)
             .map(|_| {
                 Mover::new(
+                    state,
                     state.random_color(Some(2.0 / 3.0)),
                     rng.gen_range(0.0, width),
                     rng.gen_range(0.0, height),
@@ -17,7 +17,7 @@ struct Oscillator {
 
 impl Oscillator {
     fn new(state: &PistonAppState) -> Self {
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         let uniform = Uniform::new_inclusive(-0.05, 0.05);
         Oscillator {
             color: state.random_color(Some(1.0)),
@@ -18,11 +18,10 @@ struct App {
 
 impl App {
     fn new() -> Self {
-        let mut rng = thread_rng();
         App {
-            base_hue: rng.gen(),
+            base_hue: 0.0,
             node_texture: None,
-            color_offset: rng.gen(),
+            color_offset: 0.0,
             start_angle: 0.0,
             velocity: 0.05,
         }
@@ -34,7 +33,10 @@ impl App {
 }
 
 impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, _: &PistonAppState) {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        let mut rng = state.rng();
+        self.base_hue = rng.gen();
+        self.color_offset = rng.gen();
         self.node_texture = Some(
             Texture::from_path(
                 &mut window.factory,
@@ -36,26 +36,21 @@ impl PistonApp for App {
         self.angular_velocity += self.angular_acceleration;
         self.angle += self.angular_velocity;
         window.draw_2d(state.event(), |context, gfx| {
-            clear(color::WHITE, gfx);
-            let transform = context
-                .transform
-                .trans(state.width() / 2.0, state.height() / 2.0)
-                .rot_rad(self.angle);
-            line(color::BLACK, 2.0, [-96.0, 0.0, 96.0, 0.0], transform, gfx);
-            for i in 0..self.baton_colors.len() {
+            let mut canvas = state.canvas(context, gfx);
+            canvas.background(color::WHITE);
+            canvas.translate(state.width() / 2.0, state.height() / 2.0);
+            canvas.rotate(self.angle);
+            canvas.stroke_weight(4.0);
+            canvas.line(-96.0, 0.0, 96.0, 0.0);
+            canvas.stroke_weight(2.0);
+            canvas.ellipse_resolution(16);
+            for (i, &color) in self.baton_colors.iter().enumerate() {
                 let axis = match i {
                     0 => -1.0,
                     _ => 1.0,
                 };
-                Ellipse::new_border(color::BLACK, 1.0)
-                    .resolution(16)
-                    .color(self.baton_colors[i])
-                    .draw(
-                        ellipse::circle(108.0 * axis, 0.0, 12.0),
-                        &context.draw_state,
-                        transform,
-                        gfx,
-                    );
+                canvas.fill(color);
+                canvas.ellipse(108.0 * axis, 0.0, 24.0, 24.0);
             }
         });
     }
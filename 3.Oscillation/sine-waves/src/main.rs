@@ -23,8 +23,14 @@ struct Wave {
 }
 
 impl Wave {
-    fn new(origin: Vec2d, width: Scalar, amplitude: Scalar, period: Scalar) -> Self {
-        let mut rng = thread_rng();
+    fn new(
+        state: &PistonAppState,
+        origin: Vec2d,
+        width: Scalar,
+        amplitude: Scalar,
+        period: Scalar,
+    ) -> Self {
+        let mut rng = state.rng();
         Wave {
             base_hue: rng.gen(),
             color_offset: rng.gen(),
@@ -81,10 +87,7 @@ impl App {
     fn new() -> Self {
         App {
             node_texture: None,
-            waves: vec![
-                Wave::new([50.0, 180.0], 100.0, 20.0, 500.0),
-                Wave::new([300.0, 240.0], 300.0, 40.0, 220.0),
-            ],
+            waves: vec![],
         }
     }
 
@@ -94,7 +97,11 @@ impl App {
 }
 
 impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, _: &PistonAppState) {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        self.waves = vec![
+            Wave::new(state, [50.0, 180.0], 100.0, 20.0, 500.0),
+            Wave::new(state, [300.0, 240.0], 300.0, 40.0, 220.0),
+        ];
         self.node_texture = Some(
             Texture::from_path(
                 &mut window.factory,
@@ -17,10 +17,9 @@ struct App {
 
 impl App {
     fn new() -> Self {
-        let mut rng = thread_rng();
         App {
-            base_hue: rng.gen(),
-            color_offset: rng.gen(),
+            base_hue: 0.0,
+            color_offset: 0.0,
             r: 0.0,
             theta: 0.0,
         }
@@ -28,10 +27,14 @@ impl App {
 }
 
 impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
-        window.draw_2d(state.event(), |_, gfx| {
-            clear(color::BLACK, gfx);
-        });
+    fn settings(&self) -> PistonAppSettings {
+        PistonAppSettings::new().clear_color(color::BLACK)
+    }
+
+    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+        let mut rng = state.rng();
+        self.base_hue = rng.gen();
+        self.color_offset = rng.gen();
     }
 
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
@@ -2,26 +2,21 @@
 //! http://natureofcode.com/
 //!
 //! Particle systems - Torch-ish effect with additive blending.
+//!
+//! A bloom pass makes the flames glow, G toggles it. B cycles through the blend
+//! modes, additive by default.
+//!
+//! Particles live in a fixed-capacity pool, unless `--storage vec` is passed.
+//! `--benchmark <frames>` runs both storages headless and compares their update
+//! times, with the seed it prints or the one given with `--seed <seed>`.
 
 extern crate piston_app;
 
-use piston_app::*;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
+use std::time::Instant;
 
-    pipeline particles {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ADD),
-    }
-}
+use piston_app::particles::{ParticlePool, PooledParticle};
+use piston_app::pipelines::sprites;
+use piston_app::*;
 
 #[derive(Debug)]
 struct Particle {
@@ -33,8 +28,13 @@ struct Particle {
 }
 
 impl Particle {
-    fn new(color: Color, position: Vec2d, velocity: Vec2d) -> Self {
-        let mut rng = thread_rng();
+    fn new(
+        state: &PistonAppState,
+        color: Color,
+        position: Vec2d,
+        velocity: Vec2d,
+    ) -> Self {
+        let mut rng = state.rng();
         Particle {
             color: color,
             position: position,
@@ -52,14 +52,7 @@ impl Particle {
         self.life > 0.0
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let start = vertices.len() as u32;
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
         let life = (self.life / 2.0) as ColorComponent;
         let color = [
             self.color[0] * life,
@@ -67,44 +60,10 @@ impl Particle {
             self.color[2] * life,
             life,
         ];
-        let (x, y) = (self.position[0], self.position[1]);
-        let (w, h) = texture_atlas.texture_offsets(0);
-        let (w, h) = (w * self.life, h * self.life);
-        vertices.extend(&[
-            Vertex {
-                pos: [
-                    state.normalize_x(x + w) as f32,
-                    state.normalize_y(y + h) as f32,
-                ],
-                uv: [1.0, 1.0],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(x - w) as f32,
-                    state.normalize_y(y + h) as f32,
-                ],
-                uv: [0.0, 1.0],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(x - w) as f32,
-                    state.normalize_y(y - h) as f32,
-                ],
-                uv: [0.0, 0.0],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(x + w) as f32,
-                    state.normalize_y(y - h) as f32,
-                ],
-                uv: [1.0, 0.0],
-                color: color,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+        let transform = math::identity()
+            .trans(self.position[0], self.position[1])
+            .zoom(self.life);
+        batch.sprite(texture_atlas, 0, transform, color);
     }
 
     fn update(&mut self) {
@@ -114,125 +73,237 @@ impl Particle {
     }
 }
 
+/// Same look as `Particle`, for pooled particles.
+fn torch_style(particle: &PooledParticle) -> (Color, Scalar) {
+    let life = (particle.life / 2.0) as ColorComponent;
+    let color = [
+        particle.color[0] * life,
+        particle.color[1] * life,
+        particle.color[2] * life,
+        life,
+    ];
+    (color, particle.life)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Storage {
+    Vec,
+    Pool,
+}
+
+impl Storage {
+    fn from_cli() -> Self {
+        match cli_arg("storage").as_deref() {
+            None | Some("pool") => Storage::Pool,
+            Some("vec") => Storage::Vec,
+            Some(storage) => panic!("unknown storage {}, expected vec or pool", storage),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Particles {
+    Vec(Vec<Particle>),
+    Pool(ParticlePool),
+}
+
 #[derive(Debug)]
 struct ParticleSystem {
     color: Color,
     origin: Vec2d,
     particle_velocity: Vec2d,
-    particles: Vec<Particle>,
+    particles: Particles,
 }
 
 impl ParticleSystem {
-    fn new(state: &PistonAppState, x: Scalar, y: Scalar) -> Self {
+    const SPAWN_RATE: usize = 8;
+    const LIFETIME: Scalar = 96.0;
+
+    fn new(state: &PistonAppState, x: Scalar, y: Scalar, storage: Storage) -> Self {
         ParticleSystem {
             color: state.random_color(Some(1.0)),
             origin: [x, y],
-            particle_velocity: [0.0, -thread_rng().gen_range(4.2, 6.66)],
-            particles: vec![],
+            particle_velocity: [0.0, -state.rng().gen_range(4.2, 6.66)],
+            particles: match storage {
+                Storage::Vec => Particles::Vec(vec![]),
+                // Rounding can keep particles alive for one more update.
+                Storage::Pool => Particles::Pool(
+                    ParticlePool::with_capacity(
+                        Self::SPAWN_RATE * (Self::LIFETIME as usize + 1),
+                    )
+                    .acceleration(Vector2::new(0.0, 0.05))
+                    .style(torch_style),
+                ),
+            },
         }
     }
 
     #[inline]
     fn len(&self) -> usize {
-        self.particles.len()
+        match self.particles {
+            Particles::Vec(ref particles) => particles.len(),
+            Particles::Pool(ref pool) => pool.len(),
+        }
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        for particle in &self.particles {
-            particle.extend_vertex_buffer(state, texture_atlas, vertices, indices);
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
+        match self.particles {
+            Particles::Vec(ref particles) => {
+                for particle in particles {
+                    particle.extend_sprite_batch(texture_atlas, batch);
+                }
+            }
+            Particles::Pool(ref pool) => pool.extend_sprite_batch(texture_atlas, batch),
         }
     }
 
-    fn spawn_particle(&mut self) {
-        self.particles.push(Particle::new(
-            self.color,
-            self.origin,
-            self.particle_velocity,
-        ));
+    fn spawn_particle(&mut self, state: &PistonAppState) {
+        match self.particles {
+            Particles::Vec(ref mut particles) => particles.push(Particle::new(
+                state,
+                self.color,
+                self.origin,
+                self.particle_velocity,
+            )),
+            Particles::Pool(ref mut pool) => {
+                let mut rng = state.rng();
+                let velocity = Vector2::new(
+                    rng.sample(StandardNormal) * 0.24 + self.particle_velocity[0],
+                    rng.sample(StandardNormal) * 0.24 + self.particle_velocity[1],
+                );
+                pool.spawn(self.origin.into(), velocity, self.color, Self::LIFETIME);
+            }
+        }
     }
 
-    fn update(&mut self) {
-        for particle in &mut self.particles {
-            particle.update();
+    fn update(&mut self, state: &PistonAppState) {
+        match self.particles {
+            Particles::Vec(ref mut particles) => {
+                for particle in particles.iter_mut() {
+                    particle.update();
+                }
+                particles.retain(|particle| particle.is_alive());
+            }
+            Particles::Pool(ref mut pool) => pool.update(),
         }
-        self.particles.retain(|ref particle| particle.is_alive());
-        for _ in 0..8 {
-            self.spawn_particle();
+        for _ in 0..Self::SPAWN_RATE {
+            self.spawn_particle(state);
         }
     }
 }
 
 #[derive(Debug)]
 struct App {
+    storage: Storage,
+    initial_particle_systems: usize,
     particle_systems: Vec<ParticleSystem>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<particles::Meta>>,
+    blend_mode: BlendMode,
+    post: PostProcessing,
+    batch: SpriteBatch,
+    pipeline: Option<PistonPipeline<sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(storage: Storage, initial_particle_systems: usize) -> Self {
         App {
+            storage: storage,
+            initial_particle_systems: initial_particle_systems,
             particle_systems: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            blend_mode: BlendMode::Additive,
+            post: PostProcessing::new().pass(PostPass::Bloom {
+                threshold: 0.3,
+                radius: 24.0,
+                intensity: 1.5,
+            }),
+            batch: SpriteBatch::with_capacity(4096),
             pipeline: None,
             renderer: None,
         }
     }
 
-    fn pipeline(&self) -> &PistonPipeline<particles::Meta> {
-        self.pipeline.as_ref().unwrap()
-    }
-
     fn spawn_particle_system(&mut self, state: &PistonAppState) {
         self.particle_systems.push(ParticleSystem::new(
             state,
             state.mouse_x(),
             state.height(),
+            self.storage,
         ));
     }
-}
 
-impl PistonApp for App {
-    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
-        const MAX_INITIAL_PARTICLE_SYSTEMS: usize = 3;
-        let mut rng = thread_rng();
-        self.particle_systems = (0..MAX_INITIAL_PARTICLE_SYSTEMS)
+    fn spawn_initial_particle_systems(&mut self, state: &PistonAppState) {
+        let mut rng = state.rng();
+        self.particle_systems = (0..self.initial_particle_systems)
             .map(|_| {
                 ParticleSystem::new(
                     state,
                     rng.gen_range(42.0, state.width() - 42.0),
                     state.height(),
+                    self.storage,
                 )
             })
             .collect();
+    }
+
+    fn particle_count(&self) -> usize {
+        self.particle_systems
+            .iter()
+            .map(|particle_system| particle_system.len())
+            .sum()
+    }
+
+    /// Updates both storages for `frames` frames, with the same seed, and prints
+    /// the average update time per frame.
+    fn benchmark(frames: usize) {
+        const PARTICLE_SYSTEMS: usize = 32;
+        let seed = default_seed();
+        println!("Benchmark seed: {}", seed);
+        for &storage in &[Storage::Vec, Storage::Pool] {
+            let mut app = App::new(storage, PARTICLE_SYSTEMS);
+            let start = Instant::now();
+            App::run_headless(&mut app, PistonAppHeadless::new(frames).seed(seed));
+            let elapsed = start.elapsed();
+            println!(
+                "{:?}: {} frames in {:?} ({:?} per frame) | Particles: {}",
+                storage,
+                frames,
+                elapsed,
+                elapsed / frames.max(1) as u32,
+                app.particle_count()
+            );
+            for particle_system in &app.particle_systems {
+                if let Particles::Pool(ref pool) = particle_system.particles {
+                    println!("    {:?}", pool.stats());
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl PistonApp for App {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        self.spawn_initial_particle_systems(state);
         let (pipeline, renderer) = PistonPipelineBuilder::new()
             .texture_atlas(
                 TextureAtlas::from_path(window, "assets/particle.png").unwrap(),
             )
-            .vertex_shader(include_bytes!("particles_150_core.glslv"))
-            .fragment_shader(include_bytes!("particles_150_core.glslf"))
-            .build(window, particles::new())
+            .blend_mode(self.blend_mode)
+            .build_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn update(&mut self, _: Scalar, state: &PistonAppState) {
+        for particle_system in &mut self.particle_systems {
+            particle_system.update(state);
+        }
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
-            let total_particle_count: usize = self
-                .particle_systems
-                .iter()
-                .map(|particle_system| particle_system.len())
-                .sum();
+            let total_particle_count = self.particle_count();
             println!(
                 "Frame {} | Particle systems: {} | Total particles: {}",
                 state.frame_count(),
@@ -240,38 +311,57 @@ impl PistonApp for App {
                 total_particle_count
             );
         }
+        if state.key_hit(Key::B) {
+            self.blend_mode = self.blend_mode.next();
+            println!("Blend mode: {:?}", self.blend_mode);
+            let (pipeline, _) = PistonPipelineBuilder::new()
+                .blend_mode(self.blend_mode)
+                .build_sprites(window)
+                .unwrap();
+            self.pipeline = Some(pipeline);
+        }
+        if state.key_hit(Key::G) {
+            let enabled = self.post.toggle(0);
+            println!("Bloom: {}", if enabled { "on" } else { "off" });
+        }
         if state.mouse_button_clicked(MouseButton::Left) {
             self.spawn_particle_system(state);
         }
-        self.vertices.clear();
-        self.indices.clear();
+        self.batch.clear();
+        self.batch
+            .set_transform(math::abs_transform(state.width(), state.height()));
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
-        for particle_system in &mut self.particle_systems {
-            particle_system.update();
-            particle_system.extend_vertex_buffer(
-                state,
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+        for particle_system in &self.particle_systems {
+            particle_system.extend_sprite_batch(texture_atlas, &mut self.batch);
         }
+        renderer.bind_target(Some(self.post.begin(window, state).unwrap()));
         renderer.clear(window, color::BLACK);
-        renderer.draw(
+        self.batch.flush(
             window,
-            self.pipeline(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| particles::Data {
+            renderer,
+            self.pipeline.as_ref().unwrap(),
+            |vbuf, out| sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
                 out: out,
             },
         );
+        renderer.bind_target(None);
+        self.post.end(window).unwrap();
+    }
+
+    fn setup_headless(&mut self, state: &PistonAppState) {
+        self.spawn_initial_particle_systems(state);
     }
 }
 
 fn main() {
-    let mut app = App::new();
+    const INITIAL_PARTICLE_SYSTEMS: usize = 3;
+    if let Some(frames) = cli_arg("benchmark") {
+        App::benchmark(frames.parse().expect("--benchmark expects a frame count"));
+        return;
+    }
+    let mut app = App::new(Storage::from_cli(), INITIAL_PARTICLE_SYSTEMS);
     App::run(env!("CARGO_PKG_NAME"), &mut app);
 }
@@ -5,141 +5,18 @@
 
 extern crate piston_app;
 
+use piston_app::particles::{Curve, Particle, ParticleData, ParticleSystem};
+use piston_app::pipelines::sprites;
 use piston_app::*;
 
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline particles {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ALPHA),
-    }
-}
-
-#[derive(Debug)]
-struct ParticleData {
-    color: Color,
-    position: Vec2d,
-    velocity: Vec2d,
-    acceleration: Vec2d,
-    angle: Scalar,
-    scale: Vec2d,
-    life: Scalar,
-}
-
-impl ParticleData {
-    fn new(color: Color, position: Vec2d) -> Self {
-        let mut rng = thread_rng();
-        ParticleData {
-            color: color,
-            position: position,
-            velocity: [rng.gen_range(-1.0, 1.0), rng.gen_range(-2.0, 0.0)],
-            acceleration: [0.0, 0.05],
-            angle: 0.0,
-            scale: [1.0, 1.0],
-            life: 1.0,
-        }
-    }
-
-    #[inline]
-    fn is_alive(&self) -> bool {
-        self.life > 0.0
-    }
-
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        alpha: Scalar,
-        texture_atlas: &TextureAtlas,
-        texture_index: usize,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let start = vertices.len() as u32;
-        let color = [
-            self.color[0],
-            self.color[1],
-            self.color[2],
-            alpha as ColorComponent,
-        ];
-        let (x, y) = (self.position[0], self.position[1]);
-        let (w, h) = texture_atlas.texture_offsets(texture_index);
-        let (u, v, tw, th) = texture_atlas.texture_uv_extents(texture_index);
-        let transform = mat2x3_id()
-            .trans(x, y)
-            .rot_rad(self.angle)
-            .scale(self.scale[0], self.scale[1]);
-        let pos = [
-            math::transform_pos(transform, [w, h]),
-            math::transform_pos(transform, [-w, h]),
-            math::transform_pos(transform, [-w, -h]),
-            math::transform_pos(transform, [w, -h]),
-        ];
-        vertices.extend(&[
-            Vertex {
-                pos: [
-                    state.normalize_x(pos[0][0]) as f32,
-                    state.normalize_y(pos[0][1]) as f32,
-                ],
-                uv: [u + tw, v + th],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(pos[1][0]) as f32,
-                    state.normalize_y(pos[1][1]) as f32,
-                ],
-                uv: [u, v + th],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(pos[2][0]) as f32,
-                    state.normalize_y(pos[2][1]) as f32,
-                ],
-                uv: [u, v],
-                color: color,
-            },
-            Vertex {
-                pos: [
-                    state.normalize_x(pos[3][0]) as f32,
-                    state.normalize_y(pos[3][1]) as f32,
-                ],
-                uv: [u + tw, v],
-                color: color,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
-    }
-
-    fn update(&mut self, _: &PistonAppState) {
-        self.velocity = vec2_add(self.velocity, self.acceleration);
-        self.position = vec2_add(self.position, self.velocity);
-        self.life -= 1.0 / 128.0;
-    }
-}
-
-trait Particle: std::fmt::Debug {
-    fn is_alive(&self) -> bool;
-
-    fn extend_vertex_buffer(
-        &self,
-        _state: &PistonAppState,
-        _texture_atlas: &TextureAtlas,
-        _vertices: &mut Vec<Vertex>,
-        _indices: &mut Vec<u32>,
-    ) {
-    }
-
-    fn update(&mut self, _state: &PistonAppState) {}
-}
+const DISC_SIZE: Curve<Vec2d> =
+    Curve::from_static(&[(0.0, [1.42, 1.42]), (1.0, [0.42, 0.42])]);
+const QUAD_SIZE: Curve<Vec2d> =
+    Curve::from_static(&[(0.0, [1.0, 1.0]), (1.0, [2.4, 1.0])]);
+const TRIANGLE_SIZE: Curve<Vec2d> =
+    Curve::from_static(&[(0.0, [1.0, 1.0]), (1.0, [3.6, 3.6])]);
+const TRIANGLE_ALPHA: Curve<Scalar> =
+    Curve::from_static(&[(0.0, 0.0), (0.58, 1.0), (1.0, 0.0)]);
 
 #[derive(Debug)]
 struct DiscParticle {
@@ -147,40 +24,27 @@ struct DiscParticle {
 }
 
 impl DiscParticle {
-    fn new(color: Color, position: Vec2d) -> Self {
-        let mut particle = ParticleData::new(color, position);
-        particle.scale = [1.42, 1.42];
+    fn new(mut particle: ParticleData) -> Self {
+        particle.scale = DISC_SIZE.sample(0.0);
+        particle.texture_index = 0;
         DiscParticle { particle: particle }
     }
 }
 
 impl Particle for DiscParticle {
     #[inline]
-    fn is_alive(&self) -> bool {
-        self.particle.is_alive()
+    fn data(&self) -> &ParticleData {
+        &self.particle
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        self.particle.extend_vertex_buffer(
-            state,
-            self.particle.life,
-            texture_atlas,
-            0,
-            vertices,
-            indices,
-        );
+    #[inline]
+    fn data_mut(&mut self) -> &mut ParticleData {
+        &mut self.particle
     }
 
-    fn update(&mut self, state: &PistonAppState) {
-        self.particle.update(state);
-        let scale = state.map_range(self.particle.life, 0.0, 1.0, 0.42, 1.42);
-        self.particle.scale = [scale, scale];
+    fn update(&mut self, _: &PistonAppState) {
+        self.particle.update();
+        self.particle.scale = DISC_SIZE.sample(self.particle.age());
     }
 }
 
@@ -190,46 +54,34 @@ struct QuadParticle {
 }
 
 impl QuadParticle {
-    fn new(color: Color, position: Vec2d) -> Self {
-        QuadParticle {
-            particle: ParticleData::new(color, position),
-        }
+    fn new(mut particle: ParticleData) -> Self {
+        particle.texture_index = 1;
+        QuadParticle { particle: particle }
     }
 }
 
 impl Particle for QuadParticle {
     #[inline]
-    fn is_alive(&self) -> bool {
-        self.particle.is_alive()
+    fn data(&self) -> &ParticleData {
+        &self.particle
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        self.particle.extend_vertex_buffer(
-            state,
-            self.particle.life,
-            texture_atlas,
-            1,
-            vertices,
-            indices,
-        );
+    #[inline]
+    fn data_mut(&mut self) -> &mut ParticleData {
+        &mut self.particle
     }
 
     fn update(&mut self, state: &PistonAppState) {
-        self.particle.update(state);
-        self.particle.angle = state.map_range(
-            self.particle.position[0],
+        self.particle.update();
+        let x = self.particle.position().x;
+        self.particle.body.set_angle(state.map_range(
+            x,
             0.0,
             state.width(),
             0.0,
             consts::PI * 4.0,
-        );
-        self.particle.scale[0] = state.map_range(self.particle.life, 0.0, 1.0, 2.4, 1.0);
+        ));
+        self.particle.scale = QUAD_SIZE.sample(self.particle.age());
     }
 }
 
@@ -239,107 +91,53 @@ struct TriangleParticle {
 }
 
 impl TriangleParticle {
-    fn new(color: Color, position: Vec2d) -> Self {
-        TriangleParticle {
-            particle: ParticleData::new(color, position),
-        }
+    fn new(mut particle: ParticleData) -> Self {
+        particle.texture_index = 2;
+        particle.alpha = TRIANGLE_ALPHA.sample(0.0);
+        TriangleParticle { particle: particle }
     }
 }
 
 impl Particle for TriangleParticle {
     #[inline]
-    fn is_alive(&self) -> bool {
-        self.particle.is_alive()
+    fn data(&self) -> &ParticleData {
+        &self.particle
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let life = self.particle.life;
-        let alpha = if life < 0.42 {
-            state.map_range(life, 0.0, 0.42, 0.0, 1.0)
-        } else {
-            1.0 - state.map_range(life, 0.42, 1.0, 0.0, 1.0)
-        };
-        self.particle
-            .extend_vertex_buffer(state, alpha, texture_atlas, 2, vertices, indices);
+    #[inline]
+    fn data_mut(&mut self) -> &mut ParticleData {
+        &mut self.particle
     }
 
     fn update(&mut self, state: &PistonAppState) {
-        self.particle.update(state);
-        let (x, width) = (self.particle.position[0], state.width());
-        let scale = state.map_range(self.particle.life, 0.0, 1.0, 3.6, 1.0);
-        self.particle.angle = -state.map_range(x, 0.0, width, 0.0, consts::PI * 8.0);
-        self.particle.scale = [scale, scale];
+        self.particle.update();
+        let (x, width) = (self.particle.position().x, state.width());
+        let age = self.particle.age();
+        self.particle.body.set_angle(-state.map_range(
+            x,
+            0.0,
+            width,
+            0.0,
+            consts::PI * 8.0,
+        ));
+        self.particle.scale = TRIANGLE_SIZE.sample(age);
+        self.particle.alpha = TRIANGLE_ALPHA.sample(age);
     }
 }
 
-#[derive(Debug)]
-struct ParticleSystem {
-    base_hue: Scalar,
-    color_offset: Scalar,
-    origin: Vec2d,
-    particles: Vec<Box<dyn Particle>>,
-}
-
-impl ParticleSystem {
-    fn new(x: Scalar, y: Scalar) -> Self {
-        let mut rng = thread_rng();
-        ParticleSystem {
-            base_hue: rng.gen(),
-            color_offset: rng.gen(),
-            origin: [x, y],
-            particles: vec![],
-        }
-    }
-
-    #[inline]
-    fn len(&self) -> usize {
-        self.particles.len()
-    }
-
-    fn extend_vertex_buffer(
-        &self,
-        state: &PistonAppState,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        for particle in &self.particles {
-            particle.extend_vertex_buffer(state, texture_atlas, vertices, indices);
-        }
-    }
-
-    fn spawn_particle(&mut self, state: &PistonAppState) {
-        self.color_offset += 0.00042;
-        let color = state.noise_color(self.base_hue, self.color_offset, Some(1.0));
-        self.particles
-            .push(match thread_rng().gen::<Scalar>() * 3.0 {
-                r if r < 1.0 => Box::new(DiscParticle::new(color, self.origin)),
-                r if r < 2.0 => Box::new(QuadParticle::new(color, self.origin)),
-                _ => Box::new(TriangleParticle::new(color, self.origin)),
-            });
-    }
-
-    fn update(&mut self, state: &PistonAppState) {
-        for particle in &mut self.particles {
-            particle.update(state);
-        }
-        self.particles.retain(|ref particle| particle.is_alive());
-        self.spawn_particle(state);
+fn spawn_particle(state: &PistonAppState, particle: ParticleData) -> Box<dyn Particle> {
+    match state.rng().gen::<Scalar>() * 3.0 {
+        r if r < 1.0 => Box::new(DiscParticle::new(particle)),
+        r if r < 2.0 => Box::new(QuadParticle::new(particle)),
+        _ => Box::new(TriangleParticle::new(particle)),
     }
 }
 
 #[derive(Debug)]
 struct App {
-    particle_systems: Vec<ParticleSystem>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<particles::Meta>>,
+    particle_systems: Vec<ParticleSystem<Box<dyn Particle>>>,
+    batch: SpriteBatch,
+    pipeline: Option<PistonPipeline<sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
@@ -347,30 +145,38 @@ impl App {
     fn new() -> Self {
         App {
             particle_systems: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            batch: SpriteBatch::with_capacity(4096),
             pipeline: None,
             renderer: None,
         }
     }
 
-    fn pipeline(&self) -> &PistonPipeline<particles::Meta> {
-        self.pipeline.as_ref().unwrap()
+    fn particle_system(
+        state: &PistonAppState,
+        x: Scalar,
+        y: Scalar,
+    ) -> ParticleSystem<Box<dyn Particle>> {
+        ParticleSystem::new(state, x, y, spawn_particle)
+            .force(physics::Gravity::new(0.0, 0.05))
     }
 
     fn spawn_particle_system(&mut self, state: &PistonAppState) {
-        self.particle_systems
-            .push(ParticleSystem::new(state.mouse_x(), state.mouse_y()));
+        self.particle_systems.push(Self::particle_system(
+            state,
+            state.mouse_x(),
+            state.mouse_y(),
+        ));
     }
 }
 
 impl PistonApp for App {
     fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         const MAX_INITIAL_PARTICLE_SYSTEMS: usize = 9;
-        let mut rng = thread_rng();
+        let mut rng = state.rng();
         self.particle_systems = (0..MAX_INITIAL_PARTICLE_SYSTEMS)
             .map(|_| {
-                ParticleSystem::new(
+                Self::particle_system(
+                    state,
                     rng.gen_range(42.0, state.width() - 42.0),
                     rng.gen_range(42.0, state.height() - 42.0),
                 )
@@ -378,12 +184,14 @@ impl PistonApp for App {
             .collect();
         let (pipeline, renderer) = PistonPipelineBuilder::new()
             .texture_atlas(
-                TextureAtlas::from_paths(window, "assets/particles.png", "assets/particles.atlas")
-                    .unwrap(),
+                TextureAtlas::from_paths(
+                    window,
+                    "assets/particles.png",
+                    "assets/particles.atlas",
+                )
+                .unwrap(),
             )
-            .vertex_shader(include_bytes!("particles_150_core.glslv"))
-            .fragment_shader(include_bytes!("particles_150_core.glslf"))
-            .build(window, particles::new())
+            .build_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
@@ -406,26 +214,21 @@ impl PistonApp for App {
         if state.mouse_button_clicked(MouseButton::Left) {
             self.spawn_particle_system(state);
         }
-        self.vertices.clear();
-        self.indices.clear();
+        self.batch.clear();
+        self.batch
+            .set_transform(math::abs_transform(state.width(), state.height()));
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
         for particle_system in &mut self.particle_systems {
             particle_system.update(state);
-            particle_system.extend_vertex_buffer(
-                state,
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            particle_system.extend_sprite_batch(texture_atlas, &mut self.batch);
         }
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
-            self.pipeline(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| particles::Data {
+            renderer,
+            self.pipeline.as_ref().unwrap(),
+            |vbuf, out| sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
                 out: out,
@@ -0,0 +1,141 @@
+//! Nature of code - Following the book... in Rust, with Piston!
+//! http://natureofcode.com/
+//!
+//! Particle systems - Particle effect preset viewer.
+//!
+//! Plays the JSON preset given with `--preset <path>`
+//! (`assets/presets/fountain.json` by default), and reloads it whenever the file
+//! is saved. Click to move the emitter, press R to restart it.
+
+extern crate piston_app;
+
+use piston_app::particles::{ParticleData, ParticleEffectFile, ParticleSystem};
+use piston_app::pipelines::sprites;
+use piston_app::*;
+
+const DEFAULT_PRESET: &str = "assets/presets/fountain.json";
+
+#[derive(Debug)]
+struct App {
+    preset: Option<ParticleEffectFile>,
+    particle_system: Option<ParticleSystem<ParticleData>>,
+    batch: SpriteBatch,
+    pipeline: Option<PistonPipeline<sprites::Meta>>,
+    renderer: Option<PistonRenderer>,
+}
+
+impl App {
+    fn new() -> Self {
+        App {
+            preset: None,
+            particle_system: None,
+            batch: SpriteBatch::with_capacity(4096),
+            pipeline: None,
+            renderer: None,
+        }
+    }
+
+    fn preset(&self) -> &ParticleEffectFile {
+        self.preset.as_ref().unwrap()
+    }
+
+    fn particle_system_mut(&mut self) -> &mut ParticleSystem<ParticleData> {
+        self.particle_system.as_mut().unwrap()
+    }
+
+    fn load_effect(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        let origin = match self.particle_system {
+            Some(ref particle_system) => particle_system.origin(),
+            None => Vector2::new(state.width() / 2.0, state.height() / 2.0),
+        };
+        let effect = self.preset().effect();
+        let particle_system = effect.particle_system(state, origin.x, origin.y);
+        let texture_atlas = effect.texture_atlas(window);
+        let pipeline = texture_atlas.and_then(|texture_atlas| {
+            PistonPipelineBuilder::new()
+                .texture_atlas(texture_atlas)
+                .blend_mode(effect.blend)
+                .build_sprites(window)
+        });
+        match pipeline {
+            Ok((pipeline, renderer)) => {
+                self.particle_system = Some(particle_system);
+                self.pipeline = Some(pipeline);
+                self.renderer = Some(renderer);
+            }
+            Err(error) => eprintln!("{}: {}", self.preset().path().display(), error),
+        }
+    }
+
+    fn reload_effect(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        match self.preset.as_mut().unwrap().reload_if_modified() {
+            Ok(true) => {
+                println!("Reloaded {}", self.preset().path().display());
+                self.load_effect(window, state);
+            }
+            Ok(false) => {}
+            Err(error) => eprintln!("{}: {}", self.preset().path().display(), error),
+        }
+    }
+}
+
+impl PistonApp for App {
+    fn setup(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        let path = cli_arg("preset").unwrap_or_else(|| DEFAULT_PRESET.to_owned());
+        self.preset = Some(ParticleEffectFile::open(path).unwrap());
+        self.load_effect(window, state);
+        if self.particle_system.is_none() {
+            panic!("cannot load {}", self.preset().path().display());
+        }
+    }
+
+    fn update(&mut self, _: Scalar, state: &PistonAppState) {
+        self.particle_system_mut().update(state);
+    }
+
+    fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        const RELOAD_INTERVAL: usize = 30;
+        if state.frame_count().is_multiple_of(RELOAD_INTERVAL) {
+            self.reload_effect(window, state);
+        }
+        let particle_system = self.particle_system_mut();
+        if state.mouse_button_clicked(MouseButton::Left) {
+            particle_system.set_origin(Vector2::new(state.mouse_x(), state.mouse_y()));
+        }
+        if state.key_hit(Key::R) {
+            particle_system.emitter_mut().restart();
+        }
+        if state.key_hit(Key::D) {
+            println!(
+                "Frame {} | Particles: {}",
+                state.frame_count(),
+                self.particle_system.as_ref().unwrap().len()
+            );
+        }
+        self.batch.clear();
+        self.batch
+            .set_transform(math::abs_transform(state.width(), state.height()));
+        let renderer = self.renderer.as_ref().unwrap();
+        let texture_atlas = renderer.texture_atlas().unwrap();
+        self.particle_system
+            .as_ref()
+            .unwrap()
+            .extend_sprite_batch(texture_atlas, &mut self.batch);
+        renderer.clear(window, color::BLACK);
+        self.batch.flush(
+            window,
+            renderer,
+            self.pipeline.as_ref().unwrap(),
+            |vbuf, out| sprites::Data {
+                vbuf: vbuf,
+                sampler: texture_atlas.texture_view_sampler(),
+                out: out,
+            },
+        );
+    }
+}
+
+fn main() {
+    let mut app = App::new();
+    App::run(env!("CARGO_PKG_NAME"), &mut app);
+}
@@ -17,8 +17,8 @@ struct Particle {
 }
 
 impl Particle {
-    fn new(color: Color, x: Scalar, y: Scalar) -> Self {
-        let mut rng = thread_rng();
+    fn new(state: &PistonAppState, color: Color, x: Scalar, y: Scalar) -> Self {
+        let mut rng = state.rng();
         Particle {
             color: color,
             position: [x, y],
@@ -91,6 +91,7 @@ impl App {
 
     fn spawn_particle(&mut self, state: &PistonAppState) {
         self.particle = Some(Particle::new(
+            state,
             state.random_color(Some(1.0)),
             state.width() / 2.0,
             42.0,
@@ -6,30 +6,11 @@
 extern crate piston_app;
 extern crate wrapped2d;
 
+use piston_app::box2d::{body_transform, DebugDraw, PhysicsWorld, BODY_SKIN_DEPTH};
+use piston_app::pipelines::world_sprites;
 use piston_app::*;
 use wrapped2d::b2;
 
-const BODY_SKIN_DEPTH: f32 = 0.02;
-const PIXELS_PER_METER: f32 = 32.0;
-type World = b2::World<wrapped2d::user_data::NoUserData>;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline world {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        transform: Global<[f32; 4]> = "transform",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ALPHA),
-    }
-}
-
 #[derive(Debug)]
 struct Boundary {
     body_handle: b2::BodyHandle,
@@ -40,11 +21,20 @@ struct Boundary {
 }
 
 impl Boundary {
-    fn new(world: &mut World, x: f32, y: f32, width: f32, height: f32) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
         let mut body = world.body_mut(handle);
         let (half_width, half_height) = (width / 2.0, height / 2.0);
         body.create_fast_fixture(
@@ -60,42 +50,18 @@ impl Boundary {
         }
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let start = vertices.len() as u32;
-        let (x, y) = (self.x, self.y);
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
         let (w, h) = (
             self.half_width + BODY_SKIN_DEPTH,
             self.half_height + BODY_SKIN_DEPTH,
         );
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
-        vertices.extend(&[
-            Vertex {
-                pos: [x + w, y + h],
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x - w, y + h],
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x - w, y - h],
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x + w, y - h],
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+        batch.rect(
+            math::identity().trans(self.x.into(), self.y.into()),
+            rectangle::centered([0.0, 0.0, w.into(), h.into()]),
+            [u, v, tw, th],
+            color::BLACK,
+        );
     }
 }
 
@@ -111,12 +77,15 @@ const BODY_HALF_HEIGHT: f32 = 0.5;
 const BODY_RADIUS: f32 = 0.25;
 
 impl Entity {
-    fn new(world: &mut World, x: f32, y: f32, color: Color) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(world: &mut PhysicsWorld<usize>, x: f32, y: f32, color: Color) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
         let mut body = world.body_mut(handle);
         body.create_fast_fixture(
             &b2::PolygonShape::new_box(BODY_HALF_WIDTH, BODY_HALF_HEIGHT),
@@ -138,181 +107,56 @@ impl Entity {
         }
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.084;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let w = BODY_HALF_WIDTH + BODY_SKIN_DEPTH;
         let h = BODY_HALF_HEIGHT + BODY_SKIN_DEPTH;
         let r = BODY_RADIUS + BODY_SKIN_DEPTH;
         let (iw, ih, ir) = (w - THICKNESS, h - THICKNESS, r - THICKNESS);
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
         let (ru, rv, rw, rh) = texture_atlas.texture_uv_extents(1);
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: h }).as_array(),
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: h }).as_array(),
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: -h }).as_array(),
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: -h }).as_array(),
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: ih }).as_array(),
-                uv: [u + tw, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: ih }).as_array(),
-                uv: [u, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: -ih }).as_array(),
-                uv: [u, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: -ih }).as_array(),
-                uv: [u + tw, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: r,
-                        y: r + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru + rw, rv],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -r,
-                        y: r + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru, rv],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -r,
-                        y: -r + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru, rv + rh],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: r,
-                        y: -r + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru + rw, rv + rh],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: ir,
-                        y: ir + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru + rw, rv],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -ir,
-                        y: ir + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru, rv],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -ir,
-                        y: -ir + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru, rv + rh],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: ir,
-                        y: -ir + BODY_DELTA,
-                    })
-                .as_array(),
-                uv: [ru + rw, rv + rh],
-                color: self.color,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-            start + 8,
-            start + 9,
-            start + 10,
-            start + 10,
-            start + 11,
-            start + 8,
-            start + 12,
-            start + 13,
-            start + 14,
-            start + 14,
-            start + 15,
-            start + 12,
-        ]);
+        let (uv, circle_uv) = ([u, v, tw, th], [ru, rv, rw, rh]);
+        let delta = BODY_DELTA.into();
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, w.into(), h.into()]),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, iw.into(), ih.into()]),
+            uv,
+            self.color,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, delta, r.into()),
+            circle_uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, delta, ir.into()),
+            circle_uv,
+            self.color,
+        );
     }
 }
 
 struct App {
-    world: World,
+    world: PhysicsWorld<usize>,
     boundaries: Vec<Boundary>,
     entities: Vec<Entity>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<world::Meta>>,
+    batch: SpriteBatch,
+    debug_draw: DebugDraw,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
@@ -320,11 +164,11 @@ impl App {
     fn new() -> Self {
         const GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: -10.0 };
         App {
-            world: World::new(&GRAVITY),
+            world: PhysicsWorld::new(&GRAVITY),
             boundaries: vec![],
             entities: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            batch: SpriteBatch::with_capacity(4096).flip_v(true),
+            debug_draw: DebugDraw::new(),
             pipeline: None,
             renderer: None,
         }
@@ -333,12 +177,12 @@ impl App {
     fn dump_data(&self, state: &PistonAppState) {
         let boundary_count = self.boundaries.len();
         let entity_count = self.entities.len();
-        let vertex_count = self.vertices.len();
-        let index_count = self.indices.len();
+        let vertex_count = self.batch.vertices().len();
+        let index_count = self.batch.indices().len();
         let memory = (boundary_count * std::mem::size_of::<Boundary>()
             + entity_count * std::mem::size_of::<Entity>()
-            + vertex_count * std::mem::size_of::<Vertex>()
-            + index_count * std::mem::size_of::<u32>()) as f32
+            + std::mem::size_of_val(self.batch.vertices())
+            + std::mem::size_of_val(self.batch.indices())) as f32
             / 1024.0;
         println!(
             "Frame {} | Entities: {} | Vertices: {} | Indices: {} | Memory: {:.2} KB",
@@ -352,14 +196,17 @@ impl App {
 
     fn setup_world(&mut self, state: &PistonAppState) {
         const MAX_BOUNDARIES: usize = 5;
-        let ground = self.world.create_body(&b2::BodyDef {
-            position: b2::Vec2 { x: 0.0, y: -10.0 },
-            ..b2::BodyDef::new()
-        });
-        let width = state.width() as f32;
-        let shape = b2::PolygonShape::new_box(width * 4.2 / PIXELS_PER_METER, 10.0);
+        let ground = self.world.create_body(
+            &b2::BodyDef {
+                position: b2::Vec2 { x: 0.0, y: -10.0 },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
+        let width = self.world.meters(state.width());
+        let shape = b2::PolygonShape::new_box(width * 4.2, 10.0);
         self.world.body_mut(ground).create_fast_fixture(&shape, 0.0);
-        let boundary_width = width / 2.0 / PIXELS_PER_METER - 2.0;
+        let boundary_width = width / 2.0 - 2.0;
         self.boundaries = (0..MAX_BOUNDARIES)
             .map(|i| {
                 let side = if i % 2 == 0 { -1.0 } else { 1.0 };
@@ -375,8 +222,7 @@ impl App {
     }
 
     fn spawn_entity(&mut self, state: &PistonAppState) {
-        let x = (state.mouse_x() - state.width() / 2.0) as f32 / PIXELS_PER_METER;
-        let y = (state.height() - state.mouse_y()) as f32 / PIXELS_PER_METER;
+        let b2::Vec2 { x, y } = self.world.mouse_position(state);
         let entity = Entity::new(&mut self.world, x, y, state.random_color(Some(1.0)));
         self.entities.push(entity);
     }
@@ -394,62 +240,49 @@ impl PistonApp for App {
                 )
                 .unwrap(),
             )
-            .vertex_shader(include_bytes!("world_150_core.glslv"))
-            .fragment_shader(include_bytes!("world_150_core.glslf"))
-            .build(window, world::new())
+            .build_world_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        if state.mouse_button_pressed(MouseButton::Left) {
+            self.spawn_entity(state);
+        }
+        self.world.step(dt, state);
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
             self.dump_data(state);
         }
-        if state.mouse_button_pressed(MouseButton::Left) {
-            self.spawn_entity(state);
-        }
-        self.vertices.clear();
-        self.indices.clear();
-        self.world.step(1.0 / 60.0, 8, 3);
-        self.world.clear_forces();
+        self.debug_draw.handle_input(state);
+        self.batch.clear();
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
         for entity in &self.entities {
-            entity.extend_vertex_buffer(
-                &self.world,
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            entity.extend_sprite_batch(&self.world, texture_atlas, &mut self.batch);
         }
         for boundary in &self.boundaries {
-            boundary.extend_vertex_buffer(
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            boundary.extend_sprite_batch(texture_atlas, &mut self.batch);
         }
-        let half_width = state.width() as f32 / 2.0;
-        let half_height = state.height() as f32 / 2.0;
+        let transform = self.world.sprite_transform(state);
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
+            renderer,
             self.pipeline.as_ref().unwrap(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| world::Data {
+            |vbuf, out| world_sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
-                transform: [
-                    0.0,
-                    -1.0,
-                    PIXELS_PER_METER / half_width,
-                    PIXELS_PER_METER / half_height,
-                ],
+                transform: transform,
                 out: out,
             },
         );
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
     }
 }
 
@@ -6,30 +6,13 @@
 extern crate piston_app;
 extern crate wrapped2d;
 
+use piston_app::box2d::{
+    body_transform, DebugDraw, MouseDrag, PhysicsWorld, BODY_SKIN_DEPTH,
+};
+use piston_app::pipelines::world_sprites;
 use piston_app::*;
 use wrapped2d::b2;
 
-const BODY_SKIN_DEPTH: f32 = 0.02;
-const PIXELS_PER_METER: f32 = 32.0;
-type World = b2::World<wrapped2d::user_data::NoUserData>;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline world {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        transform: Global<[f32; 4]> = "transform",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ALPHA),
-    }
-}
-
 #[derive(Debug)]
 struct Boundary {
     body_handle: b2::BodyHandle,
@@ -40,11 +23,20 @@ struct Boundary {
 }
 
 impl Boundary {
-    fn new(world: &mut World, x: f32, y: f32, width: f32, height: f32) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
         let mut body = world.body_mut(handle);
         let (half_width, half_height) = (width / 2.0, height / 2.0);
         body.create_fast_fixture(
@@ -60,42 +52,18 @@ impl Boundary {
         }
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let start = vertices.len() as u32;
-        let (x, y) = (self.x, self.y);
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
         let (w, h) = (
             self.half_width + BODY_SKIN_DEPTH,
             self.half_height + BODY_SKIN_DEPTH,
         );
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
-        vertices.extend(&[
-            Vertex {
-                pos: [x + w, y + h],
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x - w, y + h],
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x - w, y - h],
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: [x + w, y - h],
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+        batch.rect(
+            math::identity().trans(self.x.into(), self.y.into()),
+            rectangle::centered([0.0, 0.0, w.into(), h.into()]),
+            [u, v, tw, th],
+            color::BLACK,
+        );
     }
 }
 
@@ -109,18 +77,21 @@ struct Brick {
 
 impl Brick {
     fn new(
-        world: &mut World,
+        world: &mut PhysicsWorld<usize>,
         x: f32,
         y: f32,
         width: f32,
         height: f32,
         color: Color,
     ) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
         let mut body = world.body_mut(handle);
         let (half_width, half_height) = (width / 2.0, height / 2.0);
         body.create_fast_fixture(
@@ -139,79 +110,33 @@ impl Brick {
         self.body_handle
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.084;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let (w, h) = (
             self.half_width + BODY_SKIN_DEPTH,
             self.half_height + BODY_SKIN_DEPTH,
         );
         let (iw, ih) = (w - THICKNESS, h - THICKNESS);
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: h }).as_array(),
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: h }).as_array(),
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: -h }).as_array(),
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: -h }).as_array(),
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: ih }).as_array(),
-                uv: [u + tw, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: ih }).as_array(),
-                uv: [u, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: -ih }).as_array(),
-                uv: [u, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: -ih }).as_array(),
-                uv: [u + tw, v + th],
-                color: self.color,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-        ]);
+        let uv = [u, v, tw, th];
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, w.into(), h.into()]),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, iw.into(), ih.into()]),
+            uv,
+            self.color,
+        );
     }
 }
 
@@ -223,12 +148,21 @@ struct Token {
 }
 
 impl Token {
-    fn new(world: &mut World, x: f32, y: f32, radius: f32, color: Color) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        x: f32,
+        y: f32,
+        radius: f32,
+        color: Color,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
         let mut body = world.body_mut(handle);
         let mut shape = b2::CircleShape::new();
         shape.set_radius(radius);
@@ -251,162 +185,37 @@ impl Token {
         self.body_handle
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.042;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let radius = self.radius + BODY_SKIN_DEPTH;
         let inner_radius = self.radius - THICKNESS * 2.0;
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(1);
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [u, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [u, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-            start + 8,
-            start + 9,
-            start + 10,
-            start + 10,
-            start + 11,
-            start + 8,
-        ]);
+        let (uv, center) = ([u, v, tw, th], [u + tw / 2.0, v + th / 2.0, 0.0, 0.0]);
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, radius.into()),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, inner_radius.into()),
+            uv,
+            self.color,
+        );
+        let (thickness, radius) = (THICKNESS as Scalar, self.radius as Scalar);
+        batch.rect(
+            transform,
+            [-thickness, -thickness, 2.0 * thickness, radius + thickness],
+            center,
+            color::BLACK,
+        );
     }
 }
 
@@ -423,7 +232,7 @@ const TOKEN_DELTA: f32 = 1.25;
 const TOKEN_RADIUS: f32 = 0.32;
 
 impl Entity {
-    fn new(world: &mut World, x: f32, y: f32, color: Color) -> Self {
+    fn new(world: &mut PhysicsWorld<usize>, x: f32, y: f32, color: Color) -> Self {
         let brick = Brick::new(world, x, y, BRICK_WIDTH, BRICK_HEIGHT, color);
         let tokens = [
             Token::new(world, x, y + TOKEN_DELTA, TOKEN_RADIUS, color),
@@ -457,27 +266,24 @@ impl Entity {
         }
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
-        self.render_joints(world, texture_atlas, vertices, indices);
-        self.brick
-            .extend_vertex_buffer(world, texture_atlas, vertices, indices);
+        self.render_joints(world, texture_atlas, batch);
+        self.brick.extend_sprite_batch(world, texture_atlas, batch);
         for token in &self.tokens {
-            token.extend_vertex_buffer(world, texture_atlas, vertices, indices);
+            token.extend_sprite_batch(world, texture_atlas, batch);
         }
     }
 
     fn render_joints(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.042;
         let brick_body = world.body(self.brick.body_handle());
@@ -488,52 +294,34 @@ impl Entity {
         ];
         let token_transforms = [token_bodies[0].transform(), token_bodies[1].transform()];
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
-        let uv = [u + tw / 2.0, v + th / 2.0];
+        let uv = [u + tw / 2.0, v + th / 2.0, 0.0, 0.0];
         let color = color::grey(0.25);
-        for i in 0..token_transforms.len() {
-            let start = vertices.len() as u32;
+        for (i, token_transform) in token_transforms.iter().enumerate() {
             let first = brick_transform
                 * b2::Vec2 {
                     x: 0.0,
                     y: if i == 0 { ANCHOR_DELTA } else { -ANCHOR_DELTA },
                 };
-            let second = token_transforms[i] * b2::Vec2 { x: 0.0, y: 0.0 };
-            let normal = (second - first).sqew();
-            let delta = normal * THICKNESS / normal.norm();
-            vertices.extend(&[
-                Vertex {
-                    pos: *(second + delta).as_array(),
-                    uv: uv,
-                    color: color,
-                },
-                Vertex {
-                    pos: *(first + delta).as_array(),
-                    uv: uv,
-                    color: color,
-                },
-                Vertex {
-                    pos: *(first - delta).as_array(),
-                    uv: uv,
-                    color: color,
-                },
-                Vertex {
-                    pos: *(second - delta).as_array(),
-                    uv: uv,
-                    color: color,
-                },
-            ]);
-            indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+            let second = *token_transform * b2::Vec2 { x: 0.0, y: 0.0 };
+            batch.line(
+                [first.x.into(), first.y.into()],
+                [second.x.into(), second.y.into()],
+                THICKNESS.into(),
+                uv,
+                color,
+            );
         }
     }
 }
 
 struct App {
-    world: World,
+    world: PhysicsWorld<usize>,
+    drag: MouseDrag<usize>,
     boundaries: Vec<Boundary>,
     entities: Vec<Entity>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<world::Meta>>,
+    batch: SpriteBatch,
+    debug_draw: DebugDraw,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
@@ -541,11 +329,12 @@ impl App {
     fn new() -> Self {
         const GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: -10.0 };
         App {
-            world: World::new(&GRAVITY),
+            world: PhysicsWorld::new(&GRAVITY),
+            drag: MouseDrag::new(),
             boundaries: vec![],
             entities: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            batch: SpriteBatch::with_capacity(4096).flip_v(true),
+            debug_draw: DebugDraw::new(),
             pipeline: None,
             renderer: None,
         }
@@ -554,33 +343,40 @@ impl App {
     fn dump_data(&self, state: &PistonAppState) {
         let boundary_count = self.boundaries.len();
         let entity_count = self.entities.len();
-        let vertex_count = self.vertices.len();
-        let index_count = self.indices.len();
+        let vertex_count = self.batch.vertices().len();
+        let index_count = self.batch.indices().len();
         let memory = (boundary_count * std::mem::size_of::<Boundary>()
             + entity_count * std::mem::size_of::<Entity>()
-            + vertex_count * std::mem::size_of::<Vertex>()
-            + index_count * std::mem::size_of::<u32>()) as f32
+            + std::mem::size_of_val(self.batch.vertices())
+            + std::mem::size_of_val(self.batch.indices())) as f32
             / 1024.0;
+        let stream = self.batch.stream();
         println!(
-            "Frame {} | Entities: {} | Vertices: {} | Indices: {} | Memory: {:.2} KB",
+            "Frame {} | Entities: {} | Vertices: {} | Indices: {} | Memory: {:.2} KB \
+             | GPU buffers: {:.2} KB ({} allocations)",
             state.frame_count(),
             entity_count,
             vertex_count,
             index_count,
-            memory
+            memory,
+            stream.memory_size() as f32 / 1024.0,
+            stream.allocations()
         );
     }
 
     fn setup_world(&mut self, state: &PistonAppState) {
         const MAX_BOUNDARIES: usize = 3;
-        let ground = self.world.create_body(&b2::BodyDef {
-            position: b2::Vec2 { x: 0.0, y: -10.0 },
-            ..b2::BodyDef::new()
-        });
-        let width = state.width() as f32;
-        let shape = b2::PolygonShape::new_box(width * 4.2 / PIXELS_PER_METER, 10.0);
+        let ground = self.world.create_body(
+            &b2::BodyDef {
+                position: b2::Vec2 { x: 0.0, y: -10.0 },
+                ..b2::BodyDef::new()
+            },
+            None,
+        );
+        let width = self.world.meters(state.width());
+        let shape = b2::PolygonShape::new_box(width * 4.2, 10.0);
         self.world.body_mut(ground).create_fast_fixture(&shape, 0.0);
-        let boundary_width = width / 2.0 / PIXELS_PER_METER - 2.0;
+        let boundary_width = width / 2.0 - 2.0;
         self.boundaries = (0..MAX_BOUNDARIES)
             .map(|i| {
                 let side = if i % 2 == 0 { -1.0 } else { 1.0 };
@@ -596,8 +392,7 @@ impl App {
     }
 
     fn spawn_entity(&mut self, state: &PistonAppState) {
-        let x = (state.mouse_x() - state.width() / 2.0) as f32 / PIXELS_PER_METER;
-        let y = (state.height() - state.mouse_y()) as f32 / PIXELS_PER_METER;
+        let b2::Vec2 { x, y } = self.world.mouse_position(state);
         let entity = Entity::new(&mut self.world, x, y, state.random_color(Some(1.0)));
         self.entities.push(entity);
     }
@@ -615,62 +410,50 @@ impl PistonApp for App {
                 )
                 .unwrap(),
             )
-            .vertex_shader(include_bytes!("world_150_core.glslv"))
-            .fragment_shader(include_bytes!("world_150_core.glslf"))
-            .build(window, world::new())
+            .build_world_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        let dragging = self.drag.update(&mut self.world, state);
+        if !dragging && state.mouse_button_pressed(MouseButton::Left) {
+            self.spawn_entity(state);
+        }
+        self.world.step(dt, state);
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
             self.dump_data(state);
         }
-        if state.mouse_button_pressed(MouseButton::Left) {
-            self.spawn_entity(state);
-        }
-        self.vertices.clear();
-        self.indices.clear();
-        self.world.step(1.0 / 60.0, 8, 3);
-        self.world.clear_forces();
+        self.debug_draw.handle_input(state);
+        self.batch.clear();
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
         for entity in &self.entities {
-            entity.extend_vertex_buffer(
-                &self.world,
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            entity.extend_sprite_batch(&self.world, texture_atlas, &mut self.batch);
         }
         for boundary in &self.boundaries {
-            boundary.extend_vertex_buffer(
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            boundary.extend_sprite_batch(texture_atlas, &mut self.batch);
         }
-        let half_width = state.width() as f32 / 2.0;
-        let half_height = state.height() as f32 / 2.0;
+        let transform = self.world.sprite_transform(state);
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
+            renderer,
             self.pipeline.as_ref().unwrap(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| world::Data {
+            |vbuf, out| world_sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
-                transform: [
-                    0.0,
-                    -1.0,
-                    PIXELS_PER_METER / half_width,
-                    PIXELS_PER_METER / half_height,
-                ],
+                transform: transform,
                 out: out,
             },
         );
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
     }
 }
 
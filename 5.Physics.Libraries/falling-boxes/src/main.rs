@@ -6,27 +6,11 @@
 extern crate piston_app;
 extern crate wrapped2d;
 
+use piston_app::box2d::{body_transform, ContactPhase, DebugDraw, PhysicsWorld};
+use piston_app::pipelines::world_sprites;
 use piston_app::*;
 use wrapped2d::b2;
 
-const PIXELS_PER_METER: f32 = 32.0;
-type World = b2::World<wrapped2d::user_data::NoUserData>;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline world {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        transform: Global<[f32; 4]> = "transform",
-        out: RenderTarget<gfx::format::Srgba8> = "o_color",
-    }
-}
-
 #[derive(Debug)]
 struct FallingBox {
     body_handle: b2::BodyHandle,
@@ -34,12 +18,21 @@ struct FallingBox {
 }
 
 impl FallingBox {
-    fn new(world: &mut World, x: f32, y: f32, color: Color) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        id: usize,
+        x: f32,
+        y: f32,
+        color: Color,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            Some(id),
+        );
         let mut body = world.body_mut(handle);
         body.create_fixture(
             &b2::PolygonShape::new_box(0.5, 0.5),
@@ -56,58 +49,35 @@ impl FallingBox {
         }
     }
 
-    fn extend_vertex_buffer(
-        &self,
-        world: &World,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: 0.52, y: 0.52 }).as_array(),
-                uv: [1.0, 0.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -0.52, y: 0.52 }).as_array(),
-                uv: [0.0, 0.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -0.52, y: -0.52 }).as_array(),
-                uv: [0.0, 1.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: 0.52, y: -0.52 }).as_array(),
-                uv: [1.0, 1.0],
-                color: self.color,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+    fn extend_sprite_batch(&self, world: &PhysicsWorld<usize>, batch: &mut SpriteBatch) {
+        let transform = body_transform(world.body(self.body_handle).transform());
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, 0.52),
+            [0.0, 0.0, 1.0, 1.0],
+            self.color,
+        );
     }
 }
 
 struct App {
-    world: World,
+    world: PhysicsWorld<usize>,
     boxes: Vec<FallingBox>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<world::Meta>>,
+    batch: SpriteBatch,
+    debug_draw: DebugDraw,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
 impl App {
     fn new() -> Self {
         const GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: -10.0 };
+        const IMPACT_IMPULSE: f32 = 2.0;
         App {
-            world: World::new(&GRAVITY),
+            world: PhysicsWorld::new(&GRAVITY).record_contacts(IMPACT_IMPULSE),
             boxes: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            batch: SpriteBatch::with_capacity(4096).flip_v(true),
+            debug_draw: DebugDraw::new(),
             pipeline: None,
             renderer: None,
         }
@@ -132,24 +102,36 @@ impl App {
     }
 
     fn setup_world(&mut self, state: &PistonAppState) {
-        let ground = self.world.create_body(&b2::BodyDef {
-            position: b2::Vec2 { x: 0.0, y: -10.0 },
-            ..b2::BodyDef::new()
-        });
-        let shape = b2::PolygonShape::new_box(
-            state.width() as f32 * 4.2 / PIXELS_PER_METER,
-            10.0,
+        let ground = self.world.create_body(
+            &b2::BodyDef {
+                position: b2::Vec2 { x: 0.0, y: -10.0 },
+                ..b2::BodyDef::new()
+            },
+            None,
         );
+        let shape =
+            b2::PolygonShape::new_box(self.world.meters(state.width()) * 4.2, 10.0);
         self.world.body_mut(ground).create_fast_fixture(&shape, 0.0);
     }
 
     fn spawn_box(&mut self, state: &PistonAppState) {
-        let x = (state.mouse_x() - state.width() / 2.0) as f32 / PIXELS_PER_METER;
-        let y = (state.height() - state.mouse_y()) as f32 / PIXELS_PER_METER;
+        let b2::Vec2 { x, y } = self.world.mouse_position(state);
+        let id = self.boxes.len();
         let falling_box =
-            FallingBox::new(&mut self.world, x, y, state.random_color(Some(1.0)));
+            FallingBox::new(&mut self.world, id, x, y, state.random_color(Some(1.0)));
         self.boxes.push(falling_box);
     }
+
+    fn handle_impacts(&mut self, state: &PistonAppState) {
+        for contact in self.world.contacts() {
+            if let ContactPhase::Impact { .. } = contact.phase {
+                // Boxes are never removed, their identifier is their index.
+                for &id in [contact.entity_a, contact.entity_b].iter().flatten() {
+                    self.boxes[id].color = state.random_color(Some(1.0));
+                }
+            }
+        }
+    }
 }
 
 impl PistonApp for App {
@@ -157,54 +139,51 @@ impl PistonApp for App {
         self.setup_world(state);
         let (pipeline, renderer) = PistonPipelineBuilder::new()
             .texture_atlas(TextureAtlas::from_path(window, "assets/box.png").unwrap())
-            .vertex_shader(include_bytes!("world_150_core.glslv"))
-            .fragment_shader(include_bytes!("world_150_core.glslf"))
-            .build(window, world::new())
+            .build_world_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn setup_headless(&mut self, state: &PistonAppState) {
+        self.setup_world(state);
+    }
+
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        if state.mouse_button_pressed(MouseButton::Left) {
+            self.spawn_box(state);
+        }
+        self.world.step(dt, state);
+        self.handle_impacts(state);
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
             self.dump_data(state);
         }
-        if state.mouse_button_pressed(MouseButton::Left) {
-            self.spawn_box(state);
-        }
-        self.vertices.clear();
-        self.indices.clear();
-        self.world.step(1.0 / 60.0, 8, 3);
-        self.world.clear_forces();
+        self.debug_draw.handle_input(state);
+        self.batch.clear();
         for falling_box in &self.boxes {
-            falling_box.extend_vertex_buffer(
-                &self.world,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            falling_box.extend_sprite_batch(&self.world, &mut self.batch);
         }
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
-        let half_width = state.width() as f32 / 2.0;
-        let half_height = state.height() as f32 / 2.0;
+        let transform = self.world.sprite_transform(state);
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
+            renderer,
             self.pipeline.as_ref().unwrap(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| world::Data {
+            |vbuf, out| world_sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
-                transform: [
-                    0.0,
-                    -1.0,
-                    PIXELS_PER_METER / half_width,
-                    PIXELS_PER_METER / half_height,
-                ],
+                transform: transform,
                 out: out,
             },
         );
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
     }
 }
 
@@ -0,0 +1,131 @@
+//! Nature of code - Following the book... in Rust, with Piston!
+//! http://natureofcode.com/
+//!
+//! Physics libraries - Physics scene viewer.
+//!
+//! Simulates the JSON scene given with `--scene <path>`
+//! (`assets/scenes/windmill.json` by default), drawn by the Box2D debug
+//! renderer. Drag bodies with the mouse, or click elsewhere to spawn the bodies
+//! listed in the scene. Press R to reload the scene, Tab to toggle the view.
+
+extern crate piston_app;
+
+use piston_app::box2d::{DebugDraw, MouseDrag, PhysicsWorld, Scene};
+use piston_app::*;
+
+const DEFAULT_SCENE: &str = "assets/scenes/windmill.json";
+
+struct App {
+    path: String,
+    scene: Scene,
+    world: PhysicsWorld<usize>,
+    drag: MouseDrag<usize>,
+    /// Whether the mouse grabbed a body since the button was pressed, so that
+    /// releasing it does not spawn one.
+    grabbed: bool,
+    spawned: usize,
+    next_id: usize,
+    debug_draw: DebugDraw,
+}
+
+impl App {
+    fn new() -> Self {
+        App {
+            path: DEFAULT_SCENE.to_owned(),
+            scene: Scene::default(),
+            world: Scene::default().world(),
+            drag: MouseDrag::new(),
+            grabbed: false,
+            spawned: 0,
+            next_id: 0,
+            debug_draw: DebugDraw::new().enabled(true),
+        }
+    }
+
+    fn dump_data(&self, state: &PistonAppState) {
+        let world = self.world.world();
+        println!(
+            "Frame {} | Bodies: {} | Spawned: {} | Joints: {}",
+            state.frame_count(),
+            world.bodies().count(),
+            self.spawned,
+            world.joints().count()
+        );
+    }
+
+    fn setup_world(&mut self, state: &PistonAppState) {
+        self.world = self.scene.world();
+        self.scene.populate(state, &mut self.world);
+        self.drag.reset();
+        self.grabbed = false;
+        self.spawned = 0;
+    }
+
+    fn reload_scene(&mut self, state: &PistonAppState) {
+        match Scene::from_path(&self.path) {
+            Ok(scene) => {
+                println!("Reloaded {}", self.path);
+                self.scene = scene;
+                self.setup_world(state);
+            }
+            Err(error) => eprintln!("{}: {}", self.path, error),
+        }
+    }
+
+    fn spawn_body(&mut self, state: &PistonAppState) {
+        if self.scene.spawn.is_empty() {
+            return;
+        }
+        let index = state.rng().gen_range(0, self.scene.spawn.len());
+        let position = self.world.mouse_position(state);
+        self.scene.spawn[index].create(&mut self.world, position, Some(self.next_id));
+        self.spawned += 1;
+        self.next_id += 1;
+    }
+}
+
+impl PistonApp for App {
+    fn setup(&mut self, _: &mut PistonAppWindow, state: &PistonAppState) {
+        if let Some(path) = cli_arg("scene") {
+            self.path = path;
+        }
+        self.scene = Scene::from_path(&self.path).unwrap();
+        self.setup_world(state);
+    }
+
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        if self.drag.update(&mut self.world, state) {
+            self.grabbed = true;
+        }
+        self.world.step(dt, state);
+        self.spawned -= self.world.despawned().len();
+    }
+
+    fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
+        if state.mouse_button_clicked(MouseButton::Left) {
+            if !self.grabbed {
+                self.spawn_body(state);
+            }
+            self.grabbed = false;
+        }
+        if state.key_hit(Key::R) {
+            self.reload_scene(state);
+        }
+        if state.key_hit(Key::D) {
+            self.dump_data(state);
+        }
+        self.debug_draw.handle_input(state);
+        window.draw_2d(state.event(), |_, gfx| {
+            clear(color::WHITE, gfx);
+        });
+        let transform = self.world.sprite_transform(state);
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
+    }
+}
+
+fn main() {
+    let mut app = App::new();
+    App::run(env!("CARGO_PKG_NAME"), &mut app);
+}
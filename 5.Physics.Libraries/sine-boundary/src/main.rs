@@ -6,30 +6,11 @@
 extern crate piston_app;
 extern crate wrapped2d;
 
+use piston_app::box2d::{body_transform, DebugDraw, PhysicsWorld, BODY_SKIN_DEPTH};
+use piston_app::pipelines::world_sprites;
 use piston_app::*;
 use wrapped2d::b2;
 
-const BODY_SKIN_DEPTH: f32 = 0.02;
-const PIXELS_PER_METER: f32 = 32.0;
-type World = b2::World<wrapped2d::user_data::NoUserData>;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline world {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        transform: Global<[f32; 4]> = "transform",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ALPHA),
-    }
-}
-
 #[derive(Debug)]
 struct SineBoundary {
     body_handle: b2::BodyHandle,
@@ -38,14 +19,14 @@ struct SineBoundary {
 
 impl SineBoundary {
     fn new(
-        world: &mut World,
+        world: &mut PhysicsWorld<usize>,
         x: f32,
         y: f32,
         width: f32,
         amplitude: f32,
         period: f32,
     ) -> Self {
-        let handle = world.create_body(&b2::BodyDef::new());
+        let handle = world.create_body(&b2::BodyDef::new(), None);
         let mut body = world.body_mut(handle);
         let vertices = Self::compute_vertices(x, y, width, amplitude, period);
         body.create_fast_fixture(&b2::ChainShape::new_chain(&vertices[..]), 1.0);
@@ -75,51 +56,51 @@ impl SineBoundary {
             .collect()
     }
 
-    fn extend_vertex_buffer(&self, vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>) {
+    fn extend_sprite_batch(&self, batch: &mut SpriteBatch) {
+        let depth = BODY_SKIN_DEPTH as Scalar;
         for i in 1..self.vertices.len() {
-            let start = vertices.len() as u32;
             let (from, to) = (self.vertices[i - 1], self.vertices[i]);
-            vertices.extend(&[
-                Vertex {
-                    pos: [to.x + BODY_SKIN_DEPTH, to.y + BODY_SKIN_DEPTH],
-                    uv: [0.5, 0.5],
-                    color: color::BLACK,
-                },
-                Vertex {
-                    pos: [from.x - BODY_SKIN_DEPTH, from.y + BODY_SKIN_DEPTH],
-                    uv: [0.5, 0.5],
-                    color: color::BLACK,
-                },
-                Vertex {
-                    pos: [from.x - BODY_SKIN_DEPTH, 0.0],
-                    uv: [0.5, 0.5],
-                    color: color::BLACK,
-                },
-                Vertex {
-                    pos: [to.x + BODY_SKIN_DEPTH, 0.0],
-                    uv: [0.5, 0.5],
-                    color: color::BLACK,
-                },
-            ]);
-            indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+            let (from_x, from_y) = (from.x as Scalar, from.y as Scalar);
+            let (to_x, to_y) = (to.x as Scalar, to.y as Scalar);
+            batch.quad(
+                [
+                    [to_x + depth, to_y + depth],
+                    [from_x - depth, from_y + depth],
+                    [from_x - depth, 0.0],
+                    [to_x + depth, 0.0],
+                ],
+                [[0.5, 0.5]; 4],
+                color::BLACK,
+            );
         }
     }
 }
 
 #[derive(Debug)]
 struct Token {
+    id: usize,
     body_handle: b2::BodyHandle,
     radius: f32,
     color: Color,
 }
 
 impl Token {
-    fn new(world: &mut World, x: f32, y: f32, radius: f32, color: Color) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        id: usize,
+        x: f32,
+        y: f32,
+        radius: f32,
+        color: Color,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            Some(id),
+        );
         let mut body = world.body_mut(handle);
         let mut shape = b2::CircleShape::new();
         shape.set_radius(radius);
@@ -133,185 +114,49 @@ impl Token {
             },
         );
         Token {
+            id: id,
             body_handle: handle,
             radius: radius,
             color: color,
         }
     }
 
-    fn survives(&self, world: &mut World) -> bool {
-        if world.body(self.body_handle).position().y < -2.0 {
-            world.destroy_body(self.body_handle);
-            false
-        } else {
-            true
-        }
-    }
-
-    fn extend_vertex_buffer(
-        &self,
-        world: &World,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
-    ) {
+    fn extend_sprite_batch(&self, world: &PhysicsWorld<usize>, batch: &mut SpriteBatch) {
         const THICKNESS: f32 = 0.042;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let radius = self.radius + BODY_SKIN_DEPTH;
         let inner_radius = self.radius - THICKNESS * 2.0;
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [1.0, 0.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [0.0, 0.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [0.0, 1.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [1.0, 1.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [1.0, 0.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [0.0, 0.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [0.0, 1.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [1.0, 1.0],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [0.5, 0.5],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [0.5, 0.5],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [0.5, 0.5],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [0.5, 0.5],
-                color: color::BLACK,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-            start + 8,
-            start + 9,
-            start + 10,
-            start + 10,
-            start + 11,
-            start + 8,
-        ]);
+        let (uv, center) = ([0.0, 0.0, 1.0, 1.0], [0.5, 0.5, 0.0, 0.0]);
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, radius.into()),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, inner_radius.into()),
+            uv,
+            self.color,
+        );
+        let (thickness, radius) = (THICKNESS as Scalar, self.radius as Scalar);
+        batch.rect(
+            transform,
+            [-thickness, -thickness, 2.0 * thickness, radius + thickness],
+            center,
+            color::BLACK,
+        );
     }
 }
 
 struct App {
-    world: World,
+    world: PhysicsWorld<usize>,
     boundary: Option<SineBoundary>,
     tokens: Vec<Token>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<world::Meta>>,
+    next_id: usize,
+    batch: SpriteBatch,
+    debug_draw: DebugDraw,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
@@ -319,11 +164,12 @@ impl App {
     fn new() -> Self {
         const GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: -10.0 };
         App {
-            world: World::new(&GRAVITY),
+            world: PhysicsWorld::new(&GRAVITY).despawn_below(-2.0),
             boundary: None,
             tokens: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            next_id: 0,
+            batch: SpriteBatch::with_capacity(4096).flip_v(true),
+            debug_draw: DebugDraw::new(),
             pipeline: None,
             renderer: None,
         }
@@ -331,12 +177,12 @@ impl App {
 
     fn dump_data(&self, state: &PistonAppState) {
         let token_count = self.tokens.len();
-        let vertex_count = self.vertices.len();
-        let index_count = self.indices.len();
+        let vertex_count = self.batch.vertices().len();
+        let index_count = self.batch.indices().len();
         let memory = (std::mem::size_of::<SineBoundary>()
             + token_count * std::mem::size_of::<Token>()
-            + vertex_count * std::mem::size_of::<Vertex>()
-            + index_count * std::mem::size_of::<u32>()) as f32
+            + std::mem::size_of_val(self.batch.vertices())
+            + std::mem::size_of_val(self.batch.indices())) as f32
             / 1024.0;
         println!(
             "Frame {} | Tokens: {} | Vertices: {} | Indices: {} | Memory: {:.2} KB",
@@ -349,8 +195,7 @@ impl App {
     }
 
     fn setup_world(&mut self, state: &PistonAppState) {
-        let width = state.width() as f32;
-        let half_width = width / 2.0 / PIXELS_PER_METER + 1.0;
+        let half_width = self.world.meters(state.width() / 2.0) + 1.0;
         self.boundary = Some(SineBoundary::new(
             &mut self.world,
             -half_width,
@@ -362,16 +207,17 @@ impl App {
     }
 
     fn spawn_token(&mut self, state: &PistonAppState) {
-        let x = (state.mouse_x() - state.width() / 2.0) as f32 / PIXELS_PER_METER;
-        let y = (state.height() - state.mouse_y()) as f32 / PIXELS_PER_METER;
+        let b2::Vec2 { x, y } = self.world.mouse_position(state);
         let token = Token::new(
             &mut self.world,
+            self.next_id,
             x,
             y,
-            thread_rng().gen_range(0.16, 0.5),
+            state.rng().gen_range(0.16, 0.5),
             state.random_color(Some(1.0)),
         );
         self.tokens.push(token);
+        self.next_id += 1;
     }
 }
 
@@ -380,54 +226,51 @@ impl PistonApp for App {
         self.setup_world(state);
         let (pipeline, renderer) = PistonPipelineBuilder::new()
             .texture_atlas(TextureAtlas::from_path(window, "assets/token.png").unwrap())
-            .vertex_shader(include_bytes!("world_150_core.glslv"))
-            .fragment_shader(include_bytes!("world_150_core.glslf"))
-            .build(window, world::new())
+            .build_world_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        if state.mouse_button_pressed(MouseButton::Left) {
+            self.spawn_token(state);
+        }
+        self.world.step(dt, state);
+        let despawned = self.world.despawned();
+        self.tokens.retain(|token| !despawned.contains(&token.id));
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
             self.dump_data(state);
         }
-        if state.mouse_button_pressed(MouseButton::Left) {
-            self.spawn_token(state);
-        }
-        self.vertices.clear();
-        self.indices.clear();
-        let world = &mut self.world;
-        world.step(1.0 / 60.0, 8, 3);
-        world.clear_forces();
-        self.tokens.retain(|token| token.survives(world));
+        self.debug_draw.handle_input(state);
+        self.batch.clear();
+        let world = &self.world;
         for token in &self.tokens {
-            token.extend_vertex_buffer(world, &mut self.vertices, &mut self.indices);
+            token.extend_sprite_batch(world, &mut self.batch);
         }
         let boundary = self.boundary.as_ref().unwrap();
-        boundary.extend_vertex_buffer(&mut self.vertices, &mut self.indices);
+        boundary.extend_sprite_batch(&mut self.batch);
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
-        let half_width = state.width() as f32 / 2.0;
-        let half_height = state.height() as f32 / 2.0;
+        let transform = self.world.sprite_transform(state);
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
+            renderer,
             self.pipeline.as_ref().unwrap(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| world::Data {
+            |vbuf, out| world_sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
-                transform: [
-                    0.0,
-                    -1.0,
-                    PIXELS_PER_METER / half_width,
-                    PIXELS_PER_METER / half_height,
-                ],
+                transform: transform,
                 out: out,
             },
         );
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
     }
 }
 
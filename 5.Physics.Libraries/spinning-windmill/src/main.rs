@@ -6,30 +6,13 @@
 extern crate piston_app;
 extern crate wrapped2d;
 
+use piston_app::box2d::{
+    body_transform, DebugDraw, MouseDrag, PhysicsWorld, BODY_SKIN_DEPTH,
+};
+use piston_app::pipelines::world_sprites;
 use piston_app::*;
 use wrapped2d::b2;
 
-const BODY_SKIN_DEPTH: f32 = 0.02;
-const PIXELS_PER_METER: f32 = 32.0;
-type World = b2::World<wrapped2d::user_data::NoUserData>;
-
-gfx_defines! {
-    vertex Vertex {
-        pos: [f32; 2] = "pos",
-        uv: [f32; 2] = "uv",
-        color: [f32; 4] = "color",
-    }
-
-    pipeline world {
-        vbuf: VertexBuffer<Vertex> = (),
-        sampler: TextureSampler<[f32; 4]> = "sampler",
-        transform: Global<[f32; 4]> = "transform",
-        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
-                                                 gfx::state::ColorMask::all(),
-                                                 gfx::preset::blend::ALPHA),
-    }
-}
-
 #[derive(Debug)]
 struct Brick {
     body_handle: b2::BodyHandle,
@@ -40,7 +23,7 @@ struct Brick {
 
 impl Brick {
     fn new(
-        world: &mut World,
+        world: &mut PhysicsWorld<usize>,
         x: f32,
         y: f32,
         width: f32,
@@ -48,15 +31,18 @@ impl Brick {
         color: Color,
         density: f32,
     ) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: if density > 0.0 {
-                b2::BodyType::Dynamic
-            } else {
-                b2::BodyType::Static
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: if density > 0.0 {
+                    b2::BodyType::Dynamic
+                } else {
+                    b2::BodyType::Static
+                },
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
             },
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+            None,
+        );
         let mut body = world.body_mut(handle);
         let (half_width, half_height) = (width / 2.0, height / 2.0);
         body.create_fast_fixture(
@@ -75,96 +61,61 @@ impl Brick {
         self.body_handle
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.084;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let (w, h) = (
             self.half_width + BODY_SKIN_DEPTH,
             self.half_height + BODY_SKIN_DEPTH,
         );
         let (iw, ih) = (w - THICKNESS, h - THICKNESS);
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(0);
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: h }).as_array(),
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: h }).as_array(),
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -w, y: -h }).as_array(),
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: w, y: -h }).as_array(),
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: ih }).as_array(),
-                uv: [u + tw, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: ih }).as_array(),
-                uv: [u, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: -iw, y: -ih }).as_array(),
-                uv: [u, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform * b2::Vec2 { x: iw, y: -ih }).as_array(),
-                uv: [u + tw, v + th],
-                color: self.color,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-        ]);
+        let uv = [u, v, tw, th];
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, w.into(), h.into()]),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered([0.0, 0.0, iw.into(), ih.into()]),
+            uv,
+            self.color,
+        );
     }
 }
 
 #[derive(Debug)]
 struct Token {
+    id: usize,
     body_handle: b2::BodyHandle,
     radius: f32,
     color: Color,
 }
 
 impl Token {
-    fn new(world: &mut World, x: f32, y: f32, radius: f32, color: Color) -> Self {
-        let handle = world.create_body(&b2::BodyDef {
-            body_type: b2::BodyType::Dynamic,
-            position: b2::Vec2 { x: x, y: y },
-            ..b2::BodyDef::new()
-        });
+    fn new(
+        world: &mut PhysicsWorld<usize>,
+        id: usize,
+        x: f32,
+        y: f32,
+        radius: f32,
+        color: Color,
+    ) -> Self {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: b2::BodyType::Dynamic,
+                position: b2::Vec2 { x: x, y: y },
+                ..b2::BodyDef::new()
+            },
+            Some(id),
+        );
         let mut body = world.body_mut(handle);
         let mut shape = b2::CircleShape::new();
         shape.set_radius(radius);
@@ -177,177 +128,44 @@ impl Token {
             },
         );
         Token {
+            id: id,
             body_handle: handle,
             radius: radius,
             color: color,
         }
     }
 
-    fn survives(&self, world: &mut World) -> bool {
-        if world.body(self.body_handle).position().y < -2.0 {
-            world.destroy_body(self.body_handle);
-            false
-        } else {
-            true
-        }
-    }
-
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
         const THICKNESS: f32 = 0.042;
-        let start = vertices.len() as u32;
-        let body = world.body(self.body_handle);
-        let transform = body.transform();
+        let transform = body_transform(world.body(self.body_handle).transform());
         let radius = self.radius + BODY_SKIN_DEPTH;
         let inner_radius = self.radius - THICKNESS * 2.0;
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(1);
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u, v],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v + th],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: inner_radius,
-                    })
-                .as_array(),
-                uv: [u, v],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [u, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: inner_radius,
-                        y: -inner_radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v + th],
-                color: self.color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: self.radius,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: THICKNESS,
-                        y: -THICKNESS,
-                    })
-                .as_array(),
-                uv: [u + tw / 2.0, v + th / 2.0],
-                color: color::BLACK,
-            },
-        ]);
-        indices.extend(&[
-            start,
-            start + 1,
-            start + 2,
-            start + 2,
-            start + 3,
-            start,
-            start + 4,
-            start + 5,
-            start + 6,
-            start + 6,
-            start + 7,
-            start + 4,
-            start + 8,
-            start + 9,
-            start + 10,
-            start + 10,
-            start + 11,
-            start + 8,
-        ]);
+        let (uv, center) = ([u, v, tw, th], [u + tw / 2.0, v + th / 2.0, 0.0, 0.0]);
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, radius.into()),
+            uv,
+            color::BLACK,
+        );
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, inner_radius.into()),
+            uv,
+            self.color,
+        );
+        let (thickness, radius) = (THICKNESS as Scalar, self.radius as Scalar);
+        batch.rect(
+            transform,
+            [-thickness, -thickness, 2.0 * thickness, radius + thickness],
+            center,
+            color::BLACK,
+        );
     }
 }
 
@@ -359,7 +177,7 @@ struct Windmill {
 }
 
 impl Windmill {
-    fn new(world: &mut World) -> Self {
+    fn new(world: &mut PhysicsWorld<usize>) -> Self {
         const TOWER_HEIGHT: f32 = 8.0;
         const ANCHOR_DELTA: f32 = TOWER_HEIGHT / 2.0 - 0.5;
         let color = color::grey(0.25);
@@ -396,14 +214,14 @@ impl Windmill {
         }
     }
 
-    fn is_motor_enabled(&self, world: &World) -> bool {
+    fn is_motor_enabled(&self, world: &PhysicsWorld<usize>) -> bool {
         match **world.joint(self.joint_handle) {
             b2::UnknownJoint::Revolute(ref joint) => joint.is_motor_enabled(),
             _ => false,
         }
     }
 
-    fn toggle_motor(&mut self, world: &World) {
+    fn toggle_motor(&mut self, world: &PhysicsWorld<usize>) {
         let mut joint = world.joint_mut(self.joint_handle);
         if let b2::UnknownJoint::Revolute(ref mut joint) = **joint {
             let motor_enabled = joint.is_motor_enabled();
@@ -411,80 +229,39 @@ impl Windmill {
         }
     }
 
-    fn extend_vertex_buffer(
+    fn extend_sprite_batch(
         &self,
-        world: &World,
+        world: &PhysicsWorld<usize>,
         texture_atlas: &TextureAtlas,
-        vertices: &mut Vec<Vertex>,
-        indices: &mut Vec<u32>,
+        batch: &mut SpriteBatch,
     ) {
-        self.tower
-            .extend_vertex_buffer(world, texture_atlas, vertices, indices);
-        self.sail
-            .extend_vertex_buffer(world, texture_atlas, vertices, indices);
-        let start = vertices.len() as u32;
-        let sail = world.body(self.sail.body_handle());
-        let transform = sail.transform();
-        let radius = 0.16;
+        self.tower.extend_sprite_batch(world, texture_atlas, batch);
+        self.sail.extend_sprite_batch(world, texture_atlas, batch);
+        let transform = body_transform(world.body(self.sail.body_handle()).transform());
         let (u, v, tw, th) = texture_atlas.texture_uv_extents(1);
         let color = if self.is_motor_enabled(world) {
             [0.0, 0.1, 0.0, 1.0]
         } else {
             [0.1, 0.0, 0.0, 1.0]
         };
-        vertices.extend(&[
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v],
-                color: color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: radius,
-                    })
-                .as_array(),
-                uv: [u, v],
-                color: color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: -radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u, v + th],
-                color: color,
-            },
-            Vertex {
-                pos: *(transform
-                    * b2::Vec2 {
-                        x: radius,
-                        y: -radius,
-                    })
-                .as_array(),
-                uv: [u + tw, v + th],
-                color: color,
-            },
-        ]);
-        indices.extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+        batch.rect(
+            transform,
+            rectangle::centered_square(0.0, 0.0, 0.16),
+            [u, v, tw, th],
+            color,
+        );
     }
 }
 
 struct App {
-    world: World,
+    world: PhysicsWorld<usize>,
+    drag: MouseDrag<usize>,
     windmill: Option<Windmill>,
     tokens: Vec<Token>,
-    vertices: Vec<Vertex>,
-    indices: Vec<u32>,
-    pipeline: Option<PistonPipeline<world::Meta>>,
+    next_id: usize,
+    batch: SpriteBatch,
+    debug_draw: DebugDraw,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
     renderer: Option<PistonRenderer>,
 }
 
@@ -492,11 +269,13 @@ impl App {
     fn new() -> Self {
         const GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: -10.0 };
         App {
-            world: World::new(&GRAVITY),
+            world: PhysicsWorld::new(&GRAVITY).despawn_below(-2.0),
+            drag: MouseDrag::new(),
             windmill: None,
             tokens: vec![],
-            vertices: Vec::with_capacity(4 * 4096),
-            indices: Vec::with_capacity(6 * 4096),
+            next_id: 0,
+            batch: SpriteBatch::with_capacity(4096).flip_v(true),
+            debug_draw: DebugDraw::new(),
             pipeline: None,
             renderer: None,
         }
@@ -504,11 +283,11 @@ impl App {
 
     fn dump_data(&self, state: &PistonAppState) {
         let token_count = self.tokens.len();
-        let vertex_count = self.vertices.len();
-        let index_count = self.indices.len();
+        let vertex_count = self.batch.vertices().len();
+        let index_count = self.batch.indices().len();
         let memory = (token_count * std::mem::size_of::<Token>()
-            + vertex_count * std::mem::size_of::<Vertex>()
-            + index_count * std::mem::size_of::<u32>()) as f32
+            + std::mem::size_of_val(self.batch.vertices())
+            + std::mem::size_of_val(self.batch.indices())) as f32
             / 1024.0;
         println!(
             "Frame {} | Tokens: {} | Vertices: {} | Indices: {} | Memory: {:.2} KB",
@@ -521,16 +300,17 @@ impl App {
     }
 
     fn spawn_token(&mut self, state: &PistonAppState) {
-        let x = (state.mouse_x() - state.width() / 2.0) as f32 / PIXELS_PER_METER;
-        let y = (state.height() - state.mouse_y()) as f32 / PIXELS_PER_METER;
+        let b2::Vec2 { x, y } = self.world.mouse_position(state);
         let token = Token::new(
             &mut self.world,
+            self.next_id,
             x,
             y,
-            thread_rng().gen_range(0.16, 0.5),
+            state.rng().gen_range(0.16, 0.5),
             state.random_color(Some(1.0)),
         );
         self.tokens.push(token);
+        self.next_id += 1;
     }
 }
 
@@ -546,67 +326,55 @@ impl PistonApp for App {
                 )
                 .unwrap(),
             )
-            .vertex_shader(include_bytes!("world_150_core.glslv"))
-            .fragment_shader(include_bytes!("world_150_core.glslf"))
-            .build(window, world::new())
+            .build_world_sprites(window)
             .unwrap();
         self.pipeline = Some(pipeline);
         self.renderer = Some(renderer);
     }
 
+    fn update(&mut self, dt: Scalar, state: &PistonAppState) {
+        let dragging = self.drag.update(&mut self.world, state);
+        if !dragging && state.mouse_button_pressed(MouseButton::Left) {
+            self.spawn_token(state);
+        }
+        self.world.step(dt, state);
+        let despawned = self.world.despawned();
+        self.tokens.retain(|token| !despawned.contains(&token.id));
+    }
+
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState) {
         if state.key_hit(Key::D) {
             self.dump_data(state);
         }
-        if state.mouse_button_pressed(MouseButton::Left) {
-            self.spawn_token(state);
-        }
+        self.debug_draw.handle_input(state);
         let world = &mut self.world;
         let windmill = self.windmill.as_mut().unwrap();
         if state.mouse_button_clicked(MouseButton::Right) {
             windmill.toggle_motor(world);
         }
-        self.vertices.clear();
-        self.indices.clear();
-        world.step(1.0 / 60.0, 8, 3);
-        world.clear_forces();
-        self.tokens.retain(|token| token.survives(world));
+        self.batch.clear();
         let renderer = self.renderer.as_ref().unwrap();
         let texture_atlas = renderer.texture_atlas().unwrap();
-        windmill.extend_vertex_buffer(
-            world,
-            texture_atlas,
-            &mut self.vertices,
-            &mut self.indices,
-        );
+        windmill.extend_sprite_batch(world, texture_atlas, &mut self.batch);
         for token in &self.tokens {
-            token.extend_vertex_buffer(
-                world,
-                texture_atlas,
-                &mut self.vertices,
-                &mut self.indices,
-            );
+            token.extend_sprite_batch(world, texture_atlas, &mut self.batch);
         }
-        let half_width = state.width() as f32 / 2.0;
-        let half_height = state.height() as f32 / 2.0;
+        let transform = self.world.sprite_transform(state);
         renderer.clear(window, color::WHITE);
-        renderer.draw(
+        self.batch.flush(
             window,
+            renderer,
             self.pipeline.as_ref().unwrap(),
-            &self.vertices[..],
-            &self.indices[..],
-            |vbuf, out| world::Data {
+            |vbuf, out| world_sprites::Data {
                 vbuf: vbuf,
                 sampler: texture_atlas.texture_view_sampler(),
-                transform: [
-                    0.0,
-                    -1.0,
-                    PIXELS_PER_METER / half_width,
-                    PIXELS_PER_METER / half_height,
-                ],
+                transform: transform,
                 out: out,
             },
         );
+        self.debug_draw
+            .draw(window, self.world.world(), transform)
+            .unwrap();
     }
 }
 
@@ -0,0 +1,70 @@
+//! Blend modes for stock and custom pipelines.
+
+use super::*;
+
+use gfx::state::{Blend, BlendChannel, BlendValue, Equation, Factor};
+
+/// How a pipeline combines its output with the render target.
+///
+/// `PistonPipelineBuilder::blend_mode()` selects it for the stock pipelines,
+/// custom ones use `blend()` in their `Init`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    #[default]
+    Alpha,
+    Additive,
+    Multiply,
+    /// Inverse of multiplying the inverted colours: brightens, never darkens.
+    Screen,
+    /// Alpha blending of colours already multiplied by their alpha.
+    PremultipliedAlpha,
+    /// No blending, the output overwrites the target.
+    Replace,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 6] = [
+        BlendMode::Alpha,
+        BlendMode::Additive,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::PremultipliedAlpha,
+        BlendMode::Replace,
+    ];
+
+    /// Following mode in `ALL`, wrapping around, to cycle through them.
+    pub fn next(self) -> Self {
+        let index = BlendMode::ALL
+            .iter()
+            .position(|&mode| mode == self)
+            .unwrap();
+        BlendMode::ALL[(index + 1) % BlendMode::ALL.len()]
+    }
+
+    pub fn blend(self) -> Blend {
+        match self {
+            BlendMode::Alpha => gfx::preset::blend::ALPHA,
+            BlendMode::Additive => gfx::preset::blend::ADD,
+            BlendMode::Multiply => gfx::preset::blend::MULTIPLY,
+            BlendMode::Screen => Self::one_minus_source(BlendValue::SourceColor),
+            BlendMode::PremultipliedAlpha => {
+                Self::one_minus_source(BlendValue::SourceAlpha)
+            }
+            BlendMode::Replace => gfx::preset::blend::REPLACE,
+        }
+    }
+
+    /// `source + destination * (1 - value)`, on colour and alpha alike.
+    fn one_minus_source(value: BlendValue) -> Blend {
+        let channel = BlendChannel {
+            equation: Equation::Add,
+            source: Factor::One,
+            destination: Factor::OneMinus(value),
+        };
+        Blend {
+            color: channel,
+            alpha: channel,
+        }
+    }
+}
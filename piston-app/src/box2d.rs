@@ -0,0 +1,33 @@
+//! Box2D helpers, built on `wrapped2d` when the `box2d` feature is enabled.
+
+use super::*;
+
+use wrapped2d::b2;
+use wrapped2d::user_data::{NoUserData, UserDataTypes};
+
+pub use self::contact::{ContactEvent, ContactPhase};
+pub use self::debug::{DebugDraw, DebugDrawStyle};
+pub use self::drag::MouseDrag;
+pub use self::scene::{
+    Scene, SceneBody, SceneBodyType, SceneBoundary, SceneFixture, SceneJoint, SceneMotor,
+    SceneShape,
+};
+pub use self::world::{PhysicsWorld, PIXELS_PER_METER};
+
+mod contact;
+mod debug;
+mod drag;
+mod scene;
+mod world;
+
+/// Outset of body sprites, hiding the gap Box2D keeps around polygons.
+pub const BODY_SKIN_DEPTH: f32 = 0.02;
+
+/// `SpriteBatch` transform of a body.
+pub fn body_transform(transform: &b2::Transform) -> Matrix2d {
+    let (sin, cos) = (transform.rot.sin as Scalar, transform.rot.cos as Scalar);
+    [
+        [cos, -sin, transform.pos.x as Scalar],
+        [sin, cos, transform.pos.y as Scalar],
+    ]
+}
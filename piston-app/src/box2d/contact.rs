@@ -0,0 +1,231 @@
+//! Contact events, queued by a Box2D contact listener.
+
+use super::*;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wrapped2d::dynamics::world::callbacks::ContactAccess;
+
+use super::world::{linked_entity, Entities};
+
+/// What happened between the two bodies of a `ContactEvent`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContactPhase {
+    /// The fixtures started touching.
+    Begin,
+    /// The solver first pushed the bodies apart after they started touching,
+    /// with a total normal `impulse`, in newton-seconds, at least the threshold
+    /// given to `PhysicsWorld::record_contacts()`. Resting contacts do not
+    /// report further impacts.
+    Impact { impulse: f32 },
+    /// The fixtures stopped touching, or one of the bodies was destroyed.
+    End,
+}
+
+/// Contact between two bodies, reported by `PhysicsWorld::contacts()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactEvent<E> {
+    pub phase: ContactPhase,
+    pub body_a: b2::BodyHandle,
+    pub entity_a: Option<E>,
+    pub body_b: b2::BodyHandle,
+    pub entity_b: Option<E>,
+    /// World normal, from `body_a` to `body_b`.
+    pub normal: b2::Vec2,
+    points: [b2::Vec2; b2::MAX_MANIFOLD_POINTS],
+    point_count: usize,
+}
+
+impl<E: Copy + PartialEq> ContactEvent<E> {
+    /// World contact points, none once the fixtures stopped touching.
+    #[inline]
+    pub fn points(&self) -> &[b2::Vec2] {
+        &self.points[..self.point_count]
+    }
+
+    /// Whether `entity` is one of the two bodies in contact.
+    pub fn involves(&self, entity: E) -> bool {
+        self.entity_a == Some(entity) || self.entity_b == Some(entity)
+    }
+
+    /// Entity, if any, in contact with `entity`, or `None` if `entity` is not
+    /// involved.
+    pub fn other(&self, entity: E) -> Option<Option<E>> {
+        if self.entity_a == Some(entity) {
+            Some(self.entity_b)
+        } else if self.entity_b == Some(entity) {
+            Some(self.entity_a)
+        } else {
+            None
+        }
+    }
+}
+
+/// Events recorded by a `ContactRecorder`, shared with its world.
+pub(crate) type ContactQueue<E> = Rc<RefCell<Vec<ContactEvent<E>>>>;
+
+/// Fixtures and child shapes identifying a Box2D contact.
+type ContactKey = (b2::FixtureHandle, i32, b2::FixtureHandle, i32);
+
+/// `b2::ContactListener` pushing the contacts of a world to a `ContactQueue`.
+pub(crate) struct ContactRecorder<E> {
+    queue: ContactQueue<E>,
+    entities: Entities<E>,
+    min_impulse: f32,
+    /// Contacts begun but not solved yet, whose next `post_solve()` is their
+    /// impact.
+    unsolved: Vec<ContactKey>,
+}
+
+impl<E: Copy> ContactRecorder<E> {
+    pub(crate) fn new(
+        queue: ContactQueue<E>,
+        entities: Entities<E>,
+        min_impulse: f32,
+    ) -> Self {
+        ContactRecorder {
+            queue: queue,
+            entities: entities,
+            min_impulse: min_impulse,
+            unsolved: vec![],
+        }
+    }
+
+    fn key(contact: &ContactAccess<'_, NoUserData>) -> ContactKey {
+        let (_, fixture_a) = contact.contact.fixture_a();
+        let (_, fixture_b) = contact.contact.fixture_b();
+        (
+            fixture_a,
+            contact.contact.child_index_a(),
+            fixture_b,
+            contact.contact.child_index_b(),
+        )
+    }
+
+    /// Forgets `key`, returning whether it was unsolved.
+    fn solve(&mut self, key: ContactKey) -> bool {
+        match self.unsolved.iter().position(|&unsolved| unsolved == key) {
+            Some(index) => {
+                self.unsolved.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn record(&mut self, phase: ContactPhase, contact: ContactAccess<'_, NoUserData>) {
+        let (body_a, _) = contact.contact.fixture_a();
+        let (body_b, _) = contact.contact.fixture_b();
+        let point_count = if contact.contact.is_touching() {
+            contact.contact.manifold().count as usize
+        } else {
+            0
+        };
+        let manifold = contact.contact.world_manifold();
+        self.queue.borrow_mut().push(ContactEvent {
+            phase: phase,
+            body_a: body_a,
+            entity_a: linked_entity(&self.entities, body_a),
+            body_b: body_b,
+            entity_b: linked_entity(&self.entities, body_b),
+            normal: manifold.normal,
+            points: manifold.points,
+            point_count: point_count,
+        });
+    }
+}
+
+impl<E: Copy + 'static> b2::ContactListener<NoUserData> for ContactRecorder<E> {
+    fn begin_contact(&mut self, contact: ContactAccess<'_, NoUserData>) {
+        self.unsolved.push(Self::key(&contact));
+        self.record(ContactPhase::Begin, contact);
+    }
+
+    fn end_contact(&mut self, contact: ContactAccess<'_, NoUserData>) {
+        self.solve(Self::key(&contact));
+        self.record(ContactPhase::End, contact);
+    }
+
+    fn post_solve(
+        &mut self,
+        contact: ContactAccess<'_, NoUserData>,
+        impulse: &b2::ContactImpulse,
+    ) {
+        if !self.solve(Self::key(&contact)) {
+            return;
+        }
+        let count = impulse.count as usize;
+        let impulse: f32 = impulse.normal_impulses[..count].iter().sum();
+        if impulse >= self.min_impulse {
+            self.record(ContactPhase::Impact { impulse: impulse }, contact);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Steps a box of entity 1 falling 1 m onto static ground until it rests,
+    /// then throws it up, returning the contacts recorded.
+    fn drop_box(min_impulse: f32) -> Vec<ContactEvent<u32>> {
+        let state = PistonAppState::with_size(640, 480);
+        let mut world = PhysicsWorld::new(&b2::Vec2 { x: 0.0, y: -10.0 })
+            .record_contacts(min_impulse);
+        let ground = world.create_body(&b2::BodyDef::new(), None);
+        world
+            .body_mut(ground)
+            .create_fast_fixture(&b2::PolygonShape::new_box(5.0, 0.5), 0.0);
+        let def = b2::BodyDef {
+            body_type: b2::BodyType::Dynamic,
+            position: b2::Vec2 { x: 0.0, y: 2.0 },
+            ..b2::BodyDef::new()
+        };
+        let body = world.create_body(&def, Some(1));
+        world
+            .body_mut(body)
+            .create_fast_fixture(&b2::PolygonShape::new_box(0.5, 0.5), 1.0);
+
+        let mut contacts = vec![];
+        for frame in 0..180 {
+            if frame == 120 {
+                world
+                    .body_mut(body)
+                    .set_linear_velocity(&b2::Vec2 { x: 0.0, y: 10.0 });
+            }
+            world.step(1.0 / 60.0, &state);
+            contacts.extend_from_slice(world.contacts());
+        }
+        for contact in &contacts {
+            assert!(contact.involves(1));
+            assert_eq!(contact.other(1), Some(None));
+            assert!(contact.body_a == ground || contact.body_b == ground);
+        }
+        contacts
+    }
+
+    #[test]
+    fn records_begin_impact_and_end() {
+        let contacts = drop_box(1.0);
+        let phases: Vec<_> = contacts.iter().map(|contact| contact.phase).collect();
+        assert_eq!(phases.len(), 3, "{:?}", phases);
+        assert_eq!(phases[0], ContactPhase::Begin);
+        match phases[1] {
+            ContactPhase::Impact { impulse } => assert!(impulse >= 1.0),
+            phase => panic!("expected an impact, got {:?}", phase),
+        }
+        assert_eq!(phases[2], ContactPhase::End);
+        assert_eq!(contacts[0].points().len(), 2);
+        assert!(contacts[2].points().is_empty());
+    }
+
+    #[test]
+    fn skips_impacts_below_the_threshold() {
+        let phases: Vec<_> = drop_box(1000.0)
+            .iter()
+            .map(|contact| contact.phase)
+            .collect();
+        assert_eq!(phases, vec![ContactPhase::Begin, ContactPhase::End]);
+    }
+}
@@ -0,0 +1,358 @@
+//! Debug rendering of everything a Box2D world simulates.
+
+use super::*;
+
+use pipelines::world_sprites;
+use wrapped2d::b2::{Joint as _, Shape as _};
+
+/// What `DebugDraw` shows and how. Sizes are in metres.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugDrawStyle {
+    pub fixtures: bool,
+    pub joints: bool,
+    pub aabbs: bool,
+    pub contacts: bool,
+    pub centers_of_mass: bool,
+    pub line_width: Scalar,
+    pub point_size: Scalar,
+    /// Length of the axes drawn at the centres of mass.
+    pub axis_length: Scalar,
+    /// Segments approximating a circle.
+    pub circle_segments: usize,
+    /// Opacity of fixture interiors, relative to their outlines.
+    pub fill_alpha: ColorComponent,
+    pub static_color: Color,
+    pub kinematic_color: Color,
+    pub dynamic_color: Color,
+    pub sleeping_color: Color,
+    pub inactive_color: Color,
+    pub sensor_color: Color,
+    pub joint_color: Color,
+    pub aabb_color: Color,
+    pub contact_color: Color,
+    pub center_of_mass_color: Color,
+}
+
+impl Default for DebugDrawStyle {
+    fn default() -> Self {
+        DebugDrawStyle {
+            fixtures: true,
+            joints: true,
+            aabbs: false,
+            contacts: true,
+            centers_of_mass: true,
+            line_width: 0.04,
+            point_size: 0.15,
+            axis_length: 0.4,
+            circle_segments: 24,
+            fill_alpha: 0.3,
+            static_color: [0.2, 0.6, 0.2, 1.0],
+            kinematic_color: [0.2, 0.2, 0.8, 1.0],
+            dynamic_color: [0.8, 0.3, 0.3, 1.0],
+            sleeping_color: [0.5, 0.5, 0.5, 1.0],
+            inactive_color: [0.5, 0.5, 0.3, 1.0],
+            sensor_color: [0.9, 0.6, 0.0, 1.0],
+            joint_color: [0.1, 0.6, 0.6, 1.0],
+            aabb_color: [0.8, 0.2, 0.8, 1.0],
+            contact_color: [1.0, 0.1, 0.1, 1.0],
+            center_of_mass_color: [0.1, 0.1, 0.1, 1.0],
+        }
+    }
+}
+
+/// Draws the fixtures, joints, AABBs, contact points and centres of mass of any
+/// `b2::World`, from the world itself rather than from the sketch's entities,
+/// on top of whatever was drawn before.
+///
+/// Disabled by default: `handle_input()` toggles it with `toggle_key()`.
+#[derive(Debug)]
+pub struct DebugDraw {
+    style: DebugDrawStyle,
+    enabled: bool,
+    toggle_key: Key,
+    batch: SpriteBatch,
+    pipeline: Option<PistonPipeline<world_sprites::Meta>>,
+    renderer: Option<PistonRenderer>,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        DebugDraw {
+            style: DebugDrawStyle::default(),
+            enabled: false,
+            toggle_key: Key::Tab,
+            batch: SpriteBatch::with_capacity(1024),
+            pipeline: None,
+            renderer: None,
+        }
+    }
+
+    pub fn style(mut self, style: DebugDrawStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Key toggling the debug view, `Key::Tab` by default.
+    pub fn toggle_key(mut self, key: Key) -> Self {
+        self.toggle_key = key;
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    #[inline]
+    pub fn style_mut(&mut self) -> &mut DebugDrawStyle {
+        &mut self.style
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Switches the debug view on or off, and returns whether it is now on.
+    pub fn toggle(&mut self) -> bool {
+        self.enabled = !self.enabled;
+        self.enabled
+    }
+
+    /// Toggles the debug view if `toggle_key()` was hit this frame.
+    pub fn handle_input(&mut self, state: &PistonAppState) {
+        if state.key_hit(self.toggle_key) {
+            self.toggle();
+        }
+    }
+
+    /// Draws `world` if enabled, mapping metres to normalized device coordinates
+    /// through `transform`, the `pipelines::world_sprites` uniform.
+    pub fn draw<U: UserDataTypes>(
+        &mut self,
+        window: &mut PistonAppWindow,
+        world: &b2::World<U>,
+        transform: [f32; 4],
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.renderer.is_none() {
+            let (pipeline, renderer) = PistonPipelineBuilder::new()
+                .texture_atlas(TextureAtlas::white(window)?)
+                .build_world_sprites(window)?;
+            self.pipeline = Some(pipeline);
+            self.renderer = Some(renderer);
+        }
+        self.batch.clear();
+        self.extend_sprite_batch(world);
+        let renderer = self.renderer.as_ref().unwrap();
+        let texture_atlas = renderer.texture_atlas().unwrap();
+        self.batch.flush(
+            window,
+            renderer,
+            self.pipeline.as_ref().unwrap(),
+            |vbuf, out| world_sprites::Data {
+                vbuf: vbuf,
+                sampler: texture_atlas.texture_view_sampler(),
+                transform: transform,
+                out: out,
+            },
+        );
+        Ok(())
+    }
+
+    fn extend_sprite_batch<U: UserDataTypes>(&mut self, world: &b2::World<U>) {
+        let style = self.style;
+        if style.fixtures {
+            for (_, body) in world.bodies() {
+                let body = body.borrow();
+                self.batch.set_transform(body_transform(body.transform()));
+                let color = Self::body_color(&style, &body);
+                for (_, fixture) in body.fixtures() {
+                    let fixture = fixture.borrow();
+                    let color = if fixture.is_sensor() {
+                        style.sensor_color
+                    } else {
+                        color
+                    };
+                    self.fixture(&fixture.shape(), color);
+                }
+            }
+            self.batch.set_transform(math::identity());
+        }
+        if style.joints {
+            for (_, joint) in world.joints() {
+                self.joint(world, &joint.borrow());
+            }
+        }
+        if style.aabbs {
+            for (_, body) in world.bodies() {
+                for (_, fixture) in body.borrow().fixtures() {
+                    let fixture = fixture.borrow();
+                    for child in 0..fixture.shape().child_count() {
+                        let aabb = fixture.aabb(child);
+                        let (lower, upper) = (vec2(&aabb.lower), vec2(&aabb.upper));
+                        self.polygon(
+                            &[lower, [upper[0], lower[1]], upper, [lower[0], upper[1]]],
+                            style.aabb_color,
+                            false,
+                        );
+                    }
+                }
+            }
+        }
+        if style.contacts {
+            for contact in world.contacts() {
+                if contact.is_touching() {
+                    let count = contact.manifold().count as usize;
+                    let manifold = contact.world_manifold();
+                    for point in &manifold.points[..count] {
+                        self.point(vec2(point), style.contact_color);
+                    }
+                }
+            }
+        }
+        if style.centers_of_mass {
+            for (_, body) in world.bodies() {
+                let body = body.borrow();
+                let center = vec2(body.world_center());
+                let rot = body.transform().rot;
+                let axis =
+                    vec2_scale([rot.cos as Scalar, rot.sin as Scalar], style.axis_length);
+                let color = style.center_of_mass_color;
+                self.segment(center, vec2_add(center, axis), color);
+                self.segment(center, vec2_add(center, [-axis[1], axis[0]]), color);
+                self.point(center, color);
+            }
+        }
+    }
+
+    fn body_color(style: &DebugDrawStyle, body: &b2::Body) -> Color {
+        if !body.is_active() {
+            return style.inactive_color;
+        }
+        match body.body_type() {
+            b2::BodyType::Static => style.static_color,
+            b2::BodyType::Kinematic => style.kinematic_color,
+            b2::BodyType::Dynamic if !body.is_awake() => style.sleeping_color,
+            b2::BodyType::Dynamic => style.dynamic_color,
+        }
+    }
+
+    /// Fixture shape, in the coordinates of its body.
+    fn fixture(&mut self, shape: &b2::UnknownShape, color: Color) {
+        match *shape {
+            b2::UnknownShape::Polygon(ref polygon) => {
+                let vertices: Vec<Vec2d> = (0..polygon.vertex_count())
+                    .map(|i| vec2(polygon.vertex(i)))
+                    .collect();
+                self.polygon(&vertices, color, true);
+            }
+            b2::UnknownShape::Circle(ref circle) => {
+                let center = vec2(&circle.position());
+                let radius = circle.radius() as Scalar;
+                let segments = self.style.circle_segments.max(3);
+                let vertices: Vec<Vec2d> = (0..segments)
+                    .map(|i| {
+                        let angle = i as Scalar * 2.0 * consts::PI / segments as Scalar;
+                        vec2_add(center, [radius * angle.cos(), radius * angle.sin()])
+                    })
+                    .collect();
+                self.polygon(&vertices, color, true);
+                self.segment(center, vertices[0], color);
+            }
+            b2::UnknownShape::Edge(ref edge) => {
+                self.segment(vec2(&edge.v1()), vec2(&edge.v2()), color);
+            }
+            b2::UnknownShape::Chain(ref chain) => {
+                for pair in chain.vertices().windows(2) {
+                    self.segment(vec2(&pair[0]), vec2(&pair[1]), color);
+                }
+            }
+            b2::UnknownShape::Unknown => {}
+        }
+    }
+
+    fn joint<U: UserDataTypes>(
+        &mut self,
+        world: &b2::World<U>,
+        joint: &b2::MetaJoint<U>,
+    ) {
+        let color = self.style.joint_color;
+        let (anchor_a, anchor_b) = (vec2(&joint.anchor_a()), vec2(&joint.anchor_b()));
+        match **joint {
+            b2::UnknownJoint::Distance(_)
+            | b2::UnknownJoint::Mouse(_)
+            | b2::UnknownJoint::Rope(_) => {
+                self.segment(anchor_a, anchor_b, color);
+            }
+            b2::UnknownJoint::Pulley(ref pulley) => {
+                let ground_a = vec2(&pulley.ground_anchor_a());
+                let ground_b = vec2(&pulley.ground_anchor_b());
+                self.segment(ground_a, anchor_a, color);
+                self.segment(ground_b, anchor_b, color);
+                self.segment(ground_a, ground_b, color);
+            }
+            b2::UnknownJoint::Unknown => return,
+            _ => {
+                let position_a = vec2(world.body(joint.body_a()).position());
+                let position_b = vec2(world.body(joint.body_b()).position());
+                self.segment(position_a, anchor_a, color);
+                self.segment(anchor_a, anchor_b, color);
+                self.segment(position_b, anchor_b, color);
+            }
+        }
+        self.point(anchor_a, color);
+        self.point(anchor_b, color);
+    }
+
+    /// Closed outline of `vertices`, filled as a fan if `fill` is set.
+    fn polygon(&mut self, vertices: &[Vec2d], color: Color, fill: bool) {
+        if fill {
+            let fill_color = [
+                color[0],
+                color[1],
+                color[2],
+                color[3] * self.style.fill_alpha,
+            ];
+            for i in 2..vertices.len() {
+                self.batch.triangle(
+                    [vertices[0], vertices[i - 1], vertices[i]],
+                    [[0.0, 0.0]; 3],
+                    fill_color,
+                );
+            }
+        }
+        for (i, &vertex) in vertices.iter().enumerate() {
+            self.segment(vertex, vertices[(i + 1) % vertices.len()], color);
+        }
+    }
+
+    fn segment(&mut self, from: Vec2d, to: Vec2d, color: Color) {
+        let radius = self.style.line_width / 2.0;
+        self.batch
+            .line(from, to, radius, [0.0, 0.0, 1.0, 1.0], color);
+    }
+
+    fn point(&mut self, position: Vec2d, color: Color) {
+        let radius = self.style.point_size / 2.0;
+        self.batch.rect(
+            math::identity(),
+            rectangle::centered_square(position[0], position[1], radius),
+            [0.0, 0.0, 1.0, 1.0],
+            color,
+        );
+    }
+}
+
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline]
+fn vec2(vec: &b2::Vec2) -> Vec2d {
+    [vec.x as Scalar, vec.y as Scalar]
+}
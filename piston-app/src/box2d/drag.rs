@@ -0,0 +1,286 @@
+//! Grabbing and throwing bodies with the mouse.
+
+use super::*;
+
+/// Drags the dynamic body under the cursor with a `b2::MouseJointDef` while a
+/// mouse button is held, the button being pressed over the body.
+///
+/// On release, the body keeps the velocity the joint gave it, so it can be
+/// thrown. Bodies destroyed while dragged, by `PhysicsWorld::destroy_body()` or
+/// its despawn rules, are released.
+///
+/// The body the joints are attached to is created in the first world a body is
+/// grabbed in, so `reset()` must be called when that world is replaced.
+#[derive(Debug)]
+pub struct MouseDrag<E> {
+    button: MouseButton,
+    max_force: f32,
+    frequency: f32,
+    damping_ratio: f32,
+    ground: Option<b2::BodyHandle>,
+    dragged: Option<(b2::JointHandle, b2::BodyHandle, Option<E>)>,
+    button_down: bool,
+}
+
+impl<E: Copy + PartialEq> MouseDrag<E> {
+    pub fn new() -> Self {
+        MouseDrag {
+            button: MouseButton::Left,
+            max_force: 1000.0,
+            frequency: 5.0,
+            damping_ratio: 0.7,
+            ground: None,
+            dragged: None,
+            button_down: false,
+        }
+    }
+
+    /// Button grabbing bodies, `MouseButton::Left` by default.
+    pub fn button(mut self, button: MouseButton) -> Self {
+        self.button = button;
+        self
+    }
+
+    /// Maximum force of the joint per kilogram of the dragged body, 1000 by
+    /// default.
+    pub fn max_force(mut self, max_force: f32) -> Self {
+        self.max_force = max_force;
+        self
+    }
+
+    /// Response speed of the joint in Hertz, 5 by default, and its damping
+    /// ratio, 0.7 by default.
+    pub fn stiffness(mut self, frequency: f32, damping_ratio: f32) -> Self {
+        self.frequency = frequency;
+        self.damping_ratio = damping_ratio;
+        self
+    }
+
+    #[inline]
+    pub fn is_dragging(&self) -> bool {
+        self.dragged.is_some()
+    }
+
+    #[inline]
+    pub fn dragged_body(&self) -> Option<b2::BodyHandle> {
+        self.dragged.map(|(_, body, _)| body)
+    }
+
+    #[inline]
+    pub fn dragged_entity(&self) -> Option<E> {
+        self.dragged.and_then(|(_, _, entity)| entity)
+    }
+
+    /// Forgets the dragged body and the body joints are attached to, for use
+    /// with a new world.
+    pub fn reset(&mut self) {
+        self.ground = None;
+        self.dragged = None;
+        self.button_down = false;
+    }
+
+    /// Grabs, moves or releases the dragged body according to the mouse, to
+    /// call before stepping `world`. Returns whether a body is being dragged.
+    pub fn update(
+        &mut self,
+        world: &mut PhysicsWorld<E>,
+        state: &PistonAppState,
+    ) -> bool {
+        let pressed = state.mouse_button_pressed(self.button);
+        let target = world.mouse_position(state);
+        if let Some((joint, _, _)) = self.dragged {
+            if !Self::joint_exists(world, joint) {
+                self.dragged = None;
+            }
+        }
+        match self.dragged {
+            Some((joint, _, _)) if pressed => {
+                if let b2::UnknownJoint::Mouse(ref mut joint) = **world.joint_mut(joint) {
+                    joint.set_target(&target);
+                }
+            }
+            Some(_) => self.release(world),
+            None if pressed && !self.button_down => self.grab(world, &target),
+            None => {}
+        }
+        self.button_down = pressed;
+        self.is_dragging()
+    }
+
+    /// Lets go of the dragged body, if any.
+    pub fn release(&mut self, world: &mut PhysicsWorld<E>) {
+        if let Some((joint, _, _)) = self.dragged.take() {
+            if Self::joint_exists(world, joint) {
+                world.destroy_joint(joint);
+            }
+        }
+    }
+
+    /// Whether `joint` is still in `world`: destroying a body also destroys its
+    /// joints, leaving their handles stale.
+    fn joint_exists(world: &PhysicsWorld<E>, joint: b2::JointHandle) -> bool {
+        world.world().joints().any(|(handle, _)| handle == joint)
+    }
+
+    fn grab(&mut self, world: &mut PhysicsWorld<E>, target: &b2::Vec2) {
+        let body = match Self::body_at(world, target) {
+            Some(body) => body,
+            None => return,
+        };
+        let ground = match self.ground {
+            Some(ground) => ground,
+            None => {
+                let ground = world.create_body(&b2::BodyDef::new(), None);
+                self.ground = Some(ground);
+                ground
+            }
+        };
+        let mass = world.body(body).mass();
+        let joint = world.create_joint(&b2::MouseJointDef {
+            target: *target,
+            max_force: self.max_force * mass,
+            frequency: self.frequency,
+            damping_ratio: self.damping_ratio,
+            ..b2::MouseJointDef::new(ground, body)
+        });
+        world.body_mut(body).set_awake(true);
+        self.dragged = Some((joint, body, world.entity(body)));
+    }
+
+    /// First dynamic body with a fixture containing `point`.
+    fn body_at(world: &PhysicsWorld<E>, point: &b2::Vec2) -> Option<b2::BodyHandle> {
+        const EXTENT: f32 = 0.001;
+        let aabb = b2::AABB {
+            lower: b2::Vec2 {
+                x: point.x - EXTENT,
+                y: point.y - EXTENT,
+            },
+            upper: b2::Vec2 {
+                x: point.x + EXTENT,
+                y: point.y + EXTENT,
+            },
+        };
+        let mut found = None;
+        world.world().query_aabb(
+            &mut |body_handle, fixture_handle| {
+                let body = world.body(body_handle);
+                if body.body_type() == b2::BodyType::Dynamic
+                    && body.fixture(fixture_handle).test_point(point)
+                {
+                    found = Some(body_handle);
+                    false
+                } else {
+                    true
+                }
+            },
+            &aabb,
+        );
+        found
+    }
+}
+
+impl<E: Copy + PartialEq> Default for MouseDrag<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// World without gravity holding a box of entity 7 at (0, 5), which is at
+    /// (320, 320) in a 640 by 480 window.
+    fn world_with_box() -> (PhysicsWorld<u32>, b2::BodyHandle) {
+        let mut world = PhysicsWorld::new(&b2::Vec2 { x: 0.0, y: 0.0 });
+        let def = b2::BodyDef {
+            body_type: b2::BodyType::Dynamic,
+            position: b2::Vec2 { x: 0.0, y: 5.0 },
+            ..b2::BodyDef::new()
+        };
+        let body = world.create_body(&def, Some(7));
+        world
+            .body_mut(body)
+            .create_fast_fixture(&b2::PolygonShape::new_box(0.5, 0.5), 1.0);
+        (world, body)
+    }
+
+    fn move_mouse(state: &mut PistonAppState, x: Scalar, y: Scalar, pressed: bool) {
+        state.mouse_x = x;
+        state.mouse_y = y;
+        if pressed {
+            state.pressed_mouse_buttons.insert(MouseButton::Left);
+        } else {
+            state.pressed_mouse_buttons.remove(&MouseButton::Left);
+        }
+    }
+
+    #[test]
+    fn drags_and_throws_the_body_under_the_cursor() {
+        let mut state = PistonAppState::with_size(640, 480);
+        let (mut world, body) = world_with_box();
+        let mut drag = MouseDrag::new();
+        move_mouse(&mut state, 320.0, 320.0, true);
+        assert!(drag.update(&mut world, &state));
+        assert_eq!(drag.dragged_body(), Some(body));
+        assert_eq!(drag.dragged_entity(), Some(7));
+
+        move_mouse(&mut state, 384.0, 320.0, true);
+        for _ in 0..10 {
+            assert!(drag.update(&mut world, &state));
+            world.step(1.0 / 60.0, &state);
+        }
+        assert!(world.body(body).position().x > 0.0);
+
+        move_mouse(&mut state, 384.0, 320.0, false);
+        assert!(!drag.update(&mut world, &state));
+        assert_eq!(drag.dragged_entity(), None);
+        assert_eq!(world.world().joints().count(), 0);
+        assert!(world.body(body).linear_velocity().x > 0.0);
+    }
+
+    #[test]
+    fn grabs_only_when_pressed_over_a_body() {
+        let mut state = PistonAppState::with_size(640, 480);
+        let (mut world, body) = world_with_box();
+        let mut drag = MouseDrag::new();
+        move_mouse(&mut state, 100.0, 100.0, true);
+        assert!(!drag.update(&mut world, &state));
+        move_mouse(&mut state, 320.0, 320.0, true);
+        assert!(!drag.update(&mut world, &state));
+        move_mouse(&mut state, 320.0, 320.0, false);
+        assert!(!drag.update(&mut world, &state));
+        move_mouse(&mut state, 320.0, 320.0, true);
+        assert!(drag.update(&mut world, &state));
+        assert_eq!(drag.dragged_body(), Some(body));
+    }
+
+    #[test]
+    fn releases_destroyed_bodies() {
+        let mut state = PistonAppState::with_size(640, 480);
+        let (mut world, body) = world_with_box();
+        let mut drag = MouseDrag::new();
+        move_mouse(&mut state, 320.0, 320.0, true);
+        assert!(drag.update(&mut world, &state));
+        world.destroy_body(body);
+        assert!(!drag.update(&mut world, &state));
+        assert!(!drag.is_dragging());
+    }
+
+    #[test]
+    fn reset_attaches_joints_in_the_new_world() {
+        let mut state = PistonAppState::with_size(640, 480);
+        let (mut world, _) = world_with_box();
+        let mut drag = MouseDrag::new();
+        move_mouse(&mut state, 320.0, 320.0, true);
+        assert!(drag.update(&mut world, &state));
+
+        let (mut world, body) = world_with_box();
+        drag.reset();
+        assert!(!drag.is_dragging());
+        assert!(drag.update(&mut world, &state));
+        assert_eq!(drag.dragged_body(), Some(body));
+        // The box and the new ground body.
+        assert_eq!(world.world().bodies().count(), 2);
+    }
+}
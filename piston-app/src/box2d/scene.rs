@@ -0,0 +1,687 @@
+//! Physics scenes, loaded from JSON files.
+
+use super::*;
+
+use std::collections::HashMap;
+
+/// Description of a physics world, usually loaded from a JSON file such as:
+///
+/// ```json
+/// {
+///     "gravity": { "x": 0.0, "y": -10.0 },
+///     "despawn_below": -2.0,
+///     "boundaries": [
+///         { "box": { "position": { "x": 0.0, "y": 0.5 }, "width": 20.0, "height": 1.0 } },
+///         { "sine": { "x": -10.0, "y": 5.0, "width": 20.0, "amplitude": 1.0, "period": 4.0 } }
+///     ],
+///     "bodies": [
+///         { "name": "tower", "body_type": "static", "position": { "x": 0.0, "y": 3.0 },
+///           "fixtures": [{ "shape": { "box": { "width": 0.5, "height": 4.0 } } }] },
+///         { "name": "sail", "position": { "x": 0.0, "y": 5.0 },
+///           "fixtures": [{ "shape": { "box": { "width": 6.0, "height": 0.5 } } }] }
+///     ],
+///     "joints": [
+///         { "revolute": { "body_a": "tower", "body_b": "sail",
+///                         "anchor": { "x": 0.0, "y": 5.0 },
+///                         "motor": { "speed": 6.28, "max_torque": 1024.0 } } }
+///     ],
+///     "spawn": [
+///         { "fixtures": [{ "shape": { "circle": { "radius": 0.3 } },
+///                          "restitution": 0.42 }] }
+///     ]
+/// }
+/// ```
+///
+/// Positions and sizes are in metres, in the coordinates of `PhysicsWorld`, and
+/// angles in radians. Missing fields take the defaults of `Scene::default()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scene {
+    pub gravity: Vector2,
+    pub pixels_per_meter: f32,
+    /// Despawn rule applied to spawned bodies, see `PhysicsWorld::despawn_below()`.
+    pub despawn_below: Option<f32>,
+    pub boundaries: Vec<SceneBoundary>,
+    pub bodies: Vec<SceneBody>,
+    pub joints: Vec<SceneJoint>,
+    /// Bodies spawned on demand, at a given position rather than their own.
+    pub spawn: Vec<SceneBody>,
+}
+
+impl Scene {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let scene: Scene = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let scene: Scene = serde_json::from_str(json)?;
+        scene.validate()?;
+        Ok(scene)
+    }
+
+    /// Empty world with the gravity, scale and despawn rule of the scene.
+    pub fn world<E: Copy>(&self) -> PhysicsWorld<E> {
+        let world = PhysicsWorld::new(&vec2(self.gravity))
+            .pixels_per_meter(self.pixels_per_meter);
+        match self.despawn_below {
+            Some(y) => world.despawn_below(y),
+            None => world,
+        }
+    }
+
+    /// Creates the boundaries, bodies and joints of the scene in `world`, none of
+    /// them linked to an entity, and returns the named bodies.
+    pub fn populate<E: Copy>(
+        &self,
+        state: &PistonAppState,
+        world: &mut PhysicsWorld<E>,
+    ) -> HashMap<String, b2::BodyHandle> {
+        for boundary in &self.boundaries {
+            boundary.create(state, world);
+        }
+        let mut bodies = HashMap::new();
+        for body in &self.bodies {
+            let handle = body.create(world, vec2(body.position), None);
+            if let Some(ref name) = body.name {
+                bodies.insert(name.clone(), handle);
+            }
+        }
+        for joint in &self.joints {
+            joint.create(world, &bodies);
+        }
+        bodies
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.pixels_per_meter.is_nan() || self.pixels_per_meter <= 0.0 {
+            return Err("the scale must be positive".into());
+        }
+        let mut names = vec![];
+        for boundary in &self.boundaries {
+            boundary.validate()?;
+        }
+        for body in self.bodies.iter().chain(&self.spawn) {
+            body.validate()?;
+        }
+        for name in self.bodies.iter().filter_map(|body| body.name.as_ref()) {
+            if names.contains(&name) {
+                return Err(format!("duplicate body name {:?}", name).into());
+            }
+            names.push(name);
+        }
+        for joint in &self.joints {
+            let (body_a, body_b) = joint.bodies();
+            if body_a == body_b {
+                return Err(format!("joint between {:?} and itself", body_a).into());
+            }
+            for name in &[body_a, body_b] {
+                if !names.contains(name) {
+                    return Err(format!("unknown joint body {:?}", name).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            gravity: Vector2::new(0.0, -10.0),
+            pixels_per_meter: PIXELS_PER_METER,
+            despawn_below: None,
+            boundaries: vec![],
+            bodies: vec![],
+            joints: vec![],
+            spawn: vec![],
+        }
+    }
+}
+
+/// Static boundary of a `Scene`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneBoundary {
+    /// Rectangle centred on `position`.
+    Box {
+        position: Vector2,
+        width: f32,
+        height: f32,
+        #[serde(default)]
+        angle: f32,
+    },
+    /// Chain of segments through the given points, at least `b2::LINEAR_SLOP`
+    /// apart.
+    Slope(Vec<Vector2>),
+    /// Sine wave starting at (`x`, `y`), `period` metres long.
+    Sine {
+        x: f32,
+        y: f32,
+        width: f32,
+        amplitude: f32,
+        period: f32,
+    },
+    /// Perlin noise starting at (`x`, `y`), `scale` being the noise
+    /// increment per metre.
+    Noise {
+        x: f32,
+        y: f32,
+        width: f32,
+        amplitude: f32,
+        scale: f32,
+    },
+}
+
+impl SceneBoundary {
+    /// Spacing of the vertices of sine and noise chains, and their minimum width.
+    const STEP: f32 = 0.1;
+    /// Maximum width of sine and noise chains, bounding their vertices to
+    /// `MAX_WIDTH / STEP`.
+    const MAX_WIDTH: f32 = 1000.0;
+
+    /// Creates the static body of the boundary.
+    pub fn create<E: Copy>(
+        &self,
+        state: &PistonAppState,
+        world: &mut PhysicsWorld<E>,
+    ) -> b2::BodyHandle {
+        let handle = world.create_body(&b2::BodyDef::new(), None);
+        let mut body = world.body_mut(handle);
+        match *self {
+            SceneBoundary::Box {
+                position,
+                width,
+                height,
+                angle,
+            } => {
+                let shape = b2::PolygonShape::new_oriented_box(
+                    width / 2.0,
+                    height / 2.0,
+                    &vec2(position),
+                    angle,
+                );
+                body.create_fast_fixture(&shape, 0.0);
+            }
+            SceneBoundary::Slope(ref points) => {
+                let vertices: Vec<b2::Vec2> = points.iter().map(|&p| vec2(p)).collect();
+                body.create_fast_fixture(&b2::ChainShape::new_chain(&vertices), 0.0);
+            }
+            SceneBoundary::Sine {
+                x,
+                y,
+                width,
+                amplitude,
+                period,
+            } => {
+                let dx = consts::PI as f32 * 2.0 / period;
+                let vertices = Self::chain(x, y, width, |x| (x * dx).sin() * amplitude);
+                body.create_fast_fixture(&b2::ChainShape::new_chain(&vertices), 0.0);
+            }
+            SceneBoundary::Noise {
+                x,
+                y,
+                width,
+                amplitude,
+                scale,
+            } => {
+                let vertices = Self::chain(x, y, width, |x| {
+                    let noise = state.noise(&[(x * scale) as Scalar]) as f32;
+                    (noise * 2.0 - 1.0) * amplitude
+                });
+                body.create_fast_fixture(&b2::ChainShape::new_chain(&vertices), 0.0);
+            }
+        }
+        handle
+    }
+
+    /// Vertices from (`x`, `y`) over `width` metres, each raised by `offset()` of
+    /// its horizontal distance to the first one.
+    fn chain<F: Fn(f32) -> f32>(x: f32, y: f32, width: f32, offset: F) -> Vec<b2::Vec2> {
+        (0..=(width / Self::STEP) as usize)
+            .map(|i| {
+                let dx = i as f32 * Self::STEP;
+                b2::Vec2 {
+                    x: x + dx,
+                    y: y + offset(dx),
+                }
+            })
+            .collect()
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let valid = match *self {
+            SceneBoundary::Box { width, height, .. } => width > 0.0 && height > 0.0,
+            SceneBoundary::Slope(ref points) => {
+                points.len() >= 2
+                    && points
+                        .windows(2)
+                        .all(|pair| pair[0].dist(pair[1]) > b2::LINEAR_SLOP as Scalar)
+            }
+            SceneBoundary::Sine { width, period, .. } => {
+                Self::is_chain_width(width) && period > 0.0
+            }
+            SceneBoundary::Noise { width, .. } => Self::is_chain_width(width),
+        };
+        if !valid {
+            return Err(format!("invalid boundary {:?}", self).into());
+        }
+        Ok(())
+    }
+
+    fn is_chain_width(width: f32) -> bool {
+        (Self::STEP..=Self::MAX_WIDTH).contains(&width)
+    }
+}
+
+/// Type of a `SceneBody`, mirroring `b2::BodyType`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneBodyType {
+    Static,
+    Kinematic,
+    Dynamic,
+}
+
+impl From<SceneBodyType> for b2::BodyType {
+    fn from(body_type: SceneBodyType) -> Self {
+        match body_type {
+            SceneBodyType::Static => b2::BodyType::Static,
+            SceneBodyType::Kinematic => b2::BodyType::Kinematic,
+            SceneBodyType::Dynamic => b2::BodyType::Dynamic,
+        }
+    }
+}
+
+/// Body of a `Scene`, dynamic by default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneBody {
+    /// Name joints refer to the body by.
+    pub name: Option<String>,
+    pub body_type: SceneBodyType,
+    pub position: Vector2,
+    pub angle: f32,
+    pub linear_velocity: Vector2,
+    pub angular_velocity: f32,
+    pub fixtures: Vec<SceneFixture>,
+}
+
+impl SceneBody {
+    /// Creates the body at `position` rather than its own, linked to `entity`.
+    pub fn create<E: Copy>(
+        &self,
+        world: &mut PhysicsWorld<E>,
+        position: b2::Vec2,
+        entity: Option<E>,
+    ) -> b2::BodyHandle {
+        let handle = world.create_body(
+            &b2::BodyDef {
+                body_type: self.body_type.into(),
+                position: position,
+                angle: self.angle,
+                linear_velocity: vec2(self.linear_velocity),
+                angular_velocity: self.angular_velocity,
+                ..b2::BodyDef::new()
+            },
+            entity,
+        );
+        let mut body = world.body_mut(handle);
+        for fixture in &self.fixtures {
+            let mut def = b2::FixtureDef {
+                density: fixture.density,
+                friction: fixture.friction,
+                restitution: fixture.restitution,
+                ..b2::FixtureDef::new()
+            };
+            match fixture.shape {
+                SceneShape::Box {
+                    width,
+                    height,
+                    offset,
+                    angle,
+                } => {
+                    let shape = b2::PolygonShape::new_oriented_box(
+                        width / 2.0,
+                        height / 2.0,
+                        &vec2(offset),
+                        angle,
+                    );
+                    body.create_fixture(&shape, &mut def);
+                }
+                SceneShape::Circle { radius, offset } => {
+                    let shape = b2::CircleShape::new_with(vec2(offset), radius);
+                    body.create_fixture(&shape, &mut def);
+                }
+                SceneShape::Polygon(ref points) => {
+                    let vertices: Vec<b2::Vec2> =
+                        points.iter().map(|&p| vec2(p)).collect();
+                    body.create_fixture(&b2::PolygonShape::new_with(&vertices), &mut def);
+                }
+            }
+        }
+        handle
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.fixtures.is_empty() {
+            return Err("bodies need at least one fixture".into());
+        }
+        for fixture in &self.fixtures {
+            let valid = match fixture.shape {
+                SceneShape::Box { width, height, .. } => width > 0.0 && height > 0.0,
+                SceneShape::Circle { radius, .. } => radius > 0.0,
+                SceneShape::Polygon(ref points) => is_polygon(points),
+            };
+            if !valid {
+                return Err(format!("invalid shape {:?}", fixture.shape).into());
+            }
+            if fixture.density.is_nan() || fixture.density < 0.0 {
+                return Err(format!("invalid density {}", fixture.density).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SceneBody {
+    fn default() -> Self {
+        SceneBody {
+            name: None,
+            body_type: SceneBodyType::Dynamic,
+            position: Vector2::zero(),
+            angle: 0.0,
+            linear_velocity: Vector2::zero(),
+            angular_velocity: 0.0,
+            fixtures: vec![],
+        }
+    }
+}
+
+/// Fixture of a `SceneBody`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneFixture {
+    pub shape: SceneShape,
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+}
+
+impl Default for SceneFixture {
+    fn default() -> Self {
+        SceneFixture {
+            shape: SceneShape::Box {
+                width: 1.0,
+                height: 1.0,
+                offset: Vector2::zero(),
+                angle: 0.0,
+            },
+            density: 1.0,
+            friction: 0.2,
+            restitution: 0.0,
+        }
+    }
+}
+
+/// Shape of a `SceneFixture`, relative to its body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneShape {
+    Box {
+        width: f32,
+        height: f32,
+        #[serde(default)]
+        offset: Vector2,
+        #[serde(default)]
+        angle: f32,
+    },
+    Circle {
+        radius: f32,
+        #[serde(default)]
+        offset: Vector2,
+    },
+    /// Convex polygon, with up to `b2::MAX_POLYGON_VERTICES` distinct points
+    /// that are not all aligned.
+    Polygon(Vec<Vector2>),
+}
+
+/// Joint of a `Scene`, between two named bodies. Anchors are world points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SceneJoint {
+    /// Keeps the anchors at their initial distance, springy if `frequency` is
+    /// set.
+    Distance {
+        body_a: String,
+        body_b: String,
+        anchor_a: Vector2,
+        anchor_b: Vector2,
+        #[serde(default)]
+        frequency: f32,
+        #[serde(default)]
+        damping_ratio: f32,
+    },
+    /// Pins the bodies together at `anchor`, optionally driven by a motor.
+    Revolute {
+        body_a: String,
+        body_b: String,
+        anchor: Vector2,
+        #[serde(default)]
+        motor: Option<SceneMotor>,
+    },
+}
+
+impl SceneJoint {
+    /// Creates the joint between `bodies`, which must contain both its bodies.
+    pub fn create<E: Copy>(
+        &self,
+        world: &mut PhysicsWorld<E>,
+        bodies: &HashMap<String, b2::BodyHandle>,
+    ) -> b2::JointHandle {
+        match *self {
+            SceneJoint::Distance {
+                ref body_a,
+                ref body_b,
+                anchor_a,
+                anchor_b,
+                frequency,
+                damping_ratio,
+            } => {
+                let (body_a, body_b) = (bodies[body_a], bodies[body_b]);
+                let mut def = b2::DistanceJointDef::new(body_a, body_b);
+                def.init(
+                    world.world(),
+                    body_a,
+                    body_b,
+                    &vec2(anchor_a),
+                    &vec2(anchor_b),
+                );
+                def.frequency = frequency;
+                def.damping_ratio = damping_ratio;
+                world.create_joint(&def)
+            }
+            SceneJoint::Revolute {
+                ref body_a,
+                ref body_b,
+                anchor,
+                motor,
+            } => {
+                let (body_a, body_b) = (bodies[body_a], bodies[body_b]);
+                let mut def = b2::RevoluteJointDef::new(body_a, body_b);
+                def.init(world.world(), body_a, body_b, &vec2(anchor));
+                if let Some(motor) = motor {
+                    def.enable_motor = true;
+                    def.motor_speed = motor.speed;
+                    def.max_motor_torque = motor.max_torque;
+                }
+                world.create_joint(&def)
+            }
+        }
+    }
+
+    /// Names of the bodies of the joint.
+    pub fn bodies(&self) -> (&String, &String) {
+        match *self {
+            SceneJoint::Distance {
+                ref body_a,
+                ref body_b,
+                ..
+            }
+            | SceneJoint::Revolute {
+                ref body_a,
+                ref body_b,
+                ..
+            } => (body_a, body_b),
+        }
+    }
+}
+
+/// Motor of a revolute `SceneJoint`, turning at `speed` radians per second.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneMotor {
+    pub speed: f32,
+    pub max_torque: f32,
+}
+
+#[inline]
+fn vec2(vector: Vector2) -> b2::Vec2 {
+    b2::Vec2 {
+        x: vector.x as f32,
+        y: vector.y as f32,
+    }
+}
+
+/// Whether Box2D accepts `points` as a polygon: it welds points closer than
+/// `b2::LINEAR_SLOP`, and needs some area left.
+fn is_polygon(points: &[Vector2]) -> bool {
+    if points.len() < 3 || points.len() > b2::MAX_POLYGON_VERTICES {
+        return false;
+    }
+    let slop = b2::LINEAR_SLOP as Scalar;
+    let mut area: Scalar = 0.0;
+    for (i, &a) in points.iter().enumerate() {
+        for (j, &b) in points.iter().enumerate().skip(i + 1) {
+            if a.dist(b) < slop {
+                return false;
+            }
+            for &c in &points[j + 1..] {
+                let (ab, ac) = (b - a, c - a);
+                area = area.max((ab.x * ac.y - ab.y * ac.x).abs() / 2.0);
+            }
+        }
+    }
+    area > slop * slop
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boundary(json: &str) -> Result<Scene, Box<dyn Error>> {
+        Scene::from_json(&format!("{{ \"boundaries\": [{}] }}", json))
+    }
+
+    fn polygon(points: &str) -> Result<Scene, Box<dyn Error>> {
+        Scene::from_json(&format!(
+            "{{ \"bodies\": [{{ \"fixtures\": [{{ \"shape\": {{ \"polygon\": {} }} }}] }}] }}",
+            points
+        ))
+    }
+
+    fn joint(body_a: &str, body_b: &str) -> Result<Scene, Box<dyn Error>> {
+        Scene::from_json(&format!(
+            r#"{{
+                "bodies": [
+                    {{ "name": "a", "fixtures": [{{}}] }},
+                    {{ "name": "b", "fixtures": [{{}}] }}
+                ],
+                "joints": [
+                    {{ "revolute": {{ "body_a": "{}", "body_b": "{}", "anchor": {{ "x": 0.0, "y": 0.0 }} }} }}
+                ]
+            }}"#,
+            body_a, body_b
+        ))
+    }
+
+    #[test]
+    fn accepts_valid_scene() {
+        let scene = Scene::from_json(
+            r#"{
+                "boundaries": [
+                    { "slope": [{ "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 1.0 }] },
+                    { "sine": { "x": 0.0, "y": 0.0, "width": 0.1, "amplitude": 1.0, "period": 1.0 } }
+                ],
+                "bodies": [
+                    { "fixtures": [{ "shape": { "polygon": [
+                        { "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }, { "x": 0.0, "y": 1.0 }
+                    ] } }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scene.boundaries.len(), 2);
+        assert_eq!(scene.bodies.len(), 1);
+        assert!(joint("a", "b").is_ok());
+    }
+
+    #[test]
+    fn rejects_chains_narrower_than_a_step() {
+        let sine = r#"{ "sine": { "x": 0.0, "y": 0.0, "width": 0.05, "amplitude": 1.0, "period": 1.0 } }"#;
+        let noise = r#"{ "noise": { "x": 0.0, "y": 0.0, "width": 0.05, "amplitude": 1.0, "scale": 1.0 } }"#;
+        assert!(boundary(sine).is_err());
+        assert!(boundary(noise).is_err());
+    }
+
+    #[test]
+    fn rejects_chains_too_wide() {
+        let sine = r#"{ "sine": { "x": 0.0, "y": 0.0, "width": 1e9, "amplitude": 1.0, "period": 1.0 } }"#;
+        let noise = r#"{ "noise": { "x": 0.0, "y": 0.0, "width": 1e9, "amplitude": 1.0, "scale": 1.0 } }"#;
+        assert!(boundary(sine).is_err());
+        assert!(boundary(noise).is_err());
+        let widest = r#"{ "noise": { "x": 0.0, "y": 0.0, "width": 1000.0, "amplitude": 1.0, "scale": 1.0 } }"#;
+        assert!(boundary(widest).is_ok());
+    }
+
+    #[test]
+    fn rejects_negative_densities() {
+        let body = |density: &str| {
+            Scene::from_json(&format!(
+                "{{ \"bodies\": [{{ \"fixtures\": [{{ \"density\": {} }}] }}] }}",
+                density
+            ))
+        };
+        assert!(body("0.0").is_ok());
+        assert!(body("-1.0").is_err());
+    }
+
+    #[test]
+    fn rejects_slopes_with_close_points() {
+        assert!(boundary(r#"{ "slope": [{ "x": 0.0, "y": 0.0 }] }"#).is_err());
+        let slope = r#"{ "slope": [
+            { "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }, { "x": 1.001, "y": 0.0 }
+        ] }"#;
+        assert!(boundary(slope).is_err());
+    }
+
+    #[test]
+    fn rejects_joints_between_a_body_and_itself() {
+        assert!(joint("a", "a").is_err());
+        assert!(joint("a", "c").is_err());
+    }
+
+    #[test]
+    fn rejects_degenerate_polygons() {
+        let too_few = r#"[{ "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }]"#;
+        let duplicate = r#"[
+            { "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }, { "x": 1.0, "y": 0.0 }, { "x": 0.0, "y": 1.0 }
+        ]"#;
+        let collinear =
+            r#"[{ "x": 0.0, "y": 0.0 }, { "x": 1.0, "y": 1.0 }, { "x": 2.0, "y": 2.0 }]"#;
+        assert!(polygon(too_few).is_err());
+        assert!(polygon(duplicate).is_err());
+        assert!(polygon(collinear).is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! Box2D world wrapper, in pixels and metres.
+
+use super::*;
+
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+
+use super::contact::{ContactQueue, ContactRecorder};
+
+/// Default scale between the window and the world.
+pub const PIXELS_PER_METER: f32 = 32.0;
+
+/// Bodies linked to an entity identifier `E`, shared with the contact recorder.
+///
+/// Entities are not kept in the `wrapped2d` user data of bodies: `wrapped2d`
+/// reads body handles back from Box2D at the wrong offset once body data is not
+/// `()`, so that fixtures, contacts and queries would report invalid bodies.
+pub(crate) type Entities<E> = Rc<RefCell<Vec<(b2::BodyHandle, E)>>>;
+
+/// Entity linked to the body `handle` in `entities`, if any.
+pub(crate) fn linked_entity<E: Copy>(
+    entities: &Entities<E>,
+    handle: b2::BodyHandle,
+) -> Option<E> {
+    entities
+        .borrow()
+        .iter()
+        .find(|&&(body, _)| body == handle)
+        .map(|&(_, entity)| entity)
+}
+
+/// `b2::World` whose bodies may be linked to entities, stepped at a fixed rate and
+/// mapped to the window: the origin of the world is the bottom centre of the
+/// window, y points up and distances are in metres.
+///
+/// Bodies linked to an entity are destroyed by `step()` once they fall below
+/// `despawn_below()` or leave the view by more than `despawn_outside_view()`,
+/// their entities being listed by `despawned()` until the next step.
+///
+/// Once `record_contacts()` is set, the contacts of each step are listed by
+/// `contacts()` until the next step.
+pub struct PhysicsWorld<E> {
+    world: b2::World<NoUserData>,
+    entities: Entities<E>,
+    pixels_per_meter: f32,
+    time_step: Scalar,
+    velocity_iterations: i32,
+    position_iterations: i32,
+    time: Scalar,
+    despawn_below: Option<f32>,
+    despawn_margin: Option<f32>,
+    despawned: Vec<E>,
+    contact_queue: Option<ContactQueue<E>>,
+    contacts: Vec<ContactEvent<E>>,
+}
+
+impl<E: Copy> PhysicsWorld<E> {
+    pub fn new(gravity: &b2::Vec2) -> Self {
+        PhysicsWorld {
+            world: b2::World::new(gravity),
+            entities: Default::default(),
+            pixels_per_meter: PIXELS_PER_METER,
+            time_step: 1.0 / 60.0,
+            velocity_iterations: 8,
+            position_iterations: 3,
+            time: 0.0,
+            despawn_below: None,
+            despawn_margin: None,
+            despawned: vec![],
+            contact_queue: None,
+            contacts: vec![],
+        }
+    }
+
+    /// Scale between the window and the world, `PIXELS_PER_METER` by default.
+    pub fn pixels_per_meter(mut self, pixels_per_meter: f32) -> Self {
+        self.pixels_per_meter = pixels_per_meter;
+        self
+    }
+
+    /// Duration of a simulation step, in seconds, 1/60 by default.
+    pub fn time_step(mut self, time_step: Scalar) -> Self {
+        self.time_step = time_step;
+        self
+    }
+
+    /// Solver iterations per step, 8 and 3 by default.
+    pub fn iterations(mut self, velocity: i32, position: i32) -> Self {
+        self.velocity_iterations = velocity;
+        self.position_iterations = position;
+        self
+    }
+
+    /// Despawns entities whose body falls below `y`.
+    pub fn despawn_below(mut self, y: f32) -> Self {
+        self.despawn_below = Some(y);
+        self
+    }
+
+    /// Despawns entities whose body is further than `margin` outside the view.
+    pub fn despawn_outside_view(mut self, margin: f32) -> Self {
+        self.despawn_margin = Some(margin);
+        self
+    }
+
+    /// Records the contacts of each step: when fixtures begin and end touching,
+    /// and the impact ending their approach, if of at least `min_impulse`
+    /// newton-seconds.
+    pub fn record_contacts(mut self, min_impulse: f32) -> Self
+    where
+        E: 'static,
+    {
+        let queue = ContactQueue::default();
+        let recorder =
+            ContactRecorder::new(queue.clone(), self.entities.clone(), min_impulse);
+        self.world.set_contact_listener(Box::new(recorder));
+        self.contact_queue = Some(queue);
+        self
+    }
+
+    #[inline]
+    pub fn world(&self) -> &b2::World<NoUserData> {
+        &self.world
+    }
+
+    /// The Box2D world, whose bodies linked to an entity must be destroyed with
+    /// `destroy_body()` instead.
+    #[inline]
+    pub fn world_mut(&mut self) -> &mut b2::World<NoUserData> {
+        &mut self.world
+    }
+
+    #[inline]
+    pub fn body(&self, handle: b2::BodyHandle) -> Ref<'_, b2::MetaBody<NoUserData>> {
+        self.world.body(handle)
+    }
+
+    #[inline]
+    pub fn body_mut(
+        &self,
+        handle: b2::BodyHandle,
+    ) -> RefMut<'_, b2::MetaBody<NoUserData>> {
+        self.world.body_mut(handle)
+    }
+
+    /// Creates a body linked to `entity`, if any.
+    pub fn create_body(
+        &mut self,
+        def: &b2::BodyDef,
+        entity: Option<E>,
+    ) -> b2::BodyHandle {
+        let handle = self.world.create_body(def);
+        if let Some(entity) = entity {
+            self.entities.borrow_mut().push((handle, entity));
+        }
+        handle
+    }
+
+    pub fn destroy_body(&mut self, handle: b2::BodyHandle) {
+        self.world.destroy_body(handle);
+        self.entities
+            .borrow_mut()
+            .retain(|&(body, _)| body != handle);
+    }
+
+    #[inline]
+    pub fn joint(&self, handle: b2::JointHandle) -> Ref<'_, b2::MetaJoint<NoUserData>> {
+        self.world.joint(handle)
+    }
+
+    #[inline]
+    pub fn joint_mut(
+        &self,
+        handle: b2::JointHandle,
+    ) -> RefMut<'_, b2::MetaJoint<NoUserData>> {
+        self.world.joint_mut(handle)
+    }
+
+    pub fn create_joint<D: b2::JointDef>(&mut self, def: &D) -> b2::JointHandle {
+        self.world.create_joint(def)
+    }
+
+    pub fn destroy_joint(&mut self, handle: b2::JointHandle) {
+        self.world.destroy_joint(handle);
+    }
+
+    /// Entity linked to the body `handle`, if any.
+    pub fn entity(&self, handle: b2::BodyHandle) -> Option<E> {
+        linked_entity(&self.entities, handle)
+    }
+
+    /// Entities despawned by the last `step()`.
+    #[inline]
+    pub fn despawned(&self) -> &[E] {
+        &self.despawned
+    }
+
+    /// Contacts recorded since the previous `step()`, in the order they occurred.
+    #[inline]
+    pub fn contacts(&self) -> &[ContactEvent<E>] {
+        &self.contacts
+    }
+
+    /// Advances the simulation by `dt` seconds, in as many fixed steps as fit,
+    /// the remainder being carried over to the next call, then applies the
+    /// despawn rules.
+    pub fn step(&mut self, dt: Scalar, state: &PistonAppState) {
+        self.despawned.clear();
+        self.time += dt;
+        while self.time >= self.time_step {
+            self.world.step(
+                self.time_step as f32,
+                self.velocity_iterations,
+                self.position_iterations,
+            );
+            self.world.clear_forces();
+            self.time -= self.time_step;
+        }
+        self.despawn(state);
+        self.contacts.clear();
+        if let Some(ref queue) = self.contact_queue {
+            self.contacts.append(&mut queue.borrow_mut());
+        }
+    }
+
+    /// `pixels` in metres.
+    #[inline]
+    pub fn meters(&self, pixels: Scalar) -> f32 {
+        pixels as f32 / self.pixels_per_meter
+    }
+
+    /// `meters` in pixels.
+    #[inline]
+    pub fn pixels(&self, meters: f32) -> Scalar {
+        (meters * self.pixels_per_meter) as Scalar
+    }
+
+    /// World position of the window position `position`.
+    pub fn to_world(&self, state: &PistonAppState, position: Vec2d) -> b2::Vec2 {
+        b2::Vec2 {
+            x: self.meters(position[0] - state.width() / 2.0),
+            y: self.meters(state.height() - position[1]),
+        }
+    }
+
+    /// Window position of the world position `position`.
+    pub fn to_screen(&self, state: &PistonAppState, position: &b2::Vec2) -> Vec2d {
+        [
+            state.width() / 2.0 + self.pixels(position.x),
+            state.height() - self.pixels(position.y),
+        ]
+    }
+
+    /// World position of the mouse cursor.
+    pub fn mouse_position(&self, state: &PistonAppState) -> b2::Vec2 {
+        self.to_world(state, [state.mouse_x(), state.mouse_y()])
+    }
+
+    /// Part of the world visible in the window.
+    pub fn view_aabb(&self, state: &PistonAppState) -> b2::AABB {
+        let half_width = self.meters(state.width() / 2.0);
+        b2::AABB {
+            lower: b2::Vec2 {
+                x: -half_width,
+                y: 0.0,
+            },
+            upper: b2::Vec2 {
+                x: half_width,
+                y: self.meters(state.height()),
+            },
+        }
+    }
+
+    /// `pipelines::world_sprites` transform uniform mapping the world to the
+    /// window.
+    pub fn sprite_transform(&self, state: &PistonAppState) -> [f32; 4] {
+        let half_width = state.width() as f32 / 2.0;
+        let half_height = state.height() as f32 / 2.0;
+        [
+            0.0,
+            -1.0,
+            self.pixels_per_meter / half_width,
+            self.pixels_per_meter / half_height,
+        ]
+    }
+
+    fn despawn(&mut self, state: &PistonAppState) {
+        if self.despawn_below.is_none() && self.despawn_margin.is_none() {
+            return;
+        }
+        let view = self.view_aabb(state);
+        let (below, margin) = (self.despawn_below, self.despawn_margin);
+        let doomed: Vec<(b2::BodyHandle, E)> = self
+            .entities
+            .borrow()
+            .iter()
+            .copied()
+            .filter_map(|(handle, entity)| {
+                let body = self.world.body(handle);
+                let position = body.position();
+                let fallen = below.is_some_and(|y| position.y < y);
+                let gone = margin.is_some_and(|margin| {
+                    position.x < view.lower.x - margin
+                        || position.x > view.upper.x + margin
+                        || position.y < view.lower.y - margin
+                        || position.y > view.upper.y + margin
+                });
+                if fallen || gone {
+                    Some((handle, entity))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for (handle, entity) in doomed {
+            self.destroy_body(handle);
+            self.despawned.push(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_GRAVITY: b2::Vec2 = b2::Vec2 { x: 0.0, y: 0.0 };
+
+    fn body_at(world: &mut PhysicsWorld<u32>, x: f32, y: f32, entity: Option<u32>) {
+        let def = b2::BodyDef {
+            body_type: b2::BodyType::Dynamic,
+            position: b2::Vec2 { x, y },
+            ..b2::BodyDef::new()
+        };
+        world.create_body(&def, entity);
+    }
+
+    #[test]
+    fn maps_the_window_to_the_world() {
+        let state = PistonAppState::with_size(640, 480);
+        let world = PhysicsWorld::<u32>::new(&NO_GRAVITY);
+        let origin = world.to_world(&state, [320.0, 480.0]);
+        assert_eq!((origin.x, origin.y), (0.0, 0.0));
+        let point = world.to_world(&state, [352.0, 416.0]);
+        assert_eq!((point.x, point.y), (1.0, 2.0));
+        assert_eq!(world.to_screen(&state, &point), [352.0, 416.0]);
+        assert_eq!(world.meters(64.0), 2.0);
+        assert_eq!(world.pixels(2.0), 64.0);
+
+        let scaled = PhysicsWorld::<u32>::new(&NO_GRAVITY).pixels_per_meter(64.0);
+        let position = [100.0, 300.0];
+        assert_eq!(
+            scaled.to_screen(&state, &scaled.to_world(&state, position)),
+            position
+        );
+    }
+
+    #[test]
+    fn view_aabb_covers_the_window() {
+        let state = PistonAppState::with_size(640, 480);
+        let view = PhysicsWorld::<u32>::new(&NO_GRAVITY).view_aabb(&state);
+        assert_eq!((view.lower.x, view.lower.y), (-10.0, 0.0));
+        assert_eq!((view.upper.x, view.upper.y), (10.0, 15.0));
+    }
+
+    #[test]
+    fn steps_at_a_fixed_rate() {
+        let state = PistonAppState::with_size(640, 480);
+        let mut world = PhysicsWorld::<u32>::new(&NO_GRAVITY).time_step(0.25);
+        let def = b2::BodyDef {
+            body_type: b2::BodyType::Dynamic,
+            linear_velocity: b2::Vec2 { x: 1.0, y: 0.0 },
+            ..b2::BodyDef::new()
+        };
+        let body = world.create_body(&def, None);
+        world.step(0.125, &state);
+        assert_eq!(world.body(body).position().x, 0.0);
+        world.step(0.125, &state);
+        assert_eq!(world.body(body).position().x, 0.25);
+        // Two steps, the remaining 0.125 s being carried over.
+        world.step(0.625, &state);
+        assert_eq!(world.body(body).position().x, 0.75);
+        world.step(0.125, &state);
+        assert_eq!(world.body(body).position().x, 1.0);
+    }
+
+    #[test]
+    fn links_bodies_to_entities() {
+        let mut world = PhysicsWorld::new(&NO_GRAVITY);
+        let def = b2::BodyDef {
+            body_type: b2::BodyType::Dynamic,
+            ..b2::BodyDef::new()
+        };
+        let body = world.create_body(&def, Some(7));
+        let boundary = world.create_body(&b2::BodyDef::new(), None);
+        let fixture = world.body_mut(body).create_fast_fixture(
+            &b2::CircleShape::new_with(b2::Vec2 { x: 0.0, y: 0.0 }, 1.0),
+            1.0,
+        );
+        // Box2D reports fixtures by body handle, which must be the one created.
+        assert_eq!(world.body(body).fixture(fixture).body(), body);
+        assert_eq!(world.entity(body), Some(7));
+        assert_eq!(world.entity(boundary), None);
+        world.destroy_body(body);
+        assert_eq!(world.entity(body), None);
+    }
+
+    #[test]
+    fn despawns_fallen_entities() {
+        let state = PistonAppState::with_size(640, 480);
+        let mut world = PhysicsWorld::new(&NO_GRAVITY).despawn_below(-1.0);
+        body_at(&mut world, 0.0, -2.0, Some(1));
+        body_at(&mut world, 0.0, 5.0, Some(2));
+        body_at(&mut world, 0.0, -5.0, None);
+        world.step(0.0, &state);
+        assert_eq!(world.despawned(), &[1]);
+        assert_eq!(world.world().bodies().count(), 2);
+        world.step(0.0, &state);
+        assert!(world.despawned().is_empty());
+    }
+
+    #[test]
+    fn despawns_entities_outside_the_view() {
+        let state = PistonAppState::with_size(640, 480);
+        let mut world = PhysicsWorld::new(&NO_GRAVITY).despawn_outside_view(1.0);
+        body_at(&mut world, 10.5, 5.0, Some(1));
+        body_at(&mut world, -11.5, 5.0, Some(2));
+        body_at(&mut world, 0.0, 16.5, Some(3));
+        body_at(&mut world, 0.0, -0.5, Some(4));
+        world.step(0.0, &state);
+        let mut despawned = world.despawned().to_vec();
+        despawned.sort();
+        assert_eq!(despawned, vec![2, 3]);
+        world.step(0.0, &state);
+        assert!(world.despawned().is_empty());
+    }
+}
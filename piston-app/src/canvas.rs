@@ -0,0 +1,171 @@
+//! Processing-style immediate drawing, on top of `Context` and `G2d`.
+
+use super::*;
+
+/// Drawing context mirroring the Processing drawing functions used in the book,
+/// created with `PistonAppState::canvas()` inside `PistonAppWindow::draw_2d()`.
+///
+/// Like in Processing, shapes are filled in white and stroked in black with a
+/// weight of 1 until told otherwise, ellipses are specified by their center and
+/// rectangles by their top-left corner.
+pub struct PistonAppCanvas<'a, 'b: 'a> {
+    context: Context,
+    gfx: &'a mut G2d<'b>,
+    fill: Option<Color>,
+    stroke: Option<Color>,
+    stroke_weight: Scalar,
+    resolution: Resolution,
+    matrices: Vec<Matrix2d>,
+}
+
+impl<'a, 'b> PistonAppCanvas<'a, 'b> {
+    pub(crate) fn new(context: Context, gfx: &'a mut G2d<'b>) -> Self {
+        PistonAppCanvas {
+            context: context,
+            gfx: gfx,
+            fill: Some(color::WHITE),
+            stroke: Some(color::BLACK),
+            stroke_weight: 1.0,
+            resolution: 32,
+            matrices: vec![],
+        }
+    }
+
+    /// Context with the current transformation applied, for drawing with the
+    /// `graphics` primitives directly.
+    #[inline]
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    #[inline]
+    pub fn gfx(&mut self) -> &mut G2d<'b> {
+        self.gfx
+    }
+
+    pub fn background(&mut self, color: Color) {
+        clear(color, self.gfx);
+    }
+
+    pub fn fill(&mut self, color: Color) {
+        self.fill = Some(color);
+    }
+
+    pub fn no_fill(&mut self) {
+        self.fill = None;
+    }
+
+    pub fn stroke(&mut self, color: Color) {
+        self.stroke = Some(color);
+    }
+
+    pub fn no_stroke(&mut self) {
+        self.stroke = None;
+    }
+
+    pub fn stroke_weight(&mut self, weight: Scalar) {
+        self.stroke_weight = weight;
+    }
+
+    /// Number of segments used to draw ellipses.
+    pub fn ellipse_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+    }
+
+    pub fn push_matrix(&mut self) {
+        self.matrices.push(self.context.transform);
+    }
+
+    pub fn pop_matrix(&mut self) {
+        self.context.transform = self
+            .matrices
+            .pop()
+            .expect("pop_matrix() called more times than push_matrix()");
+    }
+
+    pub fn translate(&mut self, x: Scalar, y: Scalar) {
+        self.context.transform = self.context.transform.trans(x, y);
+    }
+
+    /// Rotates by `angle` radians.
+    pub fn rotate(&mut self, angle: Scalar) {
+        self.context.transform = self.context.transform.rot_rad(angle);
+    }
+
+    pub fn scale(&mut self, sx: Scalar, sy: Scalar) {
+        self.context.transform = self.context.transform.scale(sx, sy);
+    }
+
+    pub fn ellipse(&mut self, x: Scalar, y: Scalar, width: Scalar, height: Scalar) {
+        if self.fill.is_none() && self.stroke.is_none() {
+            return;
+        }
+        Ellipse {
+            color: self.fill.unwrap_or([0.0; 4]),
+            border: self.stroke.map(|color| ellipse::Border {
+                color: color,
+                radius: self.stroke_weight / 2.0,
+            }),
+            resolution: self.resolution,
+        }
+        .draw(
+            rectangle::centered([x, y, width / 2.0, height / 2.0]),
+            &self.context.draw_state,
+            self.context.transform,
+            self.gfx,
+        );
+    }
+
+    pub fn rect(&mut self, x: Scalar, y: Scalar, width: Scalar, height: Scalar) {
+        if self.fill.is_none() && self.stroke.is_none() {
+            return;
+        }
+        Rectangle {
+            color: self.fill.unwrap_or([0.0; 4]),
+            shape: rectangle::Shape::Square,
+            border: self.stroke.map(|color| rectangle::Border {
+                color: color,
+                radius: self.stroke_weight / 2.0,
+            }),
+        }
+        .draw(
+            [x, y, width, height],
+            &self.context.draw_state,
+            self.context.transform,
+            self.gfx,
+        );
+    }
+
+    pub fn line(&mut self, x1: Scalar, y1: Scalar, x2: Scalar, y2: Scalar) {
+        if let Some(color) = self.stroke {
+            Line::new_round(color, self.stroke_weight / 2.0).draw(
+                [x1, y1, x2, y2],
+                &self.context.draw_state,
+                self.context.transform,
+                self.gfx,
+            );
+        }
+    }
+
+    pub fn triangle(
+        &mut self,
+        x1: Scalar,
+        y1: Scalar,
+        x2: Scalar,
+        y2: Scalar,
+        x3: Scalar,
+        y3: Scalar,
+    ) {
+        if let Some(color) = self.fill {
+            Polygon::new(color).draw(
+                &[[x1, y1], [x2, y2], [x3, y3]],
+                &self.context.draw_state,
+                self.context.transform,
+                self.gfx,
+            );
+        }
+        self.line(x1, y1, x2, y2);
+        self.line(x2, y2, x3, y3);
+        self.line(x3, y3, x1, y1);
+    }
+}
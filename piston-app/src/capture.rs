@@ -0,0 +1,88 @@
+//! Frame capture, saving rendered frames as numbered PNG files.
+
+use super::*;
+
+use std::fs;
+use std::path::PathBuf;
+
+use ::image::{imageops, RgbImage};
+use gfx_gl as gl;
+
+/// Saves the frame on screen when `CAPTURE_KEY` is hit, and the first frames
+/// of the run when started with `--record <frames>`.
+pub(crate) struct FrameCapture {
+    directory: PathBuf,
+    prefix: String,
+    remaining: usize,
+}
+
+impl FrameCapture {
+    const CAPTURE_KEY: Key = Key::F12;
+
+    pub(crate) fn new(prefix: &str) -> Self {
+        FrameCapture {
+            directory: PathBuf::from(
+                cli_arg("capture-dir").unwrap_or_else(|| "captures".to_owned()),
+            ),
+            prefix: prefix.to_owned(),
+            remaining: cli_arg("record")
+                .map(|frames| frames.parse().expect("--record expects a frame count"))
+                .unwrap_or(0),
+        }
+    }
+
+    /// Must be called after the frame has been drawn, and before the buffers
+    /// are swapped, i.e. while handling the same render event.
+    pub(crate) fn capture(
+        &mut self,
+        window: &mut PistonAppWindow,
+        state: &PistonAppState,
+    ) {
+        if self.remaining == 0 && !state.key_hit(Self::CAPTURE_KEY) {
+            return;
+        }
+        self.remaining = self.remaining.saturating_sub(1);
+        match self.save(window, state) {
+            Ok(path) => println!("Captured frame {}", path.display()),
+            Err(error) => eprintln!("Failed to capture frame: {}", error),
+        }
+    }
+
+    fn save(
+        &self,
+        window: &mut PistonAppWindow,
+        state: &PistonAppState,
+    ) -> Result<PathBuf, Box<dyn Error>> {
+        let (width, height) = (state.viewport.draw_size[0], state.viewport.draw_size[1]);
+        let mut pixels = vec![0u8; width as usize * height as usize * 3];
+        window.encoder.flush(&mut window.device);
+        unsafe {
+            window.device.with_gl(|gl| {
+                gl.BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+                gl.ReadBuffer(gl::BACK);
+                gl.PixelStorei(gl::PACK_ALIGNMENT, 1);
+                gl.ReadPixels(
+                    0,
+                    0,
+                    width as gl::types::GLsizei,
+                    height as gl::types::GLsizei,
+                    gl::RGB,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_mut_ptr() as *mut _,
+                );
+            });
+        }
+        let frame = RgbImage::from_raw(width, height, pixels)
+            .ok_or("frame buffer size mismatch")?;
+        // OpenGL stores rows bottom-up.
+        let frame = imageops::flip_vertical(&frame);
+        fs::create_dir_all(&self.directory)?;
+        let path = self.directory.join(format!(
+            "{}-{:06}.png",
+            self.prefix,
+            state.frame_count()
+        ));
+        frame.save(&path)?;
+        Ok(path)
+    }
+}
@@ -0,0 +1,215 @@
+//! Headless runner, driving an application without a window or GPU.
+
+use super::*;
+
+/// Drives `PistonApp::setup_headless()`, `PistonApp::update()` and
+/// `PistonApp::draw_headless()` for a fixed number of frames, with a synthetic
+/// viewport and scripted input. Each frame runs exactly one fixed update step.
+#[derive(Debug, Clone)]
+pub struct PistonAppHeadless {
+    frames: usize,
+    size: Option<[u32; 2]>,
+    seed: Option<u64>,
+    inputs: Vec<(usize, Input)>,
+    replay: Option<InputReplay>,
+}
+
+impl PistonAppHeadless {
+    pub fn new(frames: usize) -> Self {
+        PistonAppHeadless {
+            frames: frames,
+            size: None,
+            seed: None,
+            inputs: vec![],
+            replay: None,
+        }
+    }
+
+    /// Viewport size, `PistonApp::settings()` window size by default.
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = Some([width, height]);
+        self
+    }
+
+    /// Seeds `PistonAppState::rng()`, instead of using `--seed`, `PISTON_APP_SEED`
+    /// or a random seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Feeds `input` to the application state right before frame `frame` is drawn.
+    pub fn input(mut self, frame: usize, input: Input) -> Self {
+        self.inputs.push((frame, input));
+        self
+    }
+
+    pub fn mouse_cursor(self, frame: usize, x: Scalar, y: Scalar) -> Self {
+        self.input(frame, Input::Move(Motion::MouseCursor(x, y)))
+    }
+
+    pub fn press<B: Into<Button>>(self, frame: usize, button: B) -> Self {
+        self.button(frame, button.into(), ButtonState::Press)
+    }
+
+    pub fn release<B: Into<Button>>(self, frame: usize, button: B) -> Self {
+        self.button(frame, button.into(), ButtonState::Release)
+    }
+
+    /// Presses `button` at frame `frame` and releases it on the next one.
+    pub fn hit<B: Into<Button>>(self, frame: usize, button: B) -> Self {
+        let button = button.into();
+        self.press(frame, button).release(frame + 1, button)
+    }
+
+    fn button(self, frame: usize, button: Button, state: ButtonState) -> Self {
+        self.input(
+            frame,
+            Input::Button(ButtonArgs {
+                state: state,
+                button: button,
+                scancode: None,
+            }),
+        )
+    }
+
+    /// Replays a recording made with `--record-input`, using its seed. Frames past
+    /// the end of the recording get the scripted input, if any.
+    pub fn replay_input<P: AsRef<Path>>(
+        mut self,
+        path: P,
+    ) -> Result<Self, Box<dyn Error>> {
+        let replay = InputReplay::open(path)?;
+        self.seed = Some(replay.seed());
+        self.replay = Some(replay);
+        Ok(self)
+    }
+
+    pub(crate) fn initial_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(default_seed)
+    }
+
+    pub(crate) fn run<A: PistonApp + ?Sized>(
+        mut self,
+        app: &mut A,
+        state: &mut PistonAppState,
+    ) {
+        let size = self
+            .size
+            .unwrap_or_else(|| app.settings().with_cli_overrides().window_size());
+        let (width, height) = (size[0], size[1]);
+        let dt = 1.0 / app.update_rate();
+        let args = RenderArgs {
+            ext_dt: dt,
+            width: width,
+            height: height,
+            draw_width: width,
+            draw_height: height,
+        };
+        state.event = Event::Loop(Loop::Render(args));
+        state.viewport = args.viewport();
+        self.inputs.sort_by_key(|&(frame, _)| frame);
+        let mut inputs = self.inputs.into_iter().peekable();
+        app.setup_headless(state);
+        for frame in 0..self.frames {
+            let mut updates = 1;
+            match self.replay.as_mut().and_then(|replay| replay.next_frame()) {
+                Some(recorded) => {
+                    recorded.apply(state);
+                    updates = recorded.updates();
+                }
+                None => {
+                    state.interpolation = 0.0;
+                    while let Some((_, input)) = inputs.next_if(|&(at, _)| at <= frame) {
+                        state.handle_input(&Event::Input(input));
+                    }
+                }
+            }
+            for _ in 0..updates {
+                app.update(dt, state);
+            }
+            app.draw_headless(state);
+            state.end_frame();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Walker {
+        size: [Scalar; 2],
+        roll: u32,
+        updates: usize,
+        position: Scalar,
+        hits: usize,
+        clicks: usize,
+        mouse: [Scalar; 2],
+    }
+
+    impl PistonApp for Walker {
+        fn draw(&mut self, _: &mut PistonAppWindow, _: &PistonAppState) {
+            unreachable!("headless runs never draw to a window");
+        }
+
+        fn setup_headless(&mut self, state: &PistonAppState) {
+            self.size = [state.width(), state.height()];
+            self.roll = state.rng().gen();
+        }
+
+        fn update(&mut self, _: Scalar, state: &PistonAppState) {
+            self.updates += 1;
+            if state.key_pressed(Key::Right) {
+                self.position += 1.0;
+            }
+        }
+
+        fn draw_headless(&mut self, state: &PistonAppState) {
+            if state.key_hit(Key::Space) {
+                self.hits += 1;
+            }
+            if state.mouse_button_clicked(MouseButton::Left) {
+                self.clicks += 1;
+            }
+            self.mouse = [state.mouse_x(), state.mouse_y()];
+        }
+    }
+
+    fn script() -> PistonAppHeadless {
+        PistonAppHeadless::new(10)
+            .size(320, 240)
+            .seed(42)
+            .press(2, Key::Right)
+            .release(5, Key::Right)
+            .hit(3, Key::Space)
+            .hit(7, Key::Space)
+            .mouse_cursor(4, 10.0, 20.0)
+            .hit(6, MouseButton::Left)
+    }
+
+    #[test]
+    fn runs_scripted_frames() {
+        let mut app = Walker::default();
+        let state = Walker::run_headless(&mut app, script());
+        assert_eq!(state.frame_count(), 10);
+        assert_eq!(state.seed(), 42);
+        assert_eq!(app.size, [320.0, 240.0]);
+        assert_eq!(app.updates, 10);
+        // Right is held for the updates of frames 2, 3 and 4.
+        assert_eq!(app.position, 3.0);
+        assert_eq!(app.hits, 2);
+        assert_eq!(app.clicks, 1);
+        assert_eq!(app.mouse, [10.0, 20.0]);
+        assert!(!state.key_pressed(Key::Right));
+    }
+
+    #[test]
+    fn seeded_runs_are_deterministic() {
+        let (mut first, mut second) = (Walker::default(), Walker::default());
+        Walker::run_headless(&mut first, script());
+        Walker::run_headless(&mut second, script());
+        assert_eq!(first.roll, second.roll);
+    }
+}
@@ -9,13 +9,19 @@ pub extern crate gfx;
 extern crate fnv;
 extern crate fps_counter;
 extern crate gfx_device_gl;
+extern crate gfx_gl;
 extern crate noise;
 extern crate piston_window;
 extern crate rand;
 extern crate sdl2_window;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
 extern crate shaders_graphics2d;
 extern crate vecmath;
+#[cfg(feature = "box2d")]
+extern crate wrapped2d;
 
 pub use std::f64::consts;
 
@@ -28,11 +34,13 @@ pub use rand::prelude::*;
 pub use types::{Color, ColorComponent, Resolution};
 pub use vecmath::*;
 
+use std::cell::RefCell;
 use std::collections::hash_set;
 use std::error::Error;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::time::Instant;
 
 use fnv::*;
 use fps_counter::*;
@@ -41,6 +49,38 @@ use gfx_device_gl::Resources;
 use noise::{NoiseFn, Perlin, Seedable};
 use shaders_graphics2d::{colored, textured};
 
+use capture::FrameCapture;
+use replay::{InputRecorder, InputReplay};
+
+pub use blend::BlendMode;
+pub use canvas::PistonAppCanvas;
+pub use headless::PistonAppHeadless;
+pub use physics::ForceGenerator;
+pub use post::{PostPass, PostProcessing};
+pub use render_target::PistonRenderTarget;
+pub use settings::PistonAppSettings;
+pub use sprite::{SpriteBatch, Vertex};
+pub use stream::PistonStreamBuffer;
+pub use vector::Vector2;
+
+#[cfg(feature = "box2d")]
+pub mod box2d;
+pub mod particles;
+pub mod physics;
+pub mod pipelines;
+
+mod blend;
+mod canvas;
+mod capture;
+mod headless;
+mod post;
+mod render_target;
+mod replay;
+mod settings;
+mod sprite;
+mod stream;
+mod vector;
+
 pub type PistonAppWindow = PistonWindow<sdl2_window::Sdl2Window>;
 
 pub trait PistonApp {
@@ -48,61 +88,120 @@ pub trait PistonApp {
 
     fn draw(&mut self, window: &mut PistonAppWindow, state: &PistonAppState);
 
+    fn settings(&self) -> PistonAppSettings {
+        PistonAppSettings::new()
+    }
+
+    /// Number of fixed-timestep `update()` calls per second of simulated time.
+    fn update_rate(&self) -> Scalar {
+        60.0
+    }
+
+    /// Advances the simulation by `dt` seconds, independently of the frame rate.
+    ///
+    /// Called zero or more times per frame, so one-shot input like
+    /// `PistonAppState::key_hit()` or `mouse_button_clicked()`, which only lasts
+    /// for the frame, belongs in `draw()`.
+    fn update(&mut self, _dt: Scalar, _state: &PistonAppState) {}
+
+    fn setup_headless(&mut self, _state: &PistonAppState) {}
+
+    fn draw_headless(&mut self, _state: &PistonAppState) {}
+
+    /// Runs the application in a window, unless `--headless <frames>` is passed.
+    ///
+    /// Other command line options:
+    ///
+    /// * `--size <width>x<height>`, `--resizable <bool>`, `--vsync <bool>`,
+    ///   `--samples <count>` and `--fps <frames>` override `settings()`,
+    /// * `--seed <seed>` seeds `PistonAppState::rng()` (also `PISTON_APP_SEED`),
+    /// * `--record <frames>` saves the first frames as PNG files, into
+    ///   `--capture-dir <path>` (`captures` by default). F12 saves a single frame,
+    /// * `--record-input <path>` records the seed and the input of every frame,
+    ///   which `--replay-input <path>` feeds back, also with `--headless`.
     fn run<T: Into<String>>(title: T, app: &mut Self) {
         let title = title.into();
+        if let Some(frames) = cli_arg("headless") {
+            let frames = frames.parse().expect("--headless expects a frame count");
+            let mut headless = PistonAppHeadless::new(frames);
+            if let Some(path) = cli_arg("replay-input") {
+                headless = headless.replay_input(path).unwrap();
+            }
+            let state = Self::run_headless(app, headless);
+            println!(
+                "{}: ran {} headless frames with seed {}",
+                title,
+                state.frame_count(),
+                state.seed()
+            );
+            return;
+        }
         let mut first = true;
-        let mut state = PistonAppState::new();
-        let mut window: PistonAppWindow = WindowSettings::new(title.clone(), [640, 480])
-            .exit_on_esc(true)
-            .resizable(false)
-            .vsync(true)
-            .build()
-            .unwrap();
+        let mut replay =
+            cli_arg("replay-input").map(|path| InputReplay::open(path).unwrap());
+        let mut state = PistonAppState::new(match replay {
+            Some(ref replay) => replay.seed(),
+            None => default_seed(),
+        });
+        println!("{}: seed {}", title, state.seed());
+        let mut recorder = cli_arg("record-input")
+            .map(|path| InputRecorder::create(path, state.seed()).unwrap());
+        let settings = app.settings().with_cli_overrides();
+        let mut window = settings.build_window(title.clone());
         let mut fps = FPSCounter::new();
+        let mut clock = FixedTimestep::new(app.update_rate());
+        let mut capture = FrameCapture::new(&title);
         while let Some(e) = window.next() {
             if let Some(args) = e.render_args() {
                 state.event = e.clone();
                 state.viewport = args.viewport();
                 if first {
                     first = false;
+                    if let Some(color) = settings.initial_clear_color() {
+                        window.draw_2d(&e, |_, gfx| clear(color, gfx));
+                    }
                     app.setup(&mut window, &state);
+                    clock.reset();
                 }
-                app.draw(&mut window, &state);
-                state.frame_count += 1;
-                state.hit_keys.clear();
-                state.clicked_mouse_buttons.clear();
-                window.set_title(format!("{} ({} FPS)", title, fps.tick()));
-            }
-            if let Some(position) = e.mouse_cursor_args() {
-                state.mouse_x = position[0];
-                state.mouse_y = position[1];
-            }
-            match e.press_args() {
-                Some(Button::Keyboard(key)) => {
-                    state.pressed_keys.insert(key);
-                }
-                Some(Button::Mouse(button)) => {
-                    state.pressed_mouse_buttons.insert(button);
-                }
-                _ => (),
-            }
-            match e.release_args() {
-                Some(Button::Keyboard(key)) => {
-                    if state.pressed_keys.contains(&key) {
-                        state.pressed_keys.remove(&key);
-                        state.hit_keys.insert(key);
+                let updates = match replay.as_mut().map(|replay| replay.next_frame()) {
+                    Some(Some(frame)) => {
+                        frame.apply(&mut state);
+                        for _ in 0..frame.updates() {
+                            app.update(clock.step, &state);
+                        }
+                        frame.updates()
                     }
-                }
-                Some(Button::Mouse(button)) => {
-                    if state.pressed_mouse_buttons.contains(&button) {
-                        state.pressed_mouse_buttons.remove(&button);
-                        state.clicked_mouse_buttons.insert(button);
+                    Some(None) => {
+                        println!("{}: input replay finished", title);
+                        replay = None;
+                        clock.reset();
+                        0
                     }
+                    None => {
+                        let updates = clock.advance(|dt| app.update(dt, &state));
+                        state.interpolation = clock.alpha();
+                        updates
+                    }
+                };
+                if let Some(ref mut recorder) = recorder {
+                    recorder.record(&state, updates).unwrap();
                 }
-                _ => (),
+                app.draw(&mut window, &state);
+                capture.capture(&mut window, &state);
+                state.end_frame();
+                window.set_title(format!("{} ({} FPS)", title, fps.tick()));
+            }
+            if replay.is_none() {
+                state.handle_input(&e);
             }
         }
     }
+
+    fn run_headless(app: &mut Self, headless: PistonAppHeadless) -> PistonAppState {
+        let mut state = PistonAppState::new(headless.initial_seed());
+        headless.run(app, &mut state);
+        state
+    }
 }
 
 pub struct PistonAppState {
@@ -115,11 +214,16 @@ pub struct PistonAppState {
     clicked_mouse_buttons: FnvHashSet<MouseButton>,
     mouse_x: Scalar,
     mouse_y: Scalar,
+    interpolation: Scalar,
+    seed: u64,
+    rng: RefCell<StdRng>,
     noise: Perlin,
 }
 
 impl PistonAppState {
-    fn new() -> Self {
+    fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let noise = Perlin::new().set_seed(rng.gen());
         PistonAppState {
             event: Event::Loop(Loop::Render(RenderArgs {
                 ext_dt: 0.0,
@@ -140,10 +244,62 @@ impl PistonAppState {
             clicked_mouse_buttons: Default::default(),
             mouse_x: 0.0,
             mouse_y: 0.0,
-            noise: Perlin::new().set_seed(thread_rng().gen()),
+            interpolation: 0.0,
+            seed: seed,
+            rng: RefCell::new(rng),
+            noise: noise,
+        }
+    }
+
+    /// State of a `width` by `height` window, for tests that need no window.
+    #[cfg(test)]
+    pub(crate) fn with_size(width: u32, height: u32) -> Self {
+        let mut state = PistonAppState::new(0);
+        state.viewport = Viewport {
+            rect: [0, 0, width as i32, height as i32],
+            draw_size: [width, height],
+            window_size: [width, height],
+        };
+        state
+    }
+
+    fn handle_input(&mut self, e: &Event) {
+        if let Some(position) = e.mouse_cursor_args() {
+            self.mouse_x = position[0];
+            self.mouse_y = position[1];
+        }
+        match e.press_args() {
+            Some(Button::Keyboard(key)) => {
+                self.pressed_keys.insert(key);
+            }
+            Some(Button::Mouse(button)) => {
+                self.pressed_mouse_buttons.insert(button);
+            }
+            _ => (),
+        }
+        match e.release_args() {
+            Some(Button::Keyboard(key)) => {
+                if self.pressed_keys.contains(&key) {
+                    self.pressed_keys.remove(&key);
+                    self.hit_keys.insert(key);
+                }
+            }
+            Some(Button::Mouse(button)) => {
+                if self.pressed_mouse_buttons.contains(&button) {
+                    self.pressed_mouse_buttons.remove(&button);
+                    self.clicked_mouse_buttons.insert(button);
+                }
+            }
+            _ => (),
         }
     }
 
+    fn end_frame(&mut self) {
+        self.frame_count += 1;
+        self.hit_keys.clear();
+        self.clicked_mouse_buttons.clear();
+    }
+
     #[inline]
     pub fn event(&self) -> &Event {
         &self.event
@@ -184,11 +340,14 @@ impl PistonAppState {
         self.pressed_keys.iter()
     }
 
+    /// Whether any key was released this frame. Like the other one-shot input,
+    /// only reliable in `PistonApp::draw()`, see `PistonApp::update()`.
     #[inline]
     pub fn any_key_hit(&self) -> bool {
         self.hit_keys.len() > 0
     }
 
+    /// Whether `key` was released this frame.
     #[inline]
     pub fn key_hit(&self, key: Key) -> bool {
         self.hit_keys.contains(&key)
@@ -214,11 +373,14 @@ impl PistonAppState {
         self.pressed_mouse_buttons.iter()
     }
 
+    /// Whether any mouse button was released this frame. Like the other one-shot
+    /// input, only reliable in `PistonApp::draw()`, see `PistonApp::update()`.
     #[inline]
     pub fn any_mouse_button_clicked(&self) -> bool {
         self.clicked_mouse_buttons.len() > 0
     }
 
+    /// Whether `button` was released this frame.
     #[inline]
     pub fn mouse_button_clicked(&self, button: MouseButton) -> bool {
         self.clicked_mouse_buttons.contains(&button)
@@ -239,6 +401,26 @@ impl PistonAppState {
         self.mouse_y
     }
 
+    /// Fraction of a fixed timestep not yet simulated when the frame is drawn,
+    /// for interpolating between the last two states produced by `update()`.
+    #[inline]
+    pub fn interpolation(&self) -> Scalar {
+        self.interpolation
+    }
+
+    /// Seed of the random number generator returned by `rng()`, which also
+    /// seeded `noise()`. Pass it back through `--seed` or `PISTON_APP_SEED` to
+    /// reproduce a run.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub fn rng(&self) -> PistonAppRng<'_> {
+        PistonAppRng { rng: &self.rng }
+    }
+
     #[inline]
     pub fn map_range(
         &self,
@@ -334,7 +516,7 @@ impl PistonAppState {
 
     pub fn random_color(&self, alpha: Option<ColorComponent>) -> Color {
         const MIN_COLOR_COMPONENT: ColorComponent = 1.0 / 3.0;
-        let mut rng = thread_rng();
+        let mut rng = self.rng();
         let uniform = Uniform::new_inclusive(MIN_COLOR_COMPONENT, 1.0);
         [
             rng.sample(uniform),
@@ -371,6 +553,15 @@ impl PistonAppState {
         ]
     }
 
+    /// Processing-style drawing context, for use inside `draw_2d()`.
+    pub fn canvas<'a, 'b>(
+        &self,
+        context: Context,
+        gfx: &'a mut G2d<'b>,
+    ) -> PistonAppCanvas<'a, 'b> {
+        PistonAppCanvas::new(context, gfx)
+    }
+
     pub fn draw_centered_texture(
         &self,
         texture: &G2dTexture,
@@ -391,18 +582,99 @@ impl PistonAppState {
     }
 }
 
+/// Handle to the seeded random number generator owned by `PistonAppState`.
+///
+/// Like `ThreadRng`, it only borrows the generator for the duration of each
+/// call, so several handles can be used at the same time.
+#[derive(Debug, Clone, Copy)]
+pub struct PistonAppRng<'a> {
+    rng: &'a RefCell<StdRng>,
+}
+
+impl<'a> RngCore for PistonAppRng<'a> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.rng.borrow_mut().next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.rng.borrow_mut().next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.borrow_mut().fill_bytes(dest)
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.borrow_mut().try_fill_bytes(dest)
+    }
+}
+
+struct FixedTimestep {
+    step: Scalar,
+    accumulator: Scalar,
+    last: Instant,
+}
+
+impl FixedTimestep {
+    fn new(rate: Scalar) -> Self {
+        FixedTimestep {
+            step: 1.0 / rate,
+            accumulator: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.last = Instant::now();
+    }
+
+    fn advance<F: FnMut(Scalar)>(&mut self, mut f: F) -> usize {
+        // Don't try to catch up after a stall, e.g. while dragging the window.
+        const MAX_FRAME_TIME: Scalar = 0.25;
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last);
+        self.last = now;
+        self.accumulator += elapsed.as_secs_f64().min(MAX_FRAME_TIME);
+        let mut updates = 0;
+        while self.accumulator >= self.step {
+            f(self.step);
+            self.accumulator -= self.step;
+            updates += 1;
+        }
+        updates
+    }
+
+    #[inline]
+    fn alpha(&self) -> Scalar {
+        self.accumulator / self.step
+    }
+}
+
 pub type PistonPipeline<M> = pso::PipelineState<Resources, M>;
 
+pub type PistonRenderTargetView =
+    gfx::handle::RenderTargetView<Resources, gfx::format::Srgba8>;
+
 pub type PistonPipelineSampler = (
     gfx::handle::ShaderResourceView<Resources, [f32; 4]>,
     gfx::handle::Sampler<Resources>,
 );
 
+type PistonPipelineResult<M> =
+    Result<(PistonPipeline<M>, PistonRenderer), Box<dyn Error>>;
+
 #[derive(Debug)]
 pub struct PistonPipelineBuilder {
     texture_atlas: Option<TextureAtlas>,
     vertex_shader: Option<&'static [u8]>,
     fragment_shader: Option<&'static [u8]>,
+    blend_mode: BlendMode,
+    render_target: Option<PistonRenderTargetView>,
 }
 
 impl PistonPipelineBuilder {
@@ -411,6 +683,8 @@ impl PistonPipelineBuilder {
             texture_atlas: None,
             vertex_shader: None,
             fragment_shader: None,
+            blend_mode: BlendMode::Alpha,
+            render_target: None,
         }
     }
 
@@ -429,26 +703,84 @@ impl PistonPipelineBuilder {
         self
     }
 
+    /// Target the renderer draws into, until `PistonRenderer::bind_target()` is
+    /// called. The window by default.
+    pub fn render_target(mut self, render_target: &PistonRenderTarget) -> Self {
+        self.render_target = Some(render_target.output_color().clone());
+        self
+    }
+
+    /// Blend mode of the stock pipelines, `BlendMode::Alpha` by default.
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     pub fn build<I: pso::PipelineInit>(
         self,
         window: &mut PistonAppWindow,
         init: I,
-    ) -> Result<(PistonPipeline<I::Meta>, PistonRenderer), Box<dyn Error>> {
-        let factory = &mut window.factory;
-        let mut default_vertex_shader = colored::VERTEX_GLSL_150_CORE;
-        let mut default_fragment_shader = colored::FRAGMENT_GLSL_150_CORE;
-        if self.texture_atlas.is_some() {
-            default_vertex_shader = textured::VERTEX_GLSL_150_CORE;
-            default_fragment_shader = textured::FRAGMENT_GLSL_150_CORE;
-        }
+    ) -> PistonPipelineResult<I::Meta> {
+        let (vertex_shader, fragment_shader) = if self.texture_atlas.is_some() {
+            (
+                textured::VERTEX_GLSL_150_CORE,
+                textured::FRAGMENT_GLSL_150_CORE,
+            )
+        } else {
+            (
+                colored::VERTEX_GLSL_150_CORE,
+                colored::FRAGMENT_GLSL_150_CORE,
+            )
+        };
+        self.build_with_default_shaders(window, init, vertex_shader, fragment_shader)
+    }
+
+    /// Builds the stock `pipelines::sprites` pipeline, blending with `blend_mode()`
+    /// and using its own shaders unless others were given.
+    pub fn build_sprites(
+        self,
+        window: &mut PistonAppWindow,
+    ) -> PistonPipelineResult<pipelines::sprites::Meta> {
+        let init = pipelines::sprites_init(self.blend_mode);
+        self.build_with_default_shaders(
+            window,
+            init,
+            pipelines::SPRITES_VERTEX_GLSL_150_CORE,
+            pipelines::SPRITES_FRAGMENT_GLSL_150_CORE,
+        )
+    }
+
+    /// Builds the stock `pipelines::world_sprites` pipeline, blending with
+    /// `blend_mode()` and using its own shaders unless others were given.
+    pub fn build_world_sprites(
+        self,
+        window: &mut PistonAppWindow,
+    ) -> PistonPipelineResult<pipelines::world_sprites::Meta> {
+        let init = pipelines::world_sprites_init(self.blend_mode);
+        self.build_with_default_shaders(
+            window,
+            init,
+            pipelines::WORLD_SPRITES_VERTEX_GLSL_150_CORE,
+            pipelines::SPRITES_FRAGMENT_GLSL_150_CORE,
+        )
+    }
+
+    fn build_with_default_shaders<I: pso::PipelineInit>(
+        self,
+        window: &mut PistonAppWindow,
+        init: I,
+        vertex_shader: &'static [u8],
+        fragment_shader: &'static [u8],
+    ) -> PistonPipelineResult<I::Meta> {
         Ok((
-            factory.create_pipeline_simple(
-                self.vertex_shader.unwrap_or(default_vertex_shader),
-                self.fragment_shader.unwrap_or(default_fragment_shader),
+            window.factory.create_pipeline_simple(
+                self.vertex_shader.unwrap_or(vertex_shader),
+                self.fragment_shader.unwrap_or(fragment_shader),
                 init,
             )?,
             PistonRenderer {
                 texture_atlas: self.texture_atlas,
+                target: RefCell::new(self.render_target),
             },
         ))
     }
@@ -457,6 +789,7 @@ impl PistonPipelineBuilder {
 #[derive(Debug)]
 pub struct PistonRenderer {
     texture_atlas: Option<TextureAtlas>,
+    target: RefCell<Option<PistonRenderTargetView>>,
 }
 
 impl PistonRenderer {
@@ -465,8 +798,15 @@ impl PistonRenderer {
         self.texture_atlas.as_ref()
     }
 
+    /// Makes `clear()` and the draw calls render into `target`, or into the
+    /// window if `None`.
+    pub fn bind_target(&self, target: Option<&PistonRenderTarget>) {
+        *self.target.borrow_mut() = target.map(|target| target.output_color().clone());
+    }
+
     pub fn clear(&self, window: &mut PistonAppWindow, color: Color) {
-        window.encoder.clear(&window.output_color, color);
+        let output_color = self.output_color(window);
+        window.encoder.clear(&output_color, color);
     }
 
     pub fn draw<B, D, F, V>(
@@ -479,19 +819,44 @@ impl PistonRenderer {
     ) where
         B: IntoIndexBuffer<Resources>,
         D: pso::PipelineData<Resources>,
-        F: FnOnce(
-            gfx::handle::Buffer<Resources, V>,
-            gfx::handle::RenderTargetView<Resources, gfx::format::Srgba8>,
-        ) -> D,
+        F: FnOnce(gfx::handle::Buffer<Resources, V>, PistonRenderTargetView) -> D,
         V: gfx::traits::Pod + pso::buffer::Structure<gfx::format::Format>,
     {
         let (vbuf, slice) = window
             .factory
             .create_vertex_buffer_with_slice(vertices, indices);
-        let data = f(vbuf, window.output_color.clone());
-        let encoder = &mut window.encoder;
-        encoder.draw(&slice, pipeline, &data);
-        encoder.flush(&mut window.device);
+        let data = f(vbuf, self.output_color(window));
+        window.encoder.draw(&slice, pipeline, &data);
+        self.flush(window);
+    }
+
+    /// Queues a draw of `slice` from `stream`, to be submitted by `flush()`.
+    pub fn draw_stream<D, F, V>(
+        &self,
+        window: &mut PistonAppWindow,
+        pipeline: &PistonPipeline<D::Meta>,
+        stream: &PistonStreamBuffer<V>,
+        slice: &Slice<Resources>,
+        f: F,
+    ) where
+        D: pso::PipelineData<Resources>,
+        F: FnOnce(gfx::handle::Buffer<Resources, V>, PistonRenderTargetView) -> D,
+        V: gfx::traits::Pod + pso::buffer::Structure<gfx::format::Format>,
+    {
+        let data = f(stream.vertex_buffer(), self.output_color(window));
+        window.encoder.draw(slice, pipeline, &data);
+    }
+
+    /// Submits the queued commands to the device.
+    pub fn flush(&self, window: &mut PistonAppWindow) {
+        window.encoder.flush(&mut window.device);
+    }
+
+    fn output_color(&self, window: &PistonAppWindow) -> PistonRenderTargetView {
+        match *self.target.borrow() {
+            Some(ref output_color) => output_color.clone(),
+            None => window.output_color.clone(),
+        }
     }
 }
 
@@ -549,11 +914,48 @@ impl TextureAtlas {
         })
     }
 
+    /// Single region atlas of one opaque white texel, to draw untextured shapes
+    /// with the textured pipelines: the vertex colours come through unchanged.
+    pub fn white(window: &mut PistonAppWindow) -> Result<Self, Box<dyn Error>> {
+        let texture = Texture::from_memory_alpha(
+            &mut window.factory,
+            &[255],
+            1,
+            1,
+            &TextureSettings::new(),
+        )?;
+        Ok(TextureAtlas {
+            texture: texture,
+            atlas: vec![[0.0, 0.0, 1.0, 1.0]],
+            normalized_atlas: vec![[0.0, 0.0, 1.0, 1.0]],
+        })
+    }
+
+    /// Single region atlas sampling `render_target`, upright.
+    pub fn from_render_target(render_target: &PistonRenderTarget) -> Self {
+        TextureAtlas {
+            texture: render_target.texture().clone(),
+            atlas: vec![[0.0, 0.0, render_target.width(), render_target.height()]],
+            normalized_atlas: vec![render_target.uv_extents()],
+        }
+    }
+
     #[inline]
     pub fn texture_view_sampler(&self) -> PistonPipelineSampler {
         (self.texture.view.clone(), self.texture.sampler.clone())
     }
 
+    /// Number of regions in the atlas.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.atlas.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.atlas.is_empty()
+    }
+
     #[inline]
     pub fn texture_extents(&self, index: usize) -> [Scalar; 4] {
         self.atlas[index]
@@ -615,8 +1017,31 @@ pub fn vec2_limit(vec: Vec2d, max: Scalar) -> Vec2d {
     }
 }
 
-pub fn vec2_random() -> Vec2d {
-    let mut rng = thread_rng();
+pub fn vec2_random<R: Rng>(rng: &mut R) -> Vec2d {
     let uniform = Uniform::new_inclusive(-1.0, 1.0);
     vec2_normalized([rng.sample(uniform), rng.sample(uniform)])
 }
+
+/// Seed given with `--seed <seed>` or `PISTON_APP_SEED`, random otherwise.
+pub fn default_seed() -> u64 {
+    cli_arg("seed")
+        .or_else(|| std::env::var("PISTON_APP_SEED").ok())
+        .map(|seed| seed.parse().expect("the seed must be an unsigned integer"))
+        .unwrap_or_else(|| thread_rng().gen())
+}
+
+/// Value of the `--<name> <value>` or `--<name>=<value>` command line option.
+pub fn cli_arg(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let prefix = format!("{}=", flag);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            return args.next();
+        }
+        if arg.starts_with(&prefix) {
+            return Some(arg[prefix.len()..].to_owned());
+        }
+    }
+    None
+}
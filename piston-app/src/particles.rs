@@ -0,0 +1,426 @@
+//! Particle systems, spawning short-lived particles from an emitter origin.
+
+use super::*;
+
+use std::fmt::Debug;
+
+pub use self::curve::{Curve, Lerp};
+pub use self::emitter::{Emitter, EmitterShape, SpawnSchedule};
+pub use self::pool::{
+    ParticlePool, ParticlePoolStats, PooledParticle, PooledParticleStyle,
+};
+pub use self::preset::{ParticleEffect, ParticleEffectFile};
+
+mod curve;
+mod emitter;
+mod pool;
+mod preset;
+
+/// Function called by `ParticleSystem` to turn freshly spawned particle data
+/// into a particle.
+pub type ParticleSpawner<P> = fn(&PistonAppState, ParticleData) -> P;
+
+/// Distribution of the initial velocity of particles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VelocityDistribution {
+    /// Uniformly sampled on each axis.
+    Uniform { min: Vector2, max: Vector2 },
+    /// Normally distributed on each axis.
+    Normal { mean: Vector2, deviation: Vector2 },
+}
+
+impl VelocityDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Vector2 {
+        match *self {
+            VelocityDistribution::Uniform { min, max } => Vector2::new(
+                Self::sample_uniform(rng, min.x, max.x),
+                Self::sample_uniform(rng, min.y, max.y),
+            ),
+            VelocityDistribution::Normal { mean, deviation } => Vector2::new(
+                mean.x + rng.sample(StandardNormal) * deviation.x,
+                mean.y + rng.sample(StandardNormal) * deviation.y,
+            ),
+        }
+    }
+
+    fn sample_uniform<R: Rng>(rng: &mut R, min: Scalar, max: Scalar) -> Scalar {
+        if min < max {
+            rng.gen_range(min, max)
+        } else {
+            min
+        }
+    }
+}
+
+/// State shared by all particles: motion, colour, alpha, scale, atlas region and
+/// remaining life, from 1 when spawned down to 0.
+#[derive(Debug, Clone)]
+pub struct ParticleData {
+    pub body: physics::Mover,
+    pub color: Color,
+    pub alpha: Scalar,
+    pub scale: Vec2d,
+    pub texture_index: usize,
+    pub life: Scalar,
+    pub decay: Scalar,
+}
+
+impl ParticleData {
+    /// Particle of mass 1 living for `lifetime` updates.
+    pub fn new(
+        position: Vector2,
+        velocity: Vector2,
+        color: Color,
+        lifetime: Scalar,
+    ) -> Self {
+        ParticleData {
+            body: physics::Mover::new(position.x, position.y, 1.0)
+                .with_velocity(velocity),
+            color: color,
+            alpha: 1.0,
+            scale: [1.0, 1.0],
+            texture_index: 0,
+            life: 1.0,
+            decay: 1.0 / lifetime,
+        }
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector2 {
+        self.body.position()
+    }
+
+    #[inline]
+    pub fn is_alive(&self) -> bool {
+        self.life > 0.0
+    }
+
+    /// Fraction of the particle's life elapsed, from 0 when spawned to 1.
+    #[inline]
+    pub fn age(&self) -> Scalar {
+        (1.0 - self.life).clamp(0.0, 1.0)
+    }
+
+    /// Integrates the forces applied since the last update, and ages the particle.
+    /// The alpha follows the remaining life.
+    pub fn update(&mut self) {
+        self.body.integrate();
+        self.life -= self.decay;
+        self.alpha = self.life.max(0.0);
+    }
+
+    /// Adds the particle's sprite, transformed by its position, angle and scale,
+    /// to `batch`.
+    pub fn extend_sprite_batch(
+        &self,
+        color: Color,
+        texture_atlas: &TextureAtlas,
+        batch: &mut SpriteBatch,
+    ) {
+        let position = self.body.position();
+        let transform = math::identity()
+            .trans(position.x, position.y)
+            .rot_rad(self.body.angle())
+            .scale(self.scale[0], self.scale[1]);
+        batch.sprite(texture_atlas, self.texture_index, transform, color);
+    }
+}
+
+pub trait Particle: Debug {
+    fn data(&self) -> &ParticleData;
+
+    fn data_mut(&mut self) -> &mut ParticleData;
+
+    #[inline]
+    fn is_alive(&self) -> bool {
+        self.data().is_alive()
+    }
+
+    /// Colour the particle is drawn with, its alpha multiplied by the particle's.
+    fn color(&self) -> Color {
+        let data = self.data();
+        let mut color = data.color;
+        color[3] *= data.alpha as ColorComponent;
+        color
+    }
+
+    fn update(&mut self, _state: &PistonAppState) {
+        self.data_mut().update();
+    }
+
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
+        self.data()
+            .extend_sprite_batch(self.color(), texture_atlas, batch);
+    }
+
+    /// Draws `texture` centered on the particle, without a custom pipeline.
+    fn draw(
+        &self,
+        texture: &G2dTexture,
+        state: &PistonAppState,
+        context: Context,
+        gfx: &mut G2d,
+    ) {
+        let position = self.data().position();
+        state.draw_centered_texture(
+            texture,
+            Some(self.color()),
+            position.x,
+            position.y,
+            &context.draw_state,
+            context.transform,
+            gfx,
+        );
+    }
+}
+
+impl Particle for ParticleData {
+    #[inline]
+    fn data(&self) -> &ParticleData {
+        self
+    }
+
+    #[inline]
+    fn data_mut(&mut self) -> &mut ParticleData {
+        self
+    }
+}
+
+impl<P: Particle + ?Sized> Particle for Box<P> {
+    #[inline]
+    fn data(&self) -> &ParticleData {
+        (**self).data()
+    }
+
+    #[inline]
+    fn data_mut(&mut self) -> &mut ParticleData {
+        (**self).data_mut()
+    }
+
+    #[inline]
+    fn is_alive(&self) -> bool {
+        (**self).is_alive()
+    }
+
+    fn color(&self) -> Color {
+        (**self).color()
+    }
+
+    fn update(&mut self, state: &PistonAppState) {
+        (**self).update(state);
+    }
+
+    fn extend_sprite_batch(&self, texture_atlas: &TextureAtlas, batch: &mut SpriteBatch) {
+        (**self).extend_sprite_batch(texture_atlas, batch);
+    }
+
+    fn draw(
+        &self,
+        texture: &G2dTexture,
+        state: &PistonAppState,
+        context: Context,
+        gfx: &mut G2d,
+    ) {
+        (**self).draw(texture, state, context, gfx);
+    }
+}
+
+/// Particles spawned by an emitter around an origin, moved by forces and removed
+/// when their life runs out.
+///
+/// Particles are colored along a Perlin noise hue path, unless a fixed `color()`
+/// or an emitter colour curve is set.
+#[derive(Debug)]
+pub struct ParticleSystem<P: Particle> {
+    origin: Vector2,
+    spawner: ParticleSpawner<P>,
+    emitter: Emitter,
+    lifetime: Scalar,
+    velocity: VelocityDistribution,
+    texture_index: usize,
+    color: Option<Color>,
+    base_hue: Scalar,
+    color_offset: Scalar,
+    forces: Vec<Box<dyn ForceGenerator>>,
+    particles: Vec<P>,
+}
+
+impl<P: Particle> ParticleSystem<P> {
+    /// Particle system emitting from (`x`, `y`), with particles created by
+    /// `spawner`. By default, one particle is spawned per update, lives for 128
+    /// updates and starts with a random velocity between (-1, -2) and (1, 0).
+    pub fn new(
+        state: &PistonAppState,
+        x: Scalar,
+        y: Scalar,
+        spawner: ParticleSpawner<P>,
+    ) -> Self {
+        let mut rng = state.rng();
+        ParticleSystem {
+            origin: Vector2::new(x, y),
+            spawner: spawner,
+            emitter: Emitter::default(),
+            lifetime: 128.0,
+            velocity: VelocityDistribution::Uniform {
+                min: Vector2::new(-1.0, -2.0),
+                max: Vector2::new(1.0, 0.0),
+            },
+            texture_index: 0,
+            color: None,
+            base_hue: rng.gen(),
+            color_offset: rng.gen(),
+            forces: vec![],
+            particles: vec![],
+        }
+    }
+
+    /// Particles spawned per update, possibly fractional.
+    pub fn spawn_rate(mut self, spawn_rate: Scalar) -> Self {
+        self.emitter
+            .set_schedule(SpawnSchedule::Continuous(spawn_rate));
+        self
+    }
+
+    /// Point emitter spawning one particle per update by default.
+    pub fn emitter(mut self, emitter: Emitter) -> Self {
+        self.emitter = emitter;
+        self
+    }
+
+    /// Number of updates a particle lives for.
+    pub fn lifetime(mut self, lifetime: Scalar) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Initial velocity range, sampled uniformly on each axis.
+    pub fn velocity(mut self, min: Vector2, max: Vector2) -> Self {
+        self.velocity = VelocityDistribution::Uniform { min: min, max: max };
+        self
+    }
+
+    pub fn velocity_distribution(mut self, velocity: VelocityDistribution) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Texture atlas region of the spawned particles, 0 by default.
+    pub fn texture_index(mut self, texture_index: usize) -> Self {
+        self.texture_index = texture_index;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    /// Applies the force of `generator` to every particle, on every update.
+    pub fn force<G: ForceGenerator + 'static>(mut self, generator: G) -> Self {
+        self.add_force(generator);
+        self
+    }
+
+    #[inline]
+    pub fn origin(&self) -> Vector2 {
+        self.origin
+    }
+
+    #[inline]
+    pub fn set_origin(&mut self, origin: Vector2) {
+        self.origin = origin;
+    }
+
+    #[inline]
+    pub fn emitter_mut(&mut self) -> &mut Emitter {
+        &mut self.emitter
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    #[inline]
+    pub fn particles(&self) -> &[P] {
+        &self.particles
+    }
+
+    pub fn add_force<G: ForceGenerator + 'static>(&mut self, generator: G) {
+        self.forces.push(Box::new(generator));
+    }
+
+    /// Applies the force of `generator` to every particle, until the next update.
+    /// Repellers are attractions with a negative `g`.
+    pub fn apply_force(&mut self, generator: &dyn ForceGenerator) {
+        for particle in &mut self.particles {
+            generator.apply(&mut particle.data_mut().body);
+        }
+    }
+
+    pub fn spawn_particle(&mut self, state: &PistonAppState) {
+        let (position, velocity) = {
+            let mut rng = state.rng();
+            let position = self.emitter.sample(&mut rng, self.origin);
+            (position, self.velocity.sample(&mut rng))
+        };
+        let color = match self.color {
+            Some(color) => color,
+            None => {
+                self.color_offset += 0.00042;
+                state.noise_color(self.base_hue, self.color_offset, Some(1.0))
+            }
+        };
+        let mut data = ParticleData::new(position, velocity, color, self.lifetime);
+        data.texture_index = self.texture_index;
+        self.emitter.apply_curves(&mut data);
+        self.particles.push((self.spawner)(state, data));
+    }
+
+    /// Applies the registered forces, moves and ages the particles, applies the
+    /// emitter curves, removes the dead particles and spawns new ones.
+    pub fn update(&mut self, state: &PistonAppState) {
+        for particle in &mut self.particles {
+            let body = &mut particle.data_mut().body;
+            for generator in &self.forces {
+                generator.apply(body);
+            }
+        }
+        for particle in &mut self.particles {
+            particle.update(state);
+            self.emitter.apply_curves(particle.data_mut());
+        }
+        self.particles.retain(|particle| particle.is_alive());
+        for _ in 0..self.emitter.tick() {
+            self.spawn_particle(state);
+        }
+    }
+
+    pub fn extend_sprite_batch(
+        &self,
+        texture_atlas: &TextureAtlas,
+        batch: &mut SpriteBatch,
+    ) {
+        for particle in &self.particles {
+            particle.extend_sprite_batch(texture_atlas, batch);
+        }
+    }
+
+    pub fn draw(
+        &self,
+        texture: &G2dTexture,
+        state: &PistonAppState,
+        context: Context,
+        gfx: &mut G2d,
+    ) {
+        for particle in &self.particles {
+            particle.draw(texture, state, context, gfx);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+//! Keyframed curves, sampled over a particle's life.
+
+use super::*;
+
+use std::borrow::Cow;
+
+/// Values that can be linearly interpolated.
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, amount: Scalar) -> Self;
+}
+
+impl Lerp for Scalar {
+    #[inline]
+    fn lerp(self, other: Self, amount: Scalar) -> Self {
+        self + (other - self) * amount
+    }
+}
+
+impl Lerp for Vec2d {
+    #[inline]
+    fn lerp(self, other: Self, amount: Scalar) -> Self {
+        [
+            self[0].lerp(other[0], amount),
+            self[1].lerp(other[1], amount),
+        ]
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Self, amount: Scalar) -> Self {
+        let amount = amount as ColorComponent;
+        let mut color = self;
+        for (component, other) in color.iter_mut().zip(&other) {
+            *component += (other - *component) * amount;
+        }
+        color
+    }
+}
+
+/// Piecewise linear curve through `(time, value)` keys, with times between 0 and
+/// 1 in ascending order. Constant before the first key and after the last one.
+///
+/// Curves built with `from_static()` can be constants. Curves need at least one
+/// key, in ascending order: the constructors panic otherwise, and deserialized
+/// ones are checked by `ParticleEffect::validate()`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Curve<T: Lerp + 'static> {
+    keys: Cow<'static, [(Scalar, T)]>,
+}
+
+impl<T: Lerp + 'static> Curve<T> {
+    pub fn new(keys: Vec<(Scalar, T)>) -> Self {
+        let curve = Curve {
+            keys: Cow::Owned(keys),
+        };
+        assert!(!curve.keys.is_empty(), "curve without keys");
+        assert!(curve.is_ascending(), "curve keys out of order");
+        curve
+    }
+
+    pub const fn from_static(keys: &'static [(Scalar, T)]) -> Self {
+        assert!(!keys.is_empty(), "curve without keys");
+        let mut i = 1;
+        while i < keys.len() {
+            assert!(keys[i - 1].0 <= keys[i].0, "curve keys out of order");
+            i += 1;
+        }
+        Curve {
+            keys: Cow::Borrowed(keys),
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Curve::new(vec![(0.0, value)])
+    }
+
+    /// From `start` at time 0 to `end` at time 1.
+    pub fn linear(start: T, end: T) -> Self {
+        Curve::new(vec![(0.0, start), (1.0, end)])
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[(Scalar, T)] {
+        &self.keys
+    }
+
+    /// Whether the key times are in ascending order, none being NaN.
+    pub fn is_ascending(&self) -> bool {
+        self.keys.windows(2).all(|pair| pair[0].0 <= pair[1].0)
+            && self.keys.iter().all(|&(time, _)| !time.is_nan())
+    }
+
+    /// Value at `time`, between 0 and 1. A NaN `time` gets the first key.
+    pub fn sample(&self, time: Scalar) -> T {
+        let keys = &*self.keys;
+        let last = keys.len() - 1;
+        if time.is_nan() || time <= keys[0].0 {
+            return keys[0].1;
+        }
+        if time >= keys[last].0 {
+            return keys[last].1;
+        }
+        let next = keys.iter().position(|&(t, _)| t > time).unwrap();
+        let (t0, v0) = keys[next - 1];
+        let (t1, v1) = keys[next];
+        v0.lerp(v1, (time - t0) / (t1 - t0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FADE: Curve<Scalar> =
+        Curve::from_static(&[(0.0, 0.0), (0.25, 1.0), (1.0, 0.0)]);
+
+    #[test]
+    fn constant_before_first_and_after_last_key() {
+        let curve = Curve::new(vec![(0.2, 1.0), (0.8, 3.0)]);
+        assert_eq!(curve.sample(0.0), 1.0);
+        assert_eq!(curve.sample(0.2), 1.0);
+        assert_eq!(curve.sample(0.8), 3.0);
+        assert_eq!(curve.sample(1.0), 3.0);
+        assert_eq!(Curve::constant(7.0).sample(0.5), 7.0);
+    }
+
+    #[test]
+    fn interpolates_between_keys() {
+        assert_eq!(FADE.sample(0.125), 0.5);
+        assert_eq!(FADE.sample(0.25), 1.0);
+        assert_eq!(FADE.sample(0.625), 0.5);
+        let linear = Curve::linear(10.0, 20.0);
+        assert_eq!(linear.sample(0.3), 13.0);
+    }
+
+    #[test]
+    fn interpolates_vectors_and_colors() {
+        let size = Curve::linear([0.0, 10.0], [4.0, 20.0]);
+        assert_eq!(size.sample(0.5), [2.0, 15.0]);
+        let color = Curve::linear([0.0, 0.0, 0.0, 1.0], [1.0, 0.5, 0.0, 0.0]);
+        assert_eq!(color.sample(0.5), [0.5, 0.25, 0.0, 0.5]);
+    }
+
+    #[test]
+    fn deserializes_from_key_list() {
+        let curve: Curve<Scalar> =
+            serde_json::from_str("[[0.0, 1.0], [1.0, 2.0]]").unwrap();
+        assert_eq!(curve, Curve::linear(1.0, 2.0));
+        assert_eq!(curve.keys().len(), 2);
+    }
+
+    #[test]
+    fn samples_nan_as_first_key() {
+        assert_eq!(FADE.sample(Scalar::NAN), 0.0);
+    }
+
+    #[test]
+    fn checks_key_order() {
+        assert!(FADE.is_ascending());
+        let unsorted: Curve<Scalar> =
+            serde_json::from_str("[[0.5, 1.0], [0.2, 2.0]]").unwrap();
+        assert!(!unsorted.is_ascending());
+        let nan: Curve<Scalar> = Curve {
+            keys: Cow::Owned(vec![(Scalar::NAN, 1.0)]),
+        };
+        assert!(!nan.is_ascending());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_empty_keys() {
+        Curve::<Scalar>::new(vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_unsorted_keys() {
+        Curve::new(vec![(1.0, 0.0), (0.0, 1.0)]);
+    }
+}
@@ -0,0 +1,241 @@
+//! Emitter shapes, spawn schedules and over-lifetime curves.
+
+use super::*;
+
+/// Area new particles are spawned in, relative to the particle system origin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmitterShape {
+    Point,
+    /// Segment between two points.
+    Line(Vector2, Vector2),
+    /// Disc of the given radius.
+    Circle(Scalar),
+    /// Annulus between an inner and an outer radius.
+    Ring(Scalar, Scalar),
+    /// Centered rectangle of the given width and height.
+    Rect(Scalar, Scalar),
+    /// Simple polygon, possibly concave.
+    Polygon(Vec<Vector2>),
+}
+
+impl EmitterShape {
+    /// Uniformly distributed point within the shape.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Vector2 {
+        match *self {
+            EmitterShape::Point => Vector2::zero(),
+            EmitterShape::Line(start, end) => start.lerp(end, rng.gen()),
+            EmitterShape::Circle(radius) => Self::sample_ring(rng, 0.0, radius),
+            EmitterShape::Ring(inner, outer) => Self::sample_ring(rng, inner, outer),
+            EmitterShape::Rect(width, height) => Vector2::new(
+                (rng.gen::<Scalar>() - 0.5) * width,
+                (rng.gen::<Scalar>() - 0.5) * height,
+            ),
+            EmitterShape::Polygon(ref points) => Self::sample_polygon(rng, points),
+        }
+    }
+
+    fn sample_ring<R: Rng>(rng: &mut R, inner: Scalar, outer: Scalar) -> Vector2 {
+        let (inner_sq, outer_sq) = (inner * inner, outer * outer);
+        let radius = (inner_sq + rng.gen::<Scalar>() * (outer_sq - inner_sq)).sqrt();
+        Vector2::random2d(rng) * radius
+    }
+
+    fn sample_polygon<R: Rng>(rng: &mut R, points: &[Vector2]) -> Vector2 {
+        const MAX_ATTEMPTS: usize = 64;
+        if points.is_empty() {
+            return Vector2::zero();
+        }
+        let (mut min, mut max) = (points[0], points[0]);
+        for point in points {
+            min = Vector2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vector2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        for _ in 0..MAX_ATTEMPTS {
+            let candidate = Vector2::new(
+                min.x + rng.gen::<Scalar>() * (max.x - min.x),
+                min.y + rng.gen::<Scalar>() * (max.y - min.y),
+            );
+            if Self::polygon_contains(points, candidate) {
+                return candidate;
+            }
+        }
+        points[rng.gen_range(0, points.len())]
+    }
+
+    fn polygon_contains(points: &[Vector2], point: Vector2) -> bool {
+        let mut inside = false;
+        let mut previous = points[points.len() - 1];
+        for &current in points {
+            if (current.y > point.y) != (previous.y > point.y)
+                && point.x
+                    < (previous.x - current.x) * (point.y - current.y)
+                        / (previous.y - current.y)
+                        + current.x
+            {
+                inside = !inside;
+            }
+            previous = current;
+        }
+        inside
+    }
+}
+
+/// When an emitter spawns particles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpawnSchedule {
+    /// Particles per update, possibly fractional.
+    Continuous(Scalar),
+    /// `count` particles at once, every `interval` updates starting with the first
+    /// one, `cycles` times or forever.
+    Burst {
+        count: usize,
+        interval: usize,
+        cycles: Option<usize>,
+    },
+}
+
+/// Spawns particles within a shape, on a schedule, and shapes their colour, size,
+/// alpha and rotation over their life.
+#[derive(Debug, Clone)]
+pub struct Emitter {
+    shape: EmitterShape,
+    schedule: SpawnSchedule,
+    color: Option<Curve<Color>>,
+    size: Option<Curve<Vec2d>>,
+    alpha: Option<Curve<Scalar>>,
+    rotation: Option<Curve<Scalar>>,
+    accumulator: Scalar,
+    updates: usize,
+}
+
+impl Emitter {
+    /// Emitter spawning one particle per update from its origin.
+    pub fn new(shape: EmitterShape) -> Self {
+        Emitter {
+            shape: shape,
+            schedule: SpawnSchedule::Continuous(1.0),
+            color: None,
+            size: None,
+            alpha: None,
+            rotation: None,
+            accumulator: 0.0,
+            updates: 0,
+        }
+    }
+
+    pub fn schedule(mut self, schedule: SpawnSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Colour over life, instead of the particle system's colour.
+    pub fn color(mut self, curve: Curve<Color>) -> Self {
+        self.color = Some(curve);
+        self
+    }
+
+    /// Scale over life, 1 on both axes by default.
+    pub fn size(mut self, curve: Curve<Vec2d>) -> Self {
+        self.size = Some(curve);
+        self
+    }
+
+    /// Alpha over life, fading out linearly by default.
+    pub fn alpha(mut self, curve: Curve<Scalar>) -> Self {
+        self.alpha = Some(curve);
+        self
+    }
+
+    /// Angle over life in radians, instead of the body's angular motion.
+    pub fn rotation(mut self, curve: Curve<Scalar>) -> Self {
+        self.rotation = Some(curve);
+        self
+    }
+
+    #[inline]
+    pub fn shape(&self) -> &EmitterShape {
+        &self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: EmitterShape) {
+        self.shape = shape;
+    }
+
+    pub fn set_schedule(&mut self, schedule: SpawnSchedule) {
+        self.schedule = schedule;
+        self.restart();
+    }
+
+    /// Restarts the schedule, firing bursts again.
+    pub fn restart(&mut self) {
+        self.accumulator = 0.0;
+        self.updates = 0;
+    }
+
+    /// Whether a burst schedule ran all its cycles.
+    pub fn is_finished(&self) -> bool {
+        match self.schedule {
+            SpawnSchedule::Continuous(_) => false,
+            SpawnSchedule::Burst {
+                interval, cycles, ..
+            } => match cycles {
+                Some(cycles) => self.updates >= cycles * interval.max(1),
+                None => false,
+            },
+        }
+    }
+
+    /// Number of particles to spawn on this update.
+    pub fn tick(&mut self) -> usize {
+        let count = match self.schedule {
+            SpawnSchedule::Continuous(rate) => {
+                self.accumulator += rate;
+                let count = self.accumulator.floor().max(0.0);
+                self.accumulator -= count;
+                count as usize
+            }
+            SpawnSchedule::Burst {
+                count, interval, ..
+            } => {
+                if !self.is_finished() && self.updates.is_multiple_of(interval.max(1)) {
+                    count
+                } else {
+                    0
+                }
+            }
+        };
+        self.updates += 1;
+        count
+    }
+
+    /// Spawn position around `origin`.
+    pub fn sample<R: Rng>(&self, rng: &mut R, origin: Vector2) -> Vector2 {
+        origin + self.shape.sample(rng)
+    }
+
+    /// Updates the colour, scale, alpha and angle of `particle` according to its
+    /// age and the curves set.
+    pub fn apply_curves(&self, particle: &mut ParticleData) {
+        let age = particle.age();
+        if let Some(ref curve) = self.color {
+            particle.color = curve.sample(age);
+        }
+        if let Some(ref curve) = self.size {
+            particle.scale = curve.sample(age);
+        }
+        if let Some(ref curve) = self.alpha {
+            particle.alpha = curve.sample(age);
+        }
+        if let Some(ref curve) = self.rotation {
+            particle.body.set_angle(curve.sample(age));
+        }
+    }
+}
+
+impl Default for Emitter {
+    fn default() -> Self {
+        Emitter::new(EmitterShape::Point)
+    }
+}
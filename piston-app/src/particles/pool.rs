@@ -0,0 +1,341 @@
+//! Fixed-capacity, structure-of-arrays particle storage.
+
+use super::*;
+
+/// Function called by `ParticlePool` to get the colour and scale a particle is
+/// drawn with.
+pub type PooledParticleStyle = fn(&PooledParticle) -> (Color, Scalar);
+
+/// Snapshot of a pooled particle, as passed to its `PooledParticleStyle`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PooledParticle {
+    pub position: Vector2,
+    pub velocity: Vector2,
+    pub color: Color,
+    /// Remaining life, from 1 when spawned down to 0.
+    pub life: Scalar,
+}
+
+impl PooledParticle {
+    /// Default style: the particle colour with its alpha multiplied by the
+    /// remaining life, at scale 1.
+    pub fn fade_out(&self) -> (Color, Scalar) {
+        let mut color = self.color;
+        color[3] *= self.life.max(0.0) as ColorComponent;
+        (color, 1.0)
+    }
+}
+
+/// Counters kept by `ParticlePool`, since it was created or `reset_stats()` was
+/// last called.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParticlePoolStats {
+    /// Particles spawned into a free slot.
+    pub spawned: usize,
+    /// Particles whose life ran out, their slot recycled.
+    pub expired: usize,
+    /// Spawns refused because the pool was full.
+    pub dropped: usize,
+    /// Highest number of live particles.
+    pub peak: usize,
+}
+
+/// Particles stored as one array per attribute, allocated once for a fixed
+/// capacity. Dead particles are swap-removed, so updates and spawns never
+/// allocate, but the drawing order changes as particles expire: prefer blending
+/// modes that do not depend on it, like additive blending.
+///
+/// Pooled particles have a mass of 1 and share the pool's constant acceleration
+/// and texture atlas region.
+#[derive(Debug, Clone)]
+pub struct ParticlePool {
+    capacity: usize,
+    acceleration: Vector2,
+    texture_index: usize,
+    style: PooledParticleStyle,
+    positions: Vec<Vector2>,
+    velocities: Vec<Vector2>,
+    colors: Vec<Color>,
+    lives: Vec<Scalar>,
+    decays: Vec<Scalar>,
+    stats: ParticlePoolStats,
+}
+
+impl ParticlePool {
+    pub fn with_capacity(capacity: usize) -> Self {
+        ParticlePool {
+            capacity: capacity,
+            acceleration: Vector2::zero(),
+            texture_index: 0,
+            style: PooledParticle::fade_out,
+            positions: Vec::with_capacity(capacity),
+            velocities: Vec::with_capacity(capacity),
+            colors: Vec::with_capacity(capacity),
+            lives: Vec::with_capacity(capacity),
+            decays: Vec::with_capacity(capacity),
+            stats: ParticlePoolStats::default(),
+        }
+    }
+
+    /// Acceleration applied to every particle on every update, none by default.
+    pub fn acceleration(mut self, acceleration: Vector2) -> Self {
+        self.acceleration = acceleration;
+        self
+    }
+
+    /// Texture atlas region of the particles, 0 by default.
+    pub fn texture_index(mut self, texture_index: usize) -> Self {
+        self.texture_index = texture_index;
+        self
+    }
+
+    /// `PooledParticle::fade_out()` by default.
+    pub fn style(mut self, style: PooledParticleStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lives.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.lives.is_empty()
+    }
+
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+
+    #[inline]
+    pub fn stats(&self) -> ParticlePoolStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = ParticlePoolStats {
+            peak: self.len(),
+            ..ParticlePoolStats::default()
+        };
+    }
+
+    #[inline]
+    pub fn positions(&self) -> &[Vector2] {
+        &self.positions
+    }
+
+    #[inline]
+    pub fn velocities(&self) -> &[Vector2] {
+        &self.velocities
+    }
+
+    #[inline]
+    pub fn lives(&self) -> &[Scalar] {
+        &self.lives
+    }
+
+    pub fn get(&self, index: usize) -> Option<PooledParticle> {
+        if index < self.len() {
+            Some(PooledParticle {
+                position: self.positions[index],
+                velocity: self.velocities[index],
+                color: self.colors[index],
+                life: self.lives[index],
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = PooledParticle> + 'a {
+        (0..self.len()).map(move |index| self.get(index).unwrap())
+    }
+
+    /// Spawns a particle living for `lifetime` updates, unless the pool is full.
+    /// Returns whether the particle was spawned.
+    pub fn spawn(
+        &mut self,
+        position: Vector2,
+        velocity: Vector2,
+        color: Color,
+        lifetime: Scalar,
+    ) -> bool {
+        if self.is_full() {
+            self.stats.dropped += 1;
+            return false;
+        }
+        self.positions.push(position);
+        self.velocities.push(velocity);
+        self.colors.push(color);
+        self.lives.push(1.0);
+        self.decays.push(1.0 / lifetime);
+        self.stats.spawned += 1;
+        self.stats.peak = self.stats.peak.max(self.len());
+        true
+    }
+
+    /// Moves and ages the particles, recycling the slots of the dead ones.
+    pub fn update(&mut self) {
+        let acceleration = self.acceleration;
+        for (velocity, position) in self.velocities.iter_mut().zip(&mut self.positions) {
+            *velocity += acceleration;
+            *position += *velocity;
+        }
+        for (life, decay) in self.lives.iter_mut().zip(&self.decays) {
+            *life -= decay;
+        }
+        let mut index = 0;
+        while index < self.lives.len() {
+            if self.lives[index] > 0.0 {
+                index += 1;
+            } else {
+                self.swap_remove(index);
+                self.stats.expired += 1;
+            }
+        }
+    }
+
+    /// Removes every particle, keeping the storage.
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.velocities.clear();
+        self.colors.clear();
+        self.lives.clear();
+        self.decays.clear();
+    }
+
+    /// Adds a sprite per particle, colored and scaled by the pool's style, to
+    /// `batch`.
+    pub fn extend_sprite_batch(
+        &self,
+        texture_atlas: &TextureAtlas,
+        batch: &mut SpriteBatch,
+    ) {
+        for particle in self.iter() {
+            let (color, scale) = (self.style)(&particle);
+            let transform = math::identity()
+                .trans(particle.position.x, particle.position.y)
+                .zoom(scale);
+            batch.sprite(texture_atlas, self.texture_index, transform, color);
+        }
+    }
+
+    fn swap_remove(&mut self, index: usize) {
+        self.positions.swap_remove(index);
+        self.velocities.swap_remove(index);
+        self.colors.swap_remove(index);
+        self.lives.swap_remove(index);
+        self.decays.swap_remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = [1.0, 1.0, 1.0, 1.0];
+
+    fn spawn(pool: &mut ParticlePool, x: Scalar, lifetime: Scalar) -> bool {
+        pool.spawn(Vector2::new(x, 0.0), Vector2::zero(), WHITE, lifetime)
+    }
+
+    #[test]
+    fn spawns_until_full() {
+        let mut pool = ParticlePool::with_capacity(2);
+        assert!(pool.is_empty());
+        assert!(spawn(&mut pool, 0.0, 4.0));
+        assert!(spawn(&mut pool, 1.0, 4.0));
+        assert!(pool.is_full());
+        assert!(!spawn(&mut pool, 2.0, 4.0));
+        assert_eq!(pool.len(), 2);
+        assert_eq!(pool.capacity(), 2);
+        assert_eq!(
+            pool.stats(),
+            ParticlePoolStats {
+                spawned: 2,
+                expired: 0,
+                dropped: 1,
+                peak: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn moves_particles_with_the_pool_acceleration() {
+        let mut pool =
+            ParticlePool::with_capacity(1).acceleration(Vector2::new(0.0, 1.0));
+        pool.spawn(Vector2::zero(), Vector2::new(2.0, 0.0), WHITE, 4.0);
+        pool.update();
+        pool.update();
+        let particle = pool.get(0).unwrap();
+        assert_eq!(particle.velocity, Vector2::new(2.0, 2.0));
+        assert_eq!(particle.position, Vector2::new(4.0, 3.0));
+        assert_eq!(particle.life, 0.5);
+        assert!(pool.get(1).is_none());
+    }
+
+    #[test]
+    fn retires_expired_particles() {
+        let mut pool = ParticlePool::with_capacity(3);
+        spawn(&mut pool, 0.0, 1.0);
+        spawn(&mut pool, 1.0, 4.0);
+        spawn(&mut pool, 2.0, 2.0);
+        pool.update();
+        // The last particle took the slot of the first one.
+        assert_eq!(
+            pool.positions(),
+            &[Vector2::new(2.0, 0.0), Vector2::new(1.0, 0.0)]
+        );
+        assert_eq!(pool.lives(), &[0.5, 0.75]);
+        pool.update();
+        assert_eq!(pool.len(), 1);
+        assert_eq!(pool.iter().next().unwrap().position.x, 1.0);
+        assert_eq!(pool.stats().expired, 2);
+        assert!(spawn(&mut pool, 3.0, 1.0));
+        assert_eq!(pool.stats().peak, 3);
+    }
+
+    #[test]
+    fn reset_stats_keeps_live_particles_as_peak() {
+        let mut pool = ParticlePool::with_capacity(4);
+        spawn(&mut pool, 0.0, 1.0);
+        spawn(&mut pool, 1.0, 4.0);
+        spawn(&mut pool, 2.0, 4.0);
+        pool.update();
+        pool.reset_stats();
+        assert_eq!(
+            pool.stats(),
+            ParticlePoolStats {
+                peak: 2,
+                ..ParticlePoolStats::default()
+            }
+        );
+        pool.clear();
+        assert!(pool.is_empty());
+        assert_eq!(pool.velocities().len(), 0);
+    }
+
+    #[test]
+    fn fade_out_scales_alpha_by_life() {
+        let particle = PooledParticle {
+            position: Vector2::zero(),
+            velocity: Vector2::zero(),
+            color: [1.0, 0.5, 0.0, 0.8],
+            life: 0.5,
+        };
+        assert_eq!(particle.fade_out(), ([1.0, 0.5, 0.0, 0.4], 1.0));
+        let dead = PooledParticle {
+            life: -0.1,
+            ..particle
+        };
+        assert_eq!(dead.fade_out().0[3], 0.0);
+    }
+}
@@ -0,0 +1,313 @@
+//! Particle effect presets, loaded from JSON files.
+
+use super::*;
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Description of a particle effect, usually loaded from a JSON file such as:
+///
+/// ```json
+/// {
+///     "shape": { "circle": 8.0 },
+///     "schedule": { "continuous": 2.0 },
+///     "lifetime": 96.0,
+///     "velocity": { "normal": { "mean": { "x": 0.0, "y": -4.2 },
+///                               "deviation": { "x": 0.24, "y": 0.24 } } },
+///     "gravity": { "x": 0.0, "y": 0.05 },
+///     "blend": "additive",
+///     "texture": "assets/particle.png",
+///     "color": [[0.0, [1.0, 0.8, 0.2, 1.0]], [1.0, [1.0, 0.1, 0.0, 1.0]]]
+/// }
+/// ```
+///
+/// Missing fields take the `ParticleSystem` defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParticleEffect {
+    pub shape: EmitterShape,
+    /// Spawns at most `ParticleEffect::MAX_SPAWN` particles per update.
+    pub schedule: SpawnSchedule,
+    /// Number of updates a particle lives for.
+    pub lifetime: Scalar,
+    pub velocity: VelocityDistribution,
+    /// Acceleration applied to every particle, on every update.
+    pub gravity: Vector2,
+    pub blend: BlendMode,
+    pub texture: String,
+    /// Texture atlas file, as parsed by `TextureAtlas::from_paths()`.
+    pub atlas: Option<String>,
+    pub texture_index: usize,
+    /// Colour gradient over life, Perlin noise hues if unset.
+    pub color: Option<Curve<Color>>,
+    pub size: Option<Curve<Vec2d>>,
+    pub alpha: Option<Curve<Scalar>>,
+    pub rotation: Option<Curve<Scalar>>,
+}
+
+impl ParticleEffect {
+    /// Upper bound of the particles spawned per update, rejecting schedules that
+    /// would exhaust memory.
+    pub const MAX_SPAWN: usize = 100_000;
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let effect: ParticleEffect =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        effect.validate()?;
+        Ok(effect)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Box<dyn Error>> {
+        let effect: ParticleEffect = serde_json::from_str(json)?;
+        effect.validate()?;
+        Ok(effect)
+    }
+
+    pub fn emitter(&self) -> Emitter {
+        let mut emitter = Emitter::new(self.shape.clone()).schedule(self.schedule);
+        if let Some(ref curve) = self.color {
+            emitter = emitter.color(curve.clone());
+        }
+        if let Some(ref curve) = self.size {
+            emitter = emitter.size(curve.clone());
+        }
+        if let Some(ref curve) = self.alpha {
+            emitter = emitter.alpha(curve.clone());
+        }
+        if let Some(ref curve) = self.rotation {
+            emitter = emitter.rotation(curve.clone());
+        }
+        emitter
+    }
+
+    /// Particle system playing the effect from (`x`, `y`).
+    pub fn particle_system(
+        &self,
+        state: &PistonAppState,
+        x: Scalar,
+        y: Scalar,
+    ) -> ParticleSystem<ParticleData> {
+        ParticleSystem::new(state, x, y, |_, data| data)
+            .emitter(self.emitter())
+            .lifetime(self.lifetime)
+            .velocity_distribution(self.velocity)
+            .texture_index(self.texture_index)
+            .force(physics::Gravity::new(self.gravity.x, self.gravity.y))
+    }
+
+    /// Loads the texture and its atlas, failing if `texture_index` is not one of
+    /// its regions.
+    pub fn texture_atlas(
+        &self,
+        window: &mut PistonAppWindow,
+    ) -> Result<TextureAtlas, Box<dyn Error>> {
+        let texture_atlas = TextureAtlas::from_maybe_paths(
+            window,
+            self.texture.as_str(),
+            self.atlas.as_deref(),
+        )?;
+        self.validate_texture_index(texture_atlas.len())?;
+        Ok(texture_atlas)
+    }
+
+    fn validate(&self) -> Result<(), Box<dyn Error>> {
+        if self.lifetime.is_nan() || self.lifetime <= 0.0 {
+            return Err("the lifetime must be positive".into());
+        }
+        match self.schedule {
+            SpawnSchedule::Continuous(rate) => {
+                if !(0.0..=Self::MAX_SPAWN as Scalar).contains(&rate) {
+                    return Err(format!(
+                        "the spawn rate must be between 0 and {}",
+                        Self::MAX_SPAWN
+                    )
+                    .into());
+                }
+            }
+            SpawnSchedule::Burst {
+                count,
+                interval,
+                cycles,
+            } => {
+                if count > Self::MAX_SPAWN {
+                    return Err(format!(
+                        "bursts spawn at most {} particles",
+                        Self::MAX_SPAWN
+                    )
+                    .into());
+                }
+                if cycles
+                    .is_some_and(|cycles| cycles.checked_mul(interval.max(1)).is_none())
+                {
+                    return Err("too many burst cycles".into());
+                }
+            }
+        }
+        let empty_curve = self.color.as_ref().is_some_and(|c| c.keys().is_empty())
+            || self.size.as_ref().is_some_and(|c| c.keys().is_empty())
+            || self.alpha.as_ref().is_some_and(|c| c.keys().is_empty())
+            || self.rotation.as_ref().is_some_and(|c| c.keys().is_empty());
+        if empty_curve {
+            return Err("curves need at least one key".into());
+        }
+        let unsorted_curve = self.color.as_ref().is_some_and(|c| !c.is_ascending())
+            || self.size.as_ref().is_some_and(|c| !c.is_ascending())
+            || self.alpha.as_ref().is_some_and(|c| !c.is_ascending())
+            || self.rotation.as_ref().is_some_and(|c| !c.is_ascending());
+        if unsorted_curve {
+            return Err("curve keys must be in ascending time order".into());
+        }
+        Ok(())
+    }
+
+    fn validate_texture_index(&self, regions: usize) -> Result<(), Box<dyn Error>> {
+        if self.texture_index >= regions {
+            return Err(format!(
+                "texture index {} out of range for an atlas of {} regions",
+                self.texture_index, regions
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl Default for ParticleEffect {
+    fn default() -> Self {
+        ParticleEffect {
+            shape: EmitterShape::Point,
+            schedule: SpawnSchedule::Continuous(1.0),
+            lifetime: 128.0,
+            velocity: VelocityDistribution::Uniform {
+                min: Vector2::new(-1.0, -2.0),
+                max: Vector2::new(1.0, 0.0),
+            },
+            gravity: Vector2::zero(),
+            blend: BlendMode::Alpha,
+            texture: "assets/particle.png".to_owned(),
+            atlas: None,
+            texture_index: 0,
+            color: None,
+            size: None,
+            alpha: None,
+            rotation: None,
+        }
+    }
+}
+
+/// Particle effect file, reloaded when modified.
+#[derive(Debug)]
+pub struct ParticleEffectFile {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    effect: ParticleEffect,
+}
+
+impl ParticleEffectFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+        Ok(ParticleEffectFile {
+            modified: Self::modified(&path),
+            effect: ParticleEffect::from_path(&path)?,
+            path: path,
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn effect(&self) -> &ParticleEffect {
+        &self.effect
+    }
+
+    /// Reloads the effect if the file changed since it was last read, and returns
+    /// whether it did. On error, the previous effect is kept.
+    pub fn reload_if_modified(&mut self) -> Result<bool, Box<dyn Error>> {
+        let modified = Self::modified(&self.path);
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+        self.effect = ParticleEffect::from_path(&self.path)?;
+        Ok(true)
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejects(json: &str) -> bool {
+        ParticleEffect::from_json(json).is_err()
+    }
+
+    #[test]
+    fn parses_presets() {
+        let effect = ParticleEffect::from_json(
+            r#"{
+                "shape": { "ring": [4.0, 12.0] },
+                "schedule": { "burst": { "count": 64, "interval": 90, "cycles": null } },
+                "lifetime": 48.0,
+                "blend": "additive",
+                "size": [[0.0, [4.0, 4.0]], [1.0, [0.5, 0.5]]]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(effect.shape, EmitterShape::Ring(4.0, 12.0));
+        assert_eq!(effect.lifetime, 48.0);
+        assert_eq!(effect.texture, ParticleEffect::default().texture);
+        assert!(ParticleEffect::from_json("{}").is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(rejects(r#"{ "lifetme": 12.0 }"#));
+    }
+
+    #[test]
+    fn rejects_non_positive_lifetimes() {
+        assert!(rejects(r#"{ "lifetime": 0.0 }"#));
+        assert!(rejects(r#"{ "lifetime": -1.0 }"#));
+    }
+
+    #[test]
+    fn rejects_empty_or_unsorted_curves() {
+        assert!(rejects(r#"{ "alpha": [] }"#));
+        assert!(rejects(r#"{ "alpha": [[0.8, 1.0], [0.2, 0.0]] }"#));
+        assert!(!rejects(
+            r#"{ "alpha": [[0.2, 1.0], [0.2, 0.5], [0.8, 0.0]] }"#
+        ));
+    }
+
+    #[test]
+    fn rejects_out_of_range_texture_indices() {
+        let effect = ParticleEffect::from_json(r#"{ "texture_index": 2 }"#).unwrap();
+        assert!(effect.validate_texture_index(3).is_ok());
+        assert!(effect.validate_texture_index(2).is_err());
+    }
+
+    #[test]
+    fn bounds_spawn_schedules() {
+        assert!(rejects(r#"{ "schedule": { "continuous": 1e300 } }"#));
+        assert!(rejects(r#"{ "schedule": { "continuous": -1.0 } }"#));
+        assert!(!rejects(r#"{ "schedule": { "continuous": 0.5 } }"#));
+        let burst = |count: usize, cycles: usize| {
+            format!(
+                r#"{{ "schedule": {{ "burst": {{ "count": {}, "interval": 60, "cycles": {} }} }} }}"#,
+                count, cycles
+            )
+        };
+        assert!(!rejects(&burst(ParticleEffect::MAX_SPAWN, 3)));
+        assert!(rejects(&burst(ParticleEffect::MAX_SPAWN + 1, 3)));
+        assert!(rejects(&burst(1, usize::MAX)));
+    }
+}
@@ -0,0 +1,11 @@
+//! Simple rigid-point physics, shared by the forces and oscillation sketches.
+
+use super::*;
+
+use std::ops::Range;
+
+pub use self::forces::*;
+pub use self::mover::{Edges, Mover};
+
+mod forces;
+mod mover;
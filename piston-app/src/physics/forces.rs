@@ -0,0 +1,433 @@
+//! Force generators, and a registry applying them to sets of bodies.
+
+use super::*;
+
+use std::fmt::Debug;
+
+/// Computes the force acting on a body, from the body's current state.
+pub trait ForceGenerator: Debug {
+    fn force(&self, body: &Mover) -> Vector2;
+
+    fn apply(&self, body: &mut Mover) {
+        let force = self.force(body);
+        body.apply_force(force);
+    }
+
+    fn apply_all<B: AsMut<Mover>>(&self, bodies: &mut [B])
+    where
+        Self: Sized,
+    {
+        for body in bodies {
+            self.apply(body.as_mut());
+        }
+    }
+}
+
+/// Uniform gravitational field, pulling every body with a force proportional to
+/// its mass.
+#[derive(Debug, Clone, Copy)]
+pub struct Gravity {
+    acceleration: Vector2,
+}
+
+impl Gravity {
+    pub fn new(x: Scalar, y: Scalar) -> Self {
+        Gravity {
+            acceleration: Vector2::new(x, y),
+        }
+    }
+}
+
+impl ForceGenerator for Gravity {
+    fn force(&self, body: &Mover) -> Vector2 {
+        self.acceleration * body.mass()
+    }
+}
+
+/// Constant force, regardless of the mass: lighter bodies are blown further.
+#[derive(Debug, Clone, Copy)]
+pub struct Wind {
+    force: Vector2,
+}
+
+impl Wind {
+    pub fn new(x: Scalar, y: Scalar) -> Self {
+        Wind {
+            force: Vector2::new(x, y),
+        }
+    }
+
+    #[inline]
+    pub fn set_force(&mut self, force: Vector2) {
+        self.force = force;
+    }
+}
+
+impl ForceGenerator for Wind {
+    fn force(&self, _: &Mover) -> Vector2 {
+        self.force
+    }
+}
+
+/// Kinetic friction, opposing the velocity with a constant magnitude.
+#[derive(Debug, Clone, Copy)]
+pub struct Friction {
+    mu: Scalar,
+    normal: Scalar,
+}
+
+impl Friction {
+    pub fn new(mu: Scalar) -> Self {
+        Friction {
+            mu: mu,
+            normal: 1.0,
+        }
+    }
+
+    /// Magnitude of the normal force, 1 by default.
+    pub fn normal(mut self, normal: Scalar) -> Self {
+        self.normal = normal;
+        self
+    }
+}
+
+impl ForceGenerator for Friction {
+    fn force(&self, body: &Mover) -> Vector2 {
+        body.velocity().normalize() * (-self.mu * self.normal)
+    }
+}
+
+/// Fluid drag, opposing the velocity proportionally to the squared speed,
+/// optionally only within a rectangular area.
+#[derive(Debug, Clone, Copy)]
+pub struct Drag {
+    coefficient: Scalar,
+    area: Option<types::Rectangle>,
+}
+
+impl Drag {
+    pub fn new(coefficient: Scalar) -> Self {
+        Drag {
+            coefficient: coefficient,
+            area: None,
+        }
+    }
+
+    /// Only slows down bodies strictly inside `area`.
+    pub fn area<R: Into<types::Rectangle>>(mut self, area: R) -> Self {
+        self.area = Some(area.into());
+        self
+    }
+
+    #[inline]
+    pub fn coefficient(&self) -> Scalar {
+        self.coefficient
+    }
+
+    pub fn contains(&self, position: Vector2) -> bool {
+        match self.area {
+            Some(rect) => {
+                position.x > rect[0]
+                    && position.x < rect[0] + rect[2]
+                    && position.y > rect[1]
+                    && position.y < rect[1] + rect[3]
+            }
+            None => true,
+        }
+    }
+}
+
+impl ForceGenerator for Drag {
+    fn force(&self, body: &Mover) -> Vector2 {
+        if !self.contains(body.position()) {
+            return Vector2::zero();
+        }
+        let velocity = body.velocity();
+        velocity.normalize() * (-self.coefficient * velocity.mag_sq())
+    }
+}
+
+/// Gravitational attraction towards a point mass, or repulsion away from it with
+/// a negative `g`. The distance is clamped to avoid extreme forces.
+#[derive(Debug, Clone, Copy)]
+pub struct Attraction {
+    position: Vector2,
+    mass: Scalar,
+    g: Scalar,
+    min_distance: Scalar,
+    max_distance: Scalar,
+    scaled_by_body_mass: bool,
+}
+
+impl Attraction {
+    pub fn new(position: Vector2, mass: Scalar, g: Scalar) -> Self {
+        Attraction {
+            position: position,
+            mass: mass,
+            g: g,
+            min_distance: 5.0,
+            max_distance: Scalar::INFINITY,
+            scaled_by_body_mass: true,
+        }
+    }
+
+    /// Repulsion of the given `strength`, regardless of the body's mass.
+    pub fn repulsion(position: Vector2, strength: Scalar) -> Self {
+        Attraction {
+            scaled_by_body_mass: false,
+            ..Attraction::new(position, strength, -1.0)
+        }
+    }
+
+    /// Distance range used to compute the force, 5 to infinity by default.
+    pub fn distance(mut self, min: Scalar, max: Scalar) -> Self {
+        self.min_distance = min;
+        self.max_distance = max;
+        self
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector2 {
+        self.position
+    }
+
+    #[inline]
+    pub fn set_position(&mut self, position: Vector2) {
+        self.position = position;
+    }
+
+    #[inline]
+    pub fn mass(&self) -> Scalar {
+        self.mass
+    }
+
+    #[inline]
+    pub fn g(&self) -> Scalar {
+        self.g
+    }
+
+    fn magnitude(&self, body_mass: Scalar, distance: Scalar) -> Scalar {
+        let distance = distance.max(self.min_distance).min(self.max_distance);
+        self.g * self.mass * body_mass / (distance * distance)
+    }
+}
+
+impl ForceGenerator for Attraction {
+    fn force(&self, body: &Mover) -> Vector2 {
+        let direction = self.position - body.position();
+        let body_mass = if self.scaled_by_body_mass {
+            body.mass()
+        } else {
+            1.0
+        };
+        direction.set_mag(self.magnitude(body_mass, direction.mag()))
+    }
+}
+
+/// Hooke's law spring between a fixed anchor and the body.
+#[derive(Debug, Clone, Copy)]
+pub struct Spring {
+    anchor: Vector2,
+    rest_length: Scalar,
+    k: Scalar,
+}
+
+impl Spring {
+    pub fn new(anchor: Vector2, rest_length: Scalar, k: Scalar) -> Self {
+        Spring {
+            anchor: anchor,
+            rest_length: rest_length,
+            k: k,
+        }
+    }
+
+    #[inline]
+    pub fn anchor(&self) -> Vector2 {
+        self.anchor
+    }
+
+    #[inline]
+    pub fn set_anchor(&mut self, anchor: Vector2) {
+        self.anchor = anchor;
+    }
+}
+
+impl ForceGenerator for Spring {
+    fn force(&self, body: &Mover) -> Vector2 {
+        let extension = body.position() - self.anchor;
+        extension.set_mag(-self.k * (extension.mag() - self.rest_length))
+    }
+}
+
+/// Bodies a registered `ForceGenerator` applies to, by index.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BodySet {
+    All,
+    Only(Vec<usize>),
+    Range(Range<usize>),
+}
+
+impl BodySet {
+    pub fn contains(&self, index: usize) -> bool {
+        match *self {
+            BodySet::All => true,
+            BodySet::Only(ref indices) => indices.contains(&index),
+            BodySet::Range(ref range) => range.contains(&index),
+        }
+    }
+}
+
+/// Force generators registered against sets of bodies, applied together once
+/// per step.
+#[derive(Debug, Default)]
+pub struct ForceRegistry {
+    generators: Vec<(Box<dyn ForceGenerator>, BodySet)>,
+}
+
+impl ForceRegistry {
+    pub fn new() -> Self {
+        ForceRegistry { generators: vec![] }
+    }
+
+    pub fn add<G: ForceGenerator + 'static>(&mut self, generator: G, bodies: BodySet) {
+        self.generators.push((Box::new(generator), bodies));
+    }
+
+    pub fn clear(&mut self) {
+        self.generators.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.generators.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.generators.is_empty()
+    }
+
+    /// Applies the force of every registered generator to the bodies in its set.
+    pub fn apply<B: AsMut<Mover>>(&self, bodies: &mut [B]) {
+        for (index, body) in bodies.iter_mut().enumerate() {
+            let body = body.as_mut();
+            for (generator, set) in &self.generators {
+                if set.contains(index) {
+                    generator.apply(body);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vector::tests::assert_close;
+
+    fn moving(x: Scalar, y: Scalar, mass: Scalar, velocity: Vector2) -> Mover {
+        Mover::new(x, y, mass).with_velocity(velocity)
+    }
+
+    #[test]
+    fn gravity_scales_with_mass() {
+        let gravity = Gravity::new(0.0, 0.1);
+        assert_close(
+            gravity.force(&Mover::new(0.0, 0.0, 2.0)),
+            Vector2::new(0.0, 0.2),
+        );
+        let mut body = Mover::new(0.0, 0.0, 5.0);
+        gravity.apply(&mut body);
+        assert_close(body.acceleration(), Vector2::new(0.0, 0.1));
+    }
+
+    #[test]
+    fn wind_ignores_mass() {
+        let mut wind = Wind::new(0.5, 0.0);
+        let mut bodies = [Mover::new(0.0, 0.0, 1.0), Mover::new(0.0, 0.0, 4.0)];
+        wind.apply_all(&mut bodies);
+        assert_close(bodies[0].acceleration(), Vector2::new(0.5, 0.0));
+        assert_close(bodies[1].acceleration(), Vector2::new(0.125, 0.0));
+        wind.set_force(Vector2::new(0.0, -1.0));
+        assert_close(wind.force(&bodies[0]), Vector2::new(0.0, -1.0));
+    }
+
+    #[test]
+    fn friction_opposes_velocity() {
+        let friction = Friction::new(0.1).normal(2.0);
+        let body = moving(0.0, 0.0, 1.0, Vector2::new(3.0, 4.0));
+        assert_close(friction.force(&body), Vector2::new(-0.12, -0.16));
+        assert_eq!(friction.force(&Mover::new(0.0, 0.0, 1.0)), Vector2::zero());
+    }
+
+    #[test]
+    fn drag_grows_with_speed_within_area() {
+        let drag = Drag::new(0.1).area([0.0, 0.0, 10.0, 10.0]);
+        let inside = moving(5.0, 5.0, 1.0, Vector2::new(0.0, 2.0));
+        assert_close(drag.force(&inside), Vector2::new(0.0, -0.4));
+        let outside = moving(15.0, 5.0, 1.0, Vector2::new(0.0, 2.0));
+        assert_eq!(drag.force(&outside), Vector2::zero());
+        assert!(!drag.contains(Vector2::new(0.0, 5.0)));
+        assert!(Drag::new(0.1).contains(Vector2::new(-100.0, 100.0)));
+    }
+
+    #[test]
+    fn attraction_clamps_distance() {
+        let attraction =
+            Attraction::new(Vector2::new(0.0, 0.0), 10.0, 1.0).distance(5.0, 20.0);
+        let near = Mover::new(1.0, 0.0, 2.0);
+        assert_close(attraction.force(&near), Vector2::new(-0.8, 0.0));
+        let mid = Mover::new(0.0, 10.0, 2.0);
+        assert_close(attraction.force(&mid), Vector2::new(0.0, -0.2));
+        let far = Mover::new(0.0, -100.0, 2.0);
+        assert_close(attraction.force(&far), Vector2::new(0.0, 0.05));
+    }
+
+    #[test]
+    fn repulsion_ignores_body_mass() {
+        let repulsion = Attraction::repulsion(Vector2::new(0.0, 0.0), 100.0);
+        let light = repulsion.force(&Mover::new(10.0, 0.0, 1.0));
+        let heavy = repulsion.force(&Mover::new(10.0, 0.0, 8.0));
+        assert_close(light, Vector2::new(1.0, 0.0));
+        assert_close(heavy, light);
+    }
+
+    #[test]
+    fn spring_restores_rest_length() {
+        let spring = Spring::new(Vector2::new(0.0, 0.0), 10.0, 0.5);
+        assert_close(
+            spring.force(&Mover::new(0.0, 14.0, 1.0)),
+            Vector2::new(0.0, -2.0),
+        );
+        assert_close(
+            spring.force(&Mover::new(6.0, 0.0, 1.0)),
+            Vector2::new(2.0, 0.0),
+        );
+        assert_close(spring.force(&Mover::new(10.0, 0.0, 1.0)), Vector2::zero());
+    }
+
+    #[test]
+    fn body_sets() {
+        assert!(BodySet::All.contains(42));
+        assert!(BodySet::Only(vec![1, 3]).contains(3));
+        assert!(!BodySet::Only(vec![1, 3]).contains(2));
+        assert!(BodySet::Range(2..4).contains(2));
+        assert!(!BodySet::Range(2..4).contains(4));
+    }
+
+    #[test]
+    fn registry_applies_generators_to_their_sets() {
+        let mut registry = ForceRegistry::new();
+        registry.add(Wind::new(1.0, 0.0), BodySet::All);
+        registry.add(Gravity::new(0.0, 1.0), BodySet::Only(vec![1]));
+        assert_eq!(registry.len(), 2);
+
+        let mut bodies = vec![Mover::new(0.0, 0.0, 2.0), Mover::new(0.0, 0.0, 2.0)];
+        registry.apply(&mut bodies);
+        assert_close(bodies[0].acceleration(), Vector2::new(0.5, 0.0));
+        assert_close(bodies[1].acceleration(), Vector2::new(0.5, 1.0));
+
+        registry.clear();
+        assert!(registry.is_empty());
+    }
+}
@@ -0,0 +1,317 @@
+//! Point mass moved by accumulated forces.
+
+use super::*;
+
+/// What happens when a `Mover` reaches the edges of the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edges {
+    /// Moves freely past the edges.
+    None,
+    /// Reappears on the opposite edge.
+    Wrap,
+    /// Bounces off the edges, keeping this fraction of its speed.
+    Bounce(Scalar),
+    /// Stops at the edges.
+    Clamp,
+}
+
+/// Point mass with linear and angular motion, integrated once per `update()`
+/// from the forces applied since the previous one.
+#[derive(Debug, Clone)]
+pub struct Mover {
+    position: Vector2,
+    velocity: Vector2,
+    acceleration: Vector2,
+    mass: Scalar,
+    max_speed: Option<Scalar>,
+    angle: Scalar,
+    angular_velocity: Scalar,
+    angular_acceleration: Scalar,
+    max_angular_speed: Option<Scalar>,
+    radius: Scalar,
+    edges: Edges,
+}
+
+impl Mover {
+    pub fn new(x: Scalar, y: Scalar, mass: Scalar) -> Self {
+        Mover {
+            position: Vector2::new(x, y),
+            velocity: Vector2::zero(),
+            acceleration: Vector2::zero(),
+            mass: mass,
+            max_speed: None,
+            angle: 0.0,
+            angular_velocity: 0.0,
+            angular_acceleration: 0.0,
+            max_angular_speed: None,
+            radius: 0.0,
+            edges: Edges::None,
+        }
+    }
+
+    pub fn with_velocity(mut self, velocity: Vector2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Velocity magnitude limit, unlimited by default.
+    pub fn with_max_speed(mut self, max_speed: Scalar) -> Self {
+        self.max_speed = Some(max_speed);
+        self
+    }
+
+    /// Angular velocity limit in radians per step, unlimited by default.
+    pub fn with_max_angular_speed(mut self, max_angular_speed: Scalar) -> Self {
+        self.max_angular_speed = Some(max_angular_speed.abs());
+        self
+    }
+
+    /// Distance kept from the edges when bouncing or clamping, and travelled past
+    /// them before wrapping.
+    pub fn with_radius(mut self, radius: Scalar) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_edges(mut self, edges: Edges) -> Self {
+        self.edges = edges;
+        self
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vector2 {
+        self.position
+    }
+
+    #[inline]
+    pub fn set_position(&mut self, position: Vector2) {
+        self.position = position;
+    }
+
+    #[inline]
+    pub fn velocity(&self) -> Vector2 {
+        self.velocity
+    }
+
+    #[inline]
+    pub fn set_velocity(&mut self, velocity: Vector2) {
+        self.velocity = velocity;
+    }
+
+    /// Acceleration accumulated since the last `update()`.
+    #[inline]
+    pub fn acceleration(&self) -> Vector2 {
+        self.acceleration
+    }
+
+    #[inline]
+    pub fn mass(&self) -> Scalar {
+        self.mass
+    }
+
+    #[inline]
+    pub fn radius(&self) -> Scalar {
+        self.radius
+    }
+
+    #[inline]
+    pub fn angle(&self) -> Scalar {
+        self.angle
+    }
+
+    #[inline]
+    pub fn set_angle(&mut self, angle: Scalar) {
+        self.angle = angle;
+    }
+
+    #[inline]
+    pub fn angular_velocity(&self) -> Scalar {
+        self.angular_velocity
+    }
+
+    #[inline]
+    pub fn set_angular_velocity(&mut self, angular_velocity: Scalar) {
+        self.angular_velocity = angular_velocity;
+    }
+
+    /// Accelerates by `force` divided by the mass, until the next `update()`.
+    pub fn apply_force(&mut self, force: Vector2) {
+        self.acceleration += force / self.mass;
+    }
+
+    /// Adds `acceleration` regardless of the mass, until the next `update()`.
+    pub fn accelerate(&mut self, acceleration: Vector2) {
+        self.acceleration += acceleration;
+    }
+
+    /// Adds angular `acceleration`, in radians per step squared, until the next
+    /// `update()`.
+    pub fn apply_angular_acceleration(&mut self, acceleration: Scalar) {
+        self.angular_acceleration += acceleration;
+    }
+
+    /// Integrates the accumulated forces, then keeps the mover within the
+    /// window according to its `Edges`.
+    pub fn update(&mut self, state: &PistonAppState) {
+        self.integrate();
+        self.check_edges(state.width(), state.height());
+    }
+
+    /// Integrates the accumulated forces, then clears them.
+    pub fn integrate(&mut self) {
+        self.velocity += self.acceleration;
+        if let Some(max_speed) = self.max_speed {
+            self.velocity = self.velocity.limit(max_speed);
+        }
+        self.position += self.velocity;
+        self.angular_velocity += self.angular_acceleration;
+        if let Some(max) = self.max_angular_speed {
+            self.angular_velocity = self.angular_velocity.clamp(-max, max);
+        }
+        self.angle += self.angular_velocity;
+        self.acceleration = Vector2::zero();
+        self.angular_acceleration = 0.0;
+    }
+
+    pub fn check_edges(&mut self, width: Scalar, height: Scalar) {
+        let (x, vx) = self.constrain(self.position.x, self.velocity.x, width);
+        let (y, vy) = self.constrain(self.position.y, self.velocity.y, height);
+        self.position = Vector2::new(x, y);
+        self.velocity = Vector2::new(vx, vy);
+    }
+
+    fn constrain(
+        &self,
+        position: Scalar,
+        velocity: Scalar,
+        max: Scalar,
+    ) -> (Scalar, Scalar) {
+        let radius = self.radius;
+        match self.edges {
+            Edges::None => (position, velocity),
+            Edges::Wrap => {
+                if position > max + radius {
+                    (-radius, velocity)
+                } else if position < -radius {
+                    (max + radius, velocity)
+                } else {
+                    (position, velocity)
+                }
+            }
+            Edges::Bounce(restitution) => {
+                if position > max - radius {
+                    (max - radius, -velocity.abs() * restitution)
+                } else if position < radius {
+                    (radius, velocity.abs() * restitution)
+                } else {
+                    (position, velocity)
+                }
+            }
+            Edges::Clamp => {
+                if position > max - radius || position < radius {
+                    (position.clamp(radius, (max - radius).max(radius)), 0.0)
+                } else {
+                    (position, velocity)
+                }
+            }
+        }
+    }
+}
+
+impl AsMut<Mover> for Mover {
+    fn as_mut(&mut self) -> &mut Mover {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vector::tests::assert_close;
+
+    fn at(x: Scalar, y: Scalar, velocity: Vector2, edges: Edges) -> Mover {
+        Mover::new(x, y, 1.0)
+            .with_velocity(velocity)
+            .with_radius(5.0)
+            .with_edges(edges)
+    }
+
+    #[test]
+    fn wraps_past_the_edges() {
+        let mut mover = at(106.0, -6.0, Vector2::new(1.0, -1.0), Edges::Wrap);
+        mover.check_edges(100.0, 50.0);
+        assert_eq!(mover.position(), Vector2::new(-5.0, 55.0));
+        assert_eq!(mover.velocity(), Vector2::new(1.0, -1.0));
+
+        let mut inside = at(104.0, 0.0, Vector2::zero(), Edges::Wrap);
+        inside.check_edges(100.0, 50.0);
+        assert_eq!(inside.position(), Vector2::new(104.0, 0.0));
+    }
+
+    #[test]
+    fn bounces_with_restitution() {
+        let mut mover = at(98.0, 2.0, Vector2::new(4.0, -2.0), Edges::Bounce(0.5));
+        mover.check_edges(100.0, 50.0);
+        assert_eq!(mover.position(), Vector2::new(95.0, 5.0));
+        assert_eq!(mover.velocity(), Vector2::new(-2.0, 1.0));
+    }
+
+    #[test]
+    fn clamps_and_stops_at_the_edges() {
+        let mut mover = at(120.0, 20.0, Vector2::new(3.0, 1.0), Edges::Clamp);
+        mover.check_edges(100.0, 50.0);
+        assert_eq!(mover.position(), Vector2::new(95.0, 20.0));
+        assert_eq!(mover.velocity(), Vector2::new(0.0, 1.0));
+
+        // Smaller than the mover: kept against the first edge.
+        let mut cramped = at(8.0, 20.0, Vector2::zero(), Edges::Clamp);
+        cramped.check_edges(6.0, 50.0);
+        assert_eq!(cramped.position().x, 5.0);
+    }
+
+    #[test]
+    fn moves_freely_without_edges() {
+        let mut mover = at(-50.0, 500.0, Vector2::new(1.0, 1.0), Edges::None);
+        mover.check_edges(100.0, 50.0);
+        assert_eq!(mover.position(), Vector2::new(-50.0, 500.0));
+    }
+
+    #[test]
+    fn integrates_and_clears_forces() {
+        let mut mover = Mover::new(0.0, 0.0, 2.0).with_velocity(Vector2::new(1.0, 0.0));
+        mover.apply_force(Vector2::new(0.0, 4.0));
+        mover.accelerate(Vector2::new(1.0, 0.0));
+        assert_eq!(mover.acceleration(), Vector2::new(1.0, 2.0));
+        mover.integrate();
+        assert_eq!(mover.velocity(), Vector2::new(2.0, 2.0));
+        assert_eq!(mover.position(), Vector2::new(2.0, 2.0));
+        assert_eq!(mover.acceleration(), Vector2::zero());
+        mover.integrate();
+        assert_eq!(mover.position(), Vector2::new(4.0, 4.0));
+    }
+
+    #[test]
+    fn limits_speed() {
+        let mut mover = Mover::new(0.0, 0.0, 1.0).with_max_speed(5.0);
+        mover.accelerate(Vector2::new(30.0, 40.0));
+        mover.integrate();
+        assert_close(mover.velocity(), Vector2::new(3.0, 4.0));
+        assert_close(mover.position(), Vector2::new(3.0, 4.0));
+    }
+
+    #[test]
+    fn limits_angular_speed() {
+        let mut mover = Mover::new(0.0, 0.0, 1.0).with_max_angular_speed(0.5);
+        mover.apply_angular_acceleration(2.0);
+        mover.integrate();
+        assert_eq!(mover.angular_velocity(), 0.5);
+        assert_eq!(mover.angle(), 0.5);
+        mover.apply_angular_acceleration(-3.0);
+        mover.integrate();
+        assert_eq!(mover.angular_velocity(), -0.5);
+        assert_eq!(mover.angle(), 0.0);
+        // The acceleration only lasts for one step.
+        mover.integrate();
+        assert_eq!(mover.angular_velocity(), -0.5);
+    }
+}
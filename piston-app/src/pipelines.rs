@@ -0,0 +1,49 @@
+//! Stock pipelines drawing textured, coloured `Vertex` data.
+//!
+//! `sprites` expects positions in normalized device coordinates, as produced by a
+//! `SpriteBatch` transformed with `math::abs_transform()`. `world_sprites` maps
+//! positions through its `transform` uniform, `[x offset, y offset, x scale,
+//! y scale]`, so vertices can stay in world units.
+
+use super::*;
+
+gfx_defines! {
+    pipeline sprites {
+        vbuf: VertexBuffer<Vertex> = (),
+        sampler: TextureSampler<[f32; 4]> = "sampler",
+        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
+                                                 gfx::state::ColorMask::all(),
+                                                 gfx::preset::blend::ALPHA),
+    }
+
+    pipeline world_sprites {
+        vbuf: VertexBuffer<Vertex> = (),
+        sampler: TextureSampler<[f32; 4]> = "sampler",
+        transform: Global<[f32; 4]> = "transform",
+        out: BlendTarget<gfx::format::Srgba8> = ("o_color",
+                                                 gfx::state::ColorMask::all(),
+                                                 gfx::preset::blend::ALPHA),
+    }
+}
+
+pub const SPRITES_VERTEX_GLSL_150_CORE: &[u8] = include_bytes!("sprites_150_core.glslv");
+pub const WORLD_SPRITES_VERTEX_GLSL_150_CORE: &[u8] =
+    include_bytes!("world_sprites_150_core.glslv");
+pub const SPRITES_FRAGMENT_GLSL_150_CORE: &[u8] =
+    include_bytes!("sprites_150_core.glslf");
+
+/// `sprites` pipeline blending with `blend_mode`.
+pub fn sprites_init(blend_mode: BlendMode) -> sprites::Init<'static> {
+    sprites::Init {
+        out: ("o_color", gfx::state::ColorMask::all(), blend_mode.blend()),
+        ..sprites::new()
+    }
+}
+
+/// `world_sprites` pipeline blending with `blend_mode`.
+pub fn world_sprites_init(blend_mode: BlendMode) -> world_sprites::Init<'static> {
+    world_sprites::Init {
+        out: ("o_color", gfx::state::ColorMask::all(), blend_mode.blend()),
+        ..world_sprites::new()
+    }
+}
@@ -0,0 +1,402 @@
+//! Full-screen post-processing passes.
+
+use super::*;
+
+gfx_defines! {
+    pipeline post {
+        vbuf: VertexBuffer<Vertex> = (),
+        source: TextureSampler<[f32; 4]> = "source",
+        scene: TextureSampler<[f32; 4]> = "scene",
+        resolution: Global<[f32; 2]> = "resolution",
+        params: Global<[f32; 4]> = "params",
+        time: Global<f32> = "time",
+        out: RenderTarget<gfx::format::Srgba8> = "o_color",
+    }
+}
+
+const VERTEX_SHADER: &[u8] = include_bytes!("post/post_150_core.glslv");
+const COPY_SHADER: &[u8] = include_bytes!("post/copy_150_core.glslf");
+const BLUR_SHADER: &[u8] = include_bytes!("post/blur_150_core.glslf");
+const THRESHOLD_SHADER: &[u8] = include_bytes!("post/threshold_150_core.glslf");
+const COMPOSITE_SHADER: &[u8] = include_bytes!("post/composite_150_core.glslf");
+const VIGNETTE_SHADER: &[u8] = include_bytes!("post/vignette_150_core.glslf");
+const TONE_MAP_SHADER: &[u8] = include_bytes!("post/tone_map_150_core.glslf");
+const COLOR_GRADE_SHADER: &[u8] = include_bytes!("post/color_grade_150_core.glslf");
+
+/// Full-screen pass applied by `PostProcessing`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostPass {
+    /// Separable Gaussian blur, `radius` in pixels.
+    Blur { radius: f32 },
+    /// Blurs what is brighter than the luminance `threshold` and adds it back,
+    /// times `intensity`.
+    Bloom {
+        threshold: f32,
+        radius: f32,
+        intensity: f32,
+    },
+    /// Darkens the frame from `radius`, 0 at the center and 1 at the corners,
+    /// down to `1 - strength` at the corners.
+    Vignette { radius: f32, strength: f32 },
+    /// Scales colours by `exposure`, then maps them through a filmic curve.
+    ToneMap { exposure: f32 },
+    /// Adds `brightness`, then scales the contrast and the saturation.
+    ColorGrade {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+    },
+    /// Fragment shader using the same inputs as the stock passes: the
+    /// `sampler2D`s `source`, the previous pass's output, and `scene`, the frame
+    /// as drawn, the `vec2 resolution` in pixels, the `vec4 params` given here,
+    /// the `float time`, in frames, and the `vec2 v_uv` texture coordinates.
+    Custom {
+        fragment_shader: &'static [u8],
+        params: [f32; 4],
+    },
+}
+
+/// Ordered chain of full-screen passes: the frame is drawn into `scene()`
+/// between `begin()` and `end()`, which runs the enabled passes and draws the
+/// result into the window.
+///
+/// ```ignore
+/// let post = self.post.as_mut().unwrap();
+/// renderer.bind_target(Some(post.begin(window, state)?));
+/// // Draw the frame.
+/// renderer.bind_target(None);
+/// post.end(window)?;
+/// ```
+#[derive(Debug)]
+pub struct PostProcessing {
+    passes: Vec<(PostPass, bool)>,
+    /// The scene, then three intermediate targets, all the size of the viewport.
+    targets: Vec<PistonRenderTarget>,
+    pipelines: Vec<(&'static [u8], PistonPipeline<post::Meta>)>,
+    quad: Option<(gfx::handle::Buffer<Resources, Vertex>, Slice<Resources>)>,
+    time: f32,
+}
+
+impl PostProcessing {
+    pub fn new() -> Self {
+        PostProcessing {
+            passes: vec![],
+            targets: vec![],
+            pipelines: vec![],
+            quad: None,
+            time: 0.0,
+        }
+    }
+
+    /// Appends an enabled `pass` to the chain.
+    pub fn pass(mut self, pass: PostPass) -> Self {
+        self.passes.push((pass, true));
+        self
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&PostPass> {
+        self.passes.get(index).map(|(pass, _)| pass)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut PostPass> {
+        self.passes.get_mut(index).map(|(pass, _)| pass)
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.passes[index].1
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.passes[index].1 = enabled;
+    }
+
+    /// Enables or disables the pass at `index`, and returns whether it is now
+    /// enabled.
+    pub fn toggle(&mut self, index: usize) -> bool {
+        let enabled = !self.is_enabled(index);
+        self.set_enabled(index, enabled);
+        enabled
+    }
+
+    /// Moves the pass at `from` to `to`, shifting the passes in between.
+    pub fn move_pass(&mut self, from: usize, to: usize) {
+        let pass = self.passes.remove(from);
+        self.passes.insert(to, pass);
+    }
+
+    /// Target the frame is drawn into, as returned by `begin()`.
+    pub fn scene(&self) -> &PistonRenderTarget {
+        self.targets
+            .first()
+            .expect("PostProcessing::begin() not called")
+    }
+
+    /// Starts a frame, (re)creating the targets to match the viewport, and
+    /// returns the target to draw it into. Its contents persist from the
+    /// previous frame.
+    pub fn begin(
+        &mut self,
+        window: &mut PistonAppWindow,
+        state: &PistonAppState,
+    ) -> Result<&PistonRenderTarget, Box<dyn Error>> {
+        if self.targets.is_empty() || !self.targets[0].matches_viewport(state) {
+            self.targets = (0..4)
+                .map(|_| PistonRenderTarget::from_viewport(window, state))
+                .collect::<Result<_, _>>()?;
+        }
+        if self.quad.is_none() {
+            self.quad = Some(Self::create_quad(window));
+        }
+        self.time = state.frame_count() as f32;
+        Ok(&self.targets[0])
+    }
+
+    /// Runs the enabled passes over the scene, then draws the result into the
+    /// window.
+    pub fn end(&mut self, window: &mut PistonAppWindow) -> Result<(), Box<dyn Error>> {
+        let passes: Vec<PostPass> = self
+            .passes
+            .iter()
+            .filter(|&&(_, enabled)| enabled)
+            .map(|&(pass, _)| pass)
+            .collect();
+        for pass in &passes {
+            for shader in Self::shaders(pass) {
+                self.load_pipeline(window, shader)?;
+            }
+        }
+        self.load_pipeline(window, COPY_SHADER)?;
+        let mut current = 0;
+        for pass in &passes {
+            current = self.apply(window, pass, current);
+        }
+        let output_color = window.output_color.clone();
+        self.draw(window, COPY_SHADER, current, 0, output_color, [0.0; 4]);
+        window.encoder.flush(&mut window.device);
+        Ok(())
+    }
+
+    /// Applies `pass` to the target `current`, and returns the target holding
+    /// the result.
+    fn apply(
+        &self,
+        window: &mut PistonAppWindow,
+        pass: &PostPass,
+        current: usize,
+    ) -> usize {
+        let (a, b) = Self::others(current);
+        match *pass {
+            PostPass::Blur { radius } => {
+                self.blur(window, current, a, b, radius);
+                b
+            }
+            PostPass::Bloom {
+                threshold,
+                radius,
+                intensity,
+            } => {
+                self.draw_to(
+                    window,
+                    THRESHOLD_SHADER,
+                    current,
+                    a,
+                    [threshold, 0.0, 0.0, 0.0],
+                );
+                self.blur(window, a, b, a, radius);
+                let output_color = self.targets[b].output_color().clone();
+                self.draw(
+                    window,
+                    COMPOSITE_SHADER,
+                    a,
+                    current,
+                    output_color,
+                    [intensity, 0.0, 0.0, 0.0],
+                );
+                b
+            }
+            PostPass::Vignette { radius, strength } => {
+                self.draw_to(
+                    window,
+                    VIGNETTE_SHADER,
+                    current,
+                    a,
+                    [radius, strength, 0.0, 0.0],
+                );
+                a
+            }
+            PostPass::ToneMap { exposure } => {
+                self.draw_to(
+                    window,
+                    TONE_MAP_SHADER,
+                    current,
+                    a,
+                    [exposure, 0.0, 0.0, 0.0],
+                );
+                a
+            }
+            PostPass::ColorGrade {
+                brightness,
+                contrast,
+                saturation,
+            } => {
+                self.draw_to(
+                    window,
+                    COLOR_GRADE_SHADER,
+                    current,
+                    a,
+                    [brightness, contrast, saturation, 0.0],
+                );
+                a
+            }
+            PostPass::Custom {
+                fragment_shader,
+                params,
+            } => {
+                self.draw_to(window, fragment_shader, current, a, params);
+                a
+            }
+        }
+    }
+
+    /// Blurs `source` horizontally into `temporary`, then vertically into
+    /// `destination`.
+    fn blur(
+        &self,
+        window: &mut PistonAppWindow,
+        source: usize,
+        temporary: usize,
+        destination: usize,
+        radius: f32,
+    ) {
+        for &(from, to, params) in &[
+            (source, temporary, [radius, 1.0, 0.0, 0.0]),
+            (temporary, destination, [radius, 0.0, 1.0, 0.0]),
+        ] {
+            self.draw_to(window, BLUR_SHADER, from, to, params);
+        }
+    }
+
+    fn draw_to(
+        &self,
+        window: &mut PistonAppWindow,
+        shader: &'static [u8],
+        source: usize,
+        destination: usize,
+        params: [f32; 4],
+    ) {
+        let output_color = self.targets[destination].output_color().clone();
+        self.draw(window, shader, source, 0, output_color, params);
+    }
+
+    fn draw(
+        &self,
+        window: &mut PistonAppWindow,
+        shader: &'static [u8],
+        source: usize,
+        scene: usize,
+        output_color: PistonRenderTargetView,
+        params: [f32; 4],
+    ) {
+        let pipeline = &self
+            .pipelines
+            .iter()
+            .find(|&&(pipeline_shader, _)| pipeline_shader == shader)
+            .unwrap()
+            .1;
+        let (vbuf, slice) = self.quad.as_ref().unwrap();
+        let [width, height] = self.targets[source].viewport().draw_size;
+        let data = post::Data {
+            vbuf: vbuf.clone(),
+            source: self.targets[source].texture_view_sampler(),
+            scene: self.targets[scene].texture_view_sampler(),
+            resolution: [width as f32, height as f32],
+            params: params,
+            time: self.time,
+            out: output_color,
+        };
+        window.encoder.draw(slice, pipeline, &data);
+    }
+
+    fn load_pipeline(
+        &mut self,
+        window: &mut PistonAppWindow,
+        shader: &'static [u8],
+    ) -> Result<(), Box<dyn Error>> {
+        if !self.pipelines.iter().any(|&(loaded, _)| loaded == shader) {
+            let (pipeline, _) = PistonPipelineBuilder::new()
+                .vertex_shader(VERTEX_SHADER)
+                .fragment_shader(shader)
+                .build(window, post::new())?;
+            self.pipelines.push((shader, pipeline));
+        }
+        Ok(())
+    }
+
+    fn shaders(pass: &PostPass) -> Vec<&'static [u8]> {
+        match *pass {
+            PostPass::Blur { .. } => vec![BLUR_SHADER],
+            PostPass::Bloom { .. } => {
+                vec![THRESHOLD_SHADER, BLUR_SHADER, COMPOSITE_SHADER]
+            }
+            PostPass::Vignette { .. } => vec![VIGNETTE_SHADER],
+            PostPass::ToneMap { .. } => vec![TONE_MAP_SHADER],
+            PostPass::ColorGrade { .. } => vec![COLOR_GRADE_SHADER],
+            PostPass::Custom {
+                fragment_shader, ..
+            } => vec![fragment_shader],
+        }
+    }
+
+    /// Two intermediate targets other than `current`, so the scene is kept.
+    fn others(current: usize) -> (usize, usize) {
+        let mut others = (1..4).filter(|&index| index != current);
+        (others.next().unwrap(), others.next().unwrap())
+    }
+
+    fn create_quad(
+        window: &mut PistonAppWindow,
+    ) -> (gfx::handle::Buffer<Resources, Vertex>, Slice<Resources>) {
+        const WHITE: Color = [1.0; 4];
+        let vertices = [
+            Vertex {
+                pos: [1.0, -1.0],
+                uv: [1.0, 0.0],
+                color: WHITE,
+            },
+            Vertex {
+                pos: [-1.0, -1.0],
+                uv: [0.0, 0.0],
+                color: WHITE,
+            },
+            Vertex {
+                pos: [-1.0, 1.0],
+                uv: [0.0, 1.0],
+                color: WHITE,
+            },
+            Vertex {
+                pos: [1.0, 1.0],
+                uv: [1.0, 1.0],
+                color: WHITE,
+            },
+        ];
+        window
+            .factory
+            .create_vertex_buffer_with_slice(&vertices, &[0u16, 1, 2, 2, 3, 0][..])
+    }
+}
+
+impl Default for PostProcessing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
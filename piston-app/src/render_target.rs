@@ -0,0 +1,150 @@
+//! Offscreen render targets.
+
+use super::*;
+
+/// Offscreen colour buffer, drawn into like the window then sampled as a
+/// texture: bind it with `PistonRenderer::bind_target()` or
+/// `PistonPipelineBuilder::render_target()`, or draw into it with `draw_2d()`.
+///
+/// Its contents persist until cleared, so it can accumulate trails or feed the
+/// previous frame back into the next one.
+///
+/// Rows are stored bottom up, like the window: `uv_extents()` and `draw()` flip
+/// them back.
+#[derive(Debug, Clone)]
+pub struct PistonRenderTarget {
+    draw_size: [u32; 2],
+    size: [u32; 2],
+    texture: G2dTexture,
+    output_color: PistonRenderTargetView,
+    output_stencil: gfx::handle::DepthStencilView<Resources, gfx::format::DepthStencil>,
+}
+
+impl PistonRenderTarget {
+    /// Render target of `width` by `height` pixels.
+    pub fn new(
+        window: &mut PistonAppWindow,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, Box<dyn Error>> {
+        Self::with_draw_size(window, [width, height], [width, height])
+    }
+
+    /// Render target the size of the window's viewport, in pixels and in
+    /// points.
+    pub fn from_viewport(
+        window: &mut PistonAppWindow,
+        state: &PistonAppState,
+    ) -> Result<Self, Box<dyn Error>> {
+        let viewport = state.viewport();
+        Self::with_draw_size(window, viewport.draw_size, viewport.window_size)
+    }
+
+    fn with_draw_size(
+        window: &mut PistonAppWindow,
+        draw_size: [u32; 2],
+        size: [u32; 2],
+    ) -> Result<Self, Box<dyn Error>> {
+        let factory = &mut window.factory;
+        let (w, h) = (
+            draw_size[0] as gfx::texture::Size,
+            draw_size[1] as gfx::texture::Size,
+        );
+        let (surface, view, output_color) =
+            factory.create_render_target::<gfx::format::Srgba8>(w, h)?;
+        let output_stencil = factory.create_depth_stencil_view_only(w, h)?;
+        Ok(PistonRenderTarget {
+            draw_size: draw_size,
+            size: size,
+            texture: Texture {
+                surface: surface,
+                sampler: factory.create_sampler_linear(),
+                view: view,
+            },
+            output_color: output_color,
+            output_stencil: output_stencil,
+        })
+    }
+
+    /// Width in points, the unit of the drawing coordinates.
+    #[inline]
+    pub fn width(&self) -> Scalar {
+        self.size[0] as Scalar
+    }
+
+    /// Height in points, the unit of the drawing coordinates.
+    #[inline]
+    pub fn height(&self) -> Scalar {
+        self.size[1] as Scalar
+    }
+
+    /// Whether the target is the size of the window's viewport, to recreate it
+    /// after a resize.
+    pub fn matches_viewport(&self, state: &PistonAppState) -> bool {
+        let viewport = state.viewport();
+        viewport.draw_size == self.draw_size && viewport.window_size == self.size
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        Viewport {
+            rect: [0, 0, self.draw_size[0] as i32, self.draw_size[1] as i32],
+            draw_size: self.draw_size,
+            window_size: self.size,
+        }
+    }
+
+    #[inline]
+    pub fn texture(&self) -> &G2dTexture {
+        &self.texture
+    }
+
+    #[inline]
+    pub fn texture_view_sampler(&self) -> PistonPipelineSampler {
+        (self.texture.view.clone(), self.texture.sampler.clone())
+    }
+
+    #[inline]
+    pub fn output_color(&self) -> &PistonRenderTargetView {
+        &self.output_color
+    }
+
+    /// UV rectangle `[u, v, width, height]` of the whole target, upright.
+    #[inline]
+    pub fn uv_extents(&self) -> [f32; 4] {
+        [0.0, 1.0, 1.0, -1.0]
+    }
+
+    pub fn clear(&self, window: &mut PistonAppWindow, color: Color) {
+        window.encoder.clear(&self.output_color, color);
+    }
+
+    /// Draws into the target with the `graphics` API, like
+    /// `PistonWindow::draw_2d()` does into the window.
+    pub fn draw_2d<F, U>(&self, window: &mut PistonAppWindow, f: F) -> U
+    where
+        F: FnOnce(Context, &mut G2d) -> U,
+    {
+        let result = window.g2d.draw(
+            &mut window.encoder,
+            &self.output_color,
+            &self.output_stencil,
+            self.viewport(),
+            f,
+        );
+        window.encoder.flush(&mut window.device);
+        result
+    }
+
+    /// Draws the contents of the target, upright, with their top left corner at
+    /// the origin of `transform`.
+    pub fn draw(&self, transform: Matrix2d, gfx: &mut G2d) {
+        Image::new()
+            .rect([0.0, 0.0, self.width(), self.height()])
+            .draw(
+                &self.texture,
+                &DrawState::default(),
+                transform.trans(0.0, self.height()).flip_v(),
+                gfx,
+            );
+    }
+}
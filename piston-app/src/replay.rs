@@ -0,0 +1,192 @@
+//! Input recording and deterministic replay.
+
+use super::*;
+
+use std::io::{BufWriter, Write};
+
+/// Input seen by the application during one frame, along with the number of
+/// fixed update steps run before drawing it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct InputFrame {
+    updates: usize,
+    interpolation: Scalar,
+    mouse: [Scalar; 2],
+    pressed_keys: Vec<Key>,
+    hit_keys: Vec<Key>,
+    pressed_mouse_buttons: Vec<MouseButton>,
+    clicked_mouse_buttons: Vec<MouseButton>,
+}
+
+impl InputFrame {
+    fn capture(state: &PistonAppState, updates: usize) -> Self {
+        InputFrame {
+            updates: updates,
+            interpolation: state.interpolation,
+            mouse: [state.mouse_x, state.mouse_y],
+            pressed_keys: state.pressed_keys.iter().cloned().collect(),
+            hit_keys: state.hit_keys.iter().cloned().collect(),
+            pressed_mouse_buttons: state.pressed_mouse_buttons.iter().cloned().collect(),
+            clicked_mouse_buttons: state.clicked_mouse_buttons.iter().cloned().collect(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn updates(&self) -> usize {
+        self.updates
+    }
+
+    pub(crate) fn apply(&self, state: &mut PistonAppState) {
+        state.interpolation = self.interpolation;
+        state.mouse_x = self.mouse[0];
+        state.mouse_y = self.mouse[1];
+        state.pressed_keys = self.pressed_keys.iter().cloned().collect();
+        state.hit_keys = self.hit_keys.iter().cloned().collect();
+        state.pressed_mouse_buttons =
+            self.pressed_mouse_buttons.iter().cloned().collect();
+        state.clicked_mouse_buttons =
+            self.clicked_mouse_buttons.iter().cloned().collect();
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordingHeader {
+    seed: u64,
+}
+
+/// Writes a recording as JSON lines: a header holding the seed, then one
+/// `InputFrame` per frame.
+pub(crate) struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub(crate) fn create<P: AsRef<Path>>(
+        path: P,
+        seed: u64,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &RecordingHeader { seed: seed })?;
+        writeln!(writer)?;
+        Ok(InputRecorder { writer: writer })
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        state: &PistonAppState,
+        updates: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.writer, &InputFrame::capture(state, updates))?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+}
+
+/// Recording read back by `InputRecorder::create()`, replayed frame by frame.
+#[derive(Debug, Clone)]
+pub(crate) struct InputReplay {
+    seed: u64,
+    frames: std::vec::IntoIter<InputFrame>,
+}
+
+impl InputReplay {
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    fn read<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut lines = reader.lines();
+        let header: RecordingHeader = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => return Err("empty input recording".into()),
+        };
+        let mut frames = vec![];
+        for line in lines {
+            frames.push(serde_json::from_str(&line?)?);
+        }
+        Ok(InputReplay {
+            seed: header.seed,
+            frames: frames.into_iter(),
+        })
+    }
+
+    #[inline]
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline]
+    pub(crate) fn next_frame(&mut self) -> Option<InputFrame> {
+        self.frames.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECORDING: &str = concat!(
+        "{\"seed\":42}\n",
+        "{\"updates\":2,\"interpolation\":0.5,\"mouse\":[10.0,20.0],",
+        "\"pressed_keys\":[\"Space\"],\"hit_keys\":[\"Space\"],",
+        "\"pressed_mouse_buttons\":[],\"clicked_mouse_buttons\":[\"Left\"]}\n",
+        "{\"updates\":0,\"interpolation\":0.0,\"mouse\":[11.0,20.0],",
+        "\"pressed_keys\":[],\"hit_keys\":[],",
+        "\"pressed_mouse_buttons\":[\"Left\"],\"clicked_mouse_buttons\":[]}\n",
+    );
+
+    #[test]
+    fn reads_header_and_frames() {
+        let mut replay = InputReplay::read(RECORDING.as_bytes()).unwrap();
+        assert_eq!(replay.seed(), 42);
+        assert_eq!(replay.next_frame().unwrap().updates(), 2);
+        assert_eq!(replay.next_frame().unwrap().updates(), 0);
+        assert!(replay.next_frame().is_none());
+    }
+
+    #[test]
+    fn applies_frames_to_state() {
+        let mut replay = InputReplay::read(RECORDING.as_bytes()).unwrap();
+        let mut state = PistonAppState::new(replay.seed());
+        replay.next_frame().unwrap().apply(&mut state);
+        assert_eq!(state.interpolation, 0.5);
+        assert_eq!([state.mouse_x, state.mouse_y], [10.0, 20.0]);
+        assert!(state.key_pressed(Key::Space));
+        assert!(state.key_hit(Key::Space));
+        assert!(state.mouse_button_clicked(MouseButton::Left));
+        assert!(!state.mouse_button_pressed(MouseButton::Left));
+
+        replay.next_frame().unwrap().apply(&mut state);
+        assert!(!state.key_pressed(Key::Space));
+        assert!(!state.key_hit(Key::Space));
+        assert!(!state.mouse_button_clicked(MouseButton::Left));
+        assert!(state.mouse_button_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn round_trips_captured_frames() {
+        let mut state = PistonAppState::new(7);
+        state.mouse_x = 3.0;
+        state.hit_keys.insert(Key::R);
+        let line = serde_json::to_string(&InputFrame::capture(&state, 1)).unwrap();
+        let recording = format!("{{\"seed\":7}}\n{}\n", line);
+
+        let mut replay = InputReplay::read(recording.as_bytes()).unwrap();
+        let mut replayed = PistonAppState::new(replay.seed());
+        let frame = replay.next_frame().unwrap();
+        frame.apply(&mut replayed);
+        assert_eq!(frame.updates(), 1);
+        assert_eq!(replayed.mouse_x, 3.0);
+        assert!(replayed.key_hit(Key::R));
+    }
+
+    #[test]
+    fn rejects_empty_recording() {
+        assert!(InputReplay::read("".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        assert!(InputReplay::read("{\"seed\":1}\nnot json\n".as_bytes()).is_err());
+        assert!(InputReplay::read("{\"seed\":1}\n{\"updates\":1}\n".as_bytes()).is_err());
+    }
+}
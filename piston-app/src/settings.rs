@@ -0,0 +1,126 @@
+//! Window settings, overridable from the command line.
+
+use super::*;
+
+/// Settings of the window created by `PistonApp::run()`, as returned by
+/// `PistonApp::settings()`.
+///
+/// The `--size <width>x<height>`, `--resizable <bool>`, `--vsync <bool>`,
+/// `--samples <count>` and `--fps <frames>` command line options take
+/// precedence over these.
+#[derive(Debug, Clone)]
+pub struct PistonAppSettings {
+    size: [u32; 2],
+    resizable: bool,
+    vsync: bool,
+    samples: u8,
+    exit_on_esc: bool,
+    clear_color: Option<Color>,
+    max_fps: u64,
+}
+
+impl PistonAppSettings {
+    pub fn new() -> Self {
+        PistonAppSettings {
+            size: [640, 480],
+            resizable: false,
+            vsync: true,
+            samples: 0,
+            exit_on_esc: true,
+            clear_color: None,
+            max_fps: 60,
+        }
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = [width, height];
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.vsync = vsync;
+        self
+    }
+
+    /// Number of MSAA samples, or 0 to disable multisampling.
+    pub fn samples(mut self, samples: u8) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn exit_on_esc(mut self, exit_on_esc: bool) -> Self {
+        self.exit_on_esc = exit_on_esc;
+        self
+    }
+
+    /// Colour the window is cleared with before `PistonApp::setup()` is called.
+    pub fn clear_color(mut self, color: Color) -> Self {
+        self.clear_color = Some(color);
+        self
+    }
+
+    pub fn max_fps(mut self, max_fps: u64) -> Self {
+        self.max_fps = max_fps;
+        self
+    }
+
+    #[inline]
+    pub(crate) fn window_size(&self) -> [u32; 2] {
+        self.size
+    }
+
+    #[inline]
+    pub(crate) fn initial_clear_color(&self) -> Option<Color> {
+        self.clear_color
+    }
+
+    pub(crate) fn with_cli_overrides(mut self) -> Self {
+        if let Some(size) = cli_arg("size") {
+            let mut dimensions = size.split('x').map(|dimension| {
+                dimension.parse().expect("--size expects <width>x<height>")
+            });
+            match (dimensions.next(), dimensions.next(), dimensions.next()) {
+                (Some(width), Some(height), None) => self.size = [width, height],
+                _ => panic!("--size expects <width>x<height>"),
+            }
+        }
+        if let Some(resizable) = cli_arg("resizable") {
+            self.resizable = resizable
+                .parse()
+                .expect("--resizable expects true or false");
+        }
+        if let Some(vsync) = cli_arg("vsync") {
+            self.vsync = vsync.parse().expect("--vsync expects true or false");
+        }
+        if let Some(samples) = cli_arg("samples") {
+            self.samples = samples.parse().expect("--samples expects a sample count");
+        }
+        if let Some(max_fps) = cli_arg("fps") {
+            self.max_fps = max_fps.parse().expect("--fps expects a frame rate");
+        }
+        self
+    }
+
+    pub(crate) fn build_window(&self, title: String) -> PistonAppWindow {
+        let mut window: PistonAppWindow = WindowSettings::new(title, self.size)
+            .exit_on_esc(self.exit_on_esc)
+            .resizable(self.resizable)
+            .vsync(self.vsync)
+            .samples(self.samples)
+            .build()
+            .unwrap();
+        window.set_max_fps(self.max_fps);
+        window
+    }
+}
+
+impl Default for PistonAppSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
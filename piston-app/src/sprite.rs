@@ -0,0 +1,360 @@
+//! Batched sprites, quads and triangles, drawn in as few calls as possible.
+
+use super::*;
+
+gfx_defines! {
+    vertex Vertex {
+        pos: [f32; 2] = "pos",
+        uv: [f32; 2] = "uv",
+        color: [f32; 4] = "color",
+    }
+}
+
+/// Collects textured, coloured quads and triangles into vertex and index
+/// buffers, then draws them all from a `PistonStreamBuffer` uploaded once per
+/// `draw()`.
+///
+/// Indices are relative to the current draw call: when a draw call reaches
+/// `max_vertices()`, a new one is started, so the `u32` indices never overflow.
+#[derive(Debug)]
+pub struct SpriteBatch {
+    transform: Matrix2d,
+    flip_v: bool,
+    max_vertices: usize,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    /// Vertex and index offsets of the draw calls after the first one.
+    splits: Vec<(usize, usize)>,
+    base_vertex: usize,
+    stream: PistonStreamBuffer<Vertex>,
+}
+
+impl SpriteBatch {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Empty batch with room for `quads` quads.
+    pub fn with_capacity(quads: usize) -> Self {
+        SpriteBatch {
+            transform: math::identity(),
+            flip_v: false,
+            max_vertices: u32::MAX as usize,
+            vertices: Vec::with_capacity(4 * quads),
+            indices: Vec::with_capacity(6 * quads),
+            splits: vec![],
+            base_vertex: 0,
+            stream: PistonStreamBuffer::new(),
+        }
+    }
+
+    /// Transform applied to every vertex, after the sprite's own. Identity by
+    /// default: use `math::abs_transform(state.width(), state.height())` to draw
+    /// in window coordinates with a pipeline that expects normalized ones.
+    pub fn transform(mut self, transform: Matrix2d) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Flips textures vertically, for world spaces where y points up.
+    pub fn flip_v(mut self, flip_v: bool) -> Self {
+        self.flip_v = flip_v;
+        self
+    }
+
+    /// Vertices per draw call, `u32::MAX` by default.
+    pub fn max_vertices(mut self, max_vertices: usize) -> Self {
+        self.max_vertices = max_vertices.clamp(4, u32::MAX as usize);
+        self
+    }
+
+    #[inline]
+    pub fn set_transform(&mut self, transform: Matrix2d) {
+        self.transform = transform;
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// GPU buffers the batch is drawn from.
+    #[inline]
+    pub fn stream(&self) -> &PistonStreamBuffer<Vertex> {
+        &self.stream
+    }
+
+    /// Number of draw calls the next `draw()` will make.
+    pub fn draw_count(&self) -> usize {
+        if self.is_empty() {
+            0
+        } else {
+            self.splits.len() + 1
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.splits.clear();
+        self.base_vertex = 0;
+    }
+
+    /// Atlas region `index`, at its size in pixels, centered on the origin of
+    /// `transform`.
+    pub fn sprite(
+        &mut self,
+        texture_atlas: &TextureAtlas,
+        index: usize,
+        transform: Matrix2d,
+        color: Color,
+    ) {
+        let (w, h) = texture_atlas.texture_offsets(index);
+        let (u, v, tw, th) = texture_atlas.texture_uv_extents(index);
+        self.rect(transform, [-w, -h, 2.0 * w, 2.0 * h], [u, v, tw, th], color);
+    }
+
+    /// Rectangle `[x, y, width, height]`, transformed by `transform` and textured
+    /// with the UV rectangle `[u, v, width, height]`.
+    pub fn rect(
+        &mut self,
+        transform: Matrix2d,
+        rect: [Scalar; 4],
+        uv: [f32; 4],
+        color: Color,
+    ) {
+        let [x, y, w, h] = rect;
+        let transform = math::multiply(self.transform, transform);
+        let corners = [[x + w, y + h], [x, y + h], [x, y], [x + w, y]];
+        let uvs = self.uv_corners(uv);
+        let start = self.reserve(4);
+        self.vertices.extend(
+            corners
+                .iter()
+                .zip(&uvs)
+                .map(|(&corner, &uv)| Self::vertex(transform, corner, uv, color)),
+        );
+        self.indices
+            .extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+    }
+
+    /// Arbitrary quad, its corners in drawing order.
+    pub fn quad(&mut self, positions: [Vec2d; 4], uvs: [[f32; 2]; 4], color: Color) {
+        let start = self.reserve(4);
+        let transform = self.transform;
+        self.vertices.extend(
+            positions
+                .iter()
+                .zip(&uvs)
+                .map(|(&position, &uv)| Self::vertex(transform, position, uv, color)),
+        );
+        self.indices
+            .extend(&[start, start + 1, start + 2, start + 2, start + 3, start]);
+    }
+
+    /// Segment from `from` to `to`, extending `radius` on each side, textured with
+    /// the UV rectangle `[u, v, width, height]` along its length.
+    pub fn line(
+        &mut self,
+        from: Vec2d,
+        to: Vec2d,
+        radius: Scalar,
+        uv: [f32; 4],
+        color: Color,
+    ) {
+        let direction = vec2_sub(to, from);
+        let length = vec2_len(direction);
+        if length <= 0.0 {
+            return;
+        }
+        let delta = vec2_scale([-direction[1], direction[0]], radius / length);
+        let positions = [
+            vec2_add(to, delta),
+            vec2_add(from, delta),
+            vec2_sub(from, delta),
+            vec2_sub(to, delta),
+        ];
+        let uvs = self.uv_corners(uv);
+        self.quad(positions, uvs, color);
+    }
+
+    pub fn triangle(&mut self, positions: [Vec2d; 3], uvs: [[f32; 2]; 3], color: Color) {
+        let start = self.reserve(3);
+        let transform = self.transform;
+        self.vertices.extend(
+            positions
+                .iter()
+                .zip(&uvs)
+                .map(|(&position, &uv)| Self::vertex(transform, position, uv, color)),
+        );
+        self.indices.extend(&[start, start + 1, start + 2]);
+    }
+
+    /// Uploads the batch and queues its draw calls with `pipeline`, to be
+    /// submitted by `PistonRenderer::flush()`. `f` builds the pipeline data of
+    /// each call. The batch is kept until `clear()` is called, usually before
+    /// building the next frame's.
+    pub fn draw<D, F>(
+        &mut self,
+        window: &mut PistonAppWindow,
+        renderer: &PistonRenderer,
+        pipeline: &PistonPipeline<D::Meta>,
+        mut f: F,
+    ) where
+        D: pso::PipelineData<Resources>,
+        F: FnMut(gfx::handle::Buffer<Resources, Vertex>, PistonRenderTargetView) -> D,
+    {
+        if self.is_empty() {
+            return;
+        }
+        self.stream
+            .upload(window, &self.vertices, &self.indices)
+            .expect("cannot upload sprite batch");
+        let mut start = (0, 0);
+        let end = (self.vertices.len(), self.indices.len());
+        for &split in self.splits.iter().chain(Some(&end)) {
+            if split.1 > start.1 {
+                let slice = self.stream.slice(start.1, split.1, start.0);
+                renderer.draw_stream(window, pipeline, &self.stream, &slice, &mut f);
+            }
+            start = split;
+        }
+    }
+
+    /// Draws the batch then submits everything queued so far.
+    pub fn flush<D, F>(
+        &mut self,
+        window: &mut PistonAppWindow,
+        renderer: &PistonRenderer,
+        pipeline: &PistonPipeline<D::Meta>,
+        f: F,
+    ) where
+        D: pso::PipelineData<Resources>,
+        F: FnMut(gfx::handle::Buffer<Resources, Vertex>, PistonRenderTargetView) -> D,
+    {
+        self.draw(window, renderer, pipeline, f);
+        renderer.flush(window);
+    }
+
+    /// Makes room for `count` vertices in the current draw call, starting a new
+    /// one if needed, and returns the index of the first one.
+    fn reserve(&mut self, count: usize) -> u32 {
+        if self.vertices.len() - self.base_vertex + count > self.max_vertices {
+            self.base_vertex = self.vertices.len();
+            self.splits.push((self.vertices.len(), self.indices.len()));
+        }
+        (self.vertices.len() - self.base_vertex) as u32
+    }
+
+    /// Texture coordinates of the corners of `uv`, in the order `rect()` draws
+    /// them.
+    fn uv_corners(&self, uv: [f32; 4]) -> [[f32; 2]; 4] {
+        let [u, v, tw, th] = uv;
+        let (top, bottom) = if self.flip_v {
+            (v + th, v)
+        } else {
+            (v, v + th)
+        };
+        [[u + tw, bottom], [u, bottom], [u, top], [u + tw, top]]
+    }
+
+    #[inline]
+    fn vertex(
+        transform: Matrix2d,
+        position: Vec2d,
+        uv: [f32; 2],
+        color: Color,
+    ) -> Vertex {
+        let pos = math::transform_pos(transform, position);
+        Vertex {
+            pos: [pos[0] as f32, pos[1] as f32],
+            uv: uv,
+            color: color,
+        }
+    }
+}
+
+impl Default for SpriteBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UVS: [[f32; 2]; 4] = [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+
+    fn quad(batch: &mut SpriteBatch) {
+        let positions = [[1.0, 1.0], [0.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+        batch.quad(positions, UVS, color::WHITE);
+    }
+
+    fn triangle(batch: &mut SpriteBatch) {
+        let positions = [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]];
+        batch.triangle(positions, [UVS[0], UVS[1], UVS[2]], color::WHITE);
+    }
+
+    #[test]
+    fn fills_one_draw_call_up_to_the_limit() {
+        let mut batch = SpriteBatch::new().max_vertices(8);
+        assert_eq!(batch.draw_count(), 0);
+        quad(&mut batch);
+        quad(&mut batch);
+        assert_eq!(batch.draw_count(), 1);
+        assert_eq!(batch.vertices().len(), 8);
+        assert_eq!(&batch.indices()[6..], &[4, 5, 6, 6, 7, 4]);
+    }
+
+    #[test]
+    fn splits_draw_calls_past_the_limit() {
+        let mut batch = SpriteBatch::new().max_vertices(8);
+        quad(&mut batch);
+        quad(&mut batch);
+        triangle(&mut batch);
+        quad(&mut batch);
+        triangle(&mut batch);
+        assert_eq!(batch.draw_count(), 3);
+        assert_eq!(batch.splits, vec![(8, 12), (15, 21)]);
+        // Indices are rebased to the first vertex of their draw call.
+        assert_eq!(&batch.indices()[12..15], &[0, 1, 2]);
+        assert_eq!(&batch.indices()[15..21], &[3, 4, 5, 5, 6, 3]);
+        assert_eq!(&batch.indices()[21..], &[0, 1, 2]);
+        assert_eq!(batch.vertices().len(), 18);
+    }
+
+    #[test]
+    fn clear_resets_the_splits() {
+        let mut batch = SpriteBatch::new().max_vertices(8);
+        for _ in 0..3 {
+            quad(&mut batch);
+        }
+        assert_eq!(batch.draw_count(), 2);
+        batch.clear();
+        assert!(batch.is_empty());
+        assert_eq!(batch.draw_count(), 0);
+        quad(&mut batch);
+        assert_eq!(batch.draw_count(), 1);
+        assert_eq!(batch.indices(), &[0, 1, 2, 2, 3, 0]);
+    }
+
+    #[test]
+    fn clamps_the_limit_to_a_quad() {
+        let mut batch = SpriteBatch::new().max_vertices(1);
+        quad(&mut batch);
+        quad(&mut batch);
+        assert_eq!(batch.draw_count(), 2);
+        assert_eq!(batch.indices(), &[0, 1, 2, 2, 3, 0, 0, 1, 2, 2, 3, 0]);
+    }
+}
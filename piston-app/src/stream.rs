@@ -0,0 +1,137 @@
+//! Vertex and index buffers reused from frame to frame.
+
+use super::*;
+
+/// Dynamic GPU vertex and index buffers, allocated on first use and refilled
+/// through `update_buffer()` afterwards. They only grow, to the next power of
+/// two, when an upload no longer fits.
+///
+/// Uploads and draws are queued on the window encoder: several draws can share
+/// one `PistonRenderer::flush()`.
+#[derive(Debug)]
+pub struct PistonStreamBuffer<V> {
+    vertices: Option<gfx::handle::Buffer<Resources, V>>,
+    indices: Option<gfx::handle::Buffer<Resources, u32>>,
+    vertex_capacity: usize,
+    index_capacity: usize,
+    allocations: usize,
+}
+
+impl<V> PistonStreamBuffer<V>
+where
+    V: gfx::traits::Pod + pso::buffer::Structure<gfx::format::Format>,
+{
+    pub fn new() -> Self {
+        PistonStreamBuffer {
+            vertices: None,
+            indices: None,
+            vertex_capacity: 0,
+            index_capacity: 0,
+            allocations: 0,
+        }
+    }
+
+    #[inline]
+    pub fn vertex_capacity(&self) -> usize {
+        self.vertex_capacity
+    }
+
+    #[inline]
+    pub fn index_capacity(&self) -> usize {
+        self.index_capacity
+    }
+
+    /// Number of times the GPU buffers were (re)allocated.
+    #[inline]
+    pub fn allocations(&self) -> usize {
+        self.allocations
+    }
+
+    /// Size of the GPU buffers, in bytes.
+    pub fn memory_size(&self) -> usize {
+        self.vertex_capacity * std::mem::size_of::<V>()
+            + self.index_capacity * std::mem::size_of::<u32>()
+    }
+
+    /// Queues an upload of `vertices` and `indices` to the start of the buffers,
+    /// growing them first if needed.
+    pub fn upload(
+        &mut self,
+        window: &mut PistonAppWindow,
+        vertices: &[V],
+        indices: &[u32],
+    ) -> Result<(), Box<dyn Error>> {
+        self.reserve(window, vertices.len(), indices.len())?;
+        if let Some(ref buffer) = self.vertices {
+            window.encoder.update_buffer(buffer, vertices, 0)?;
+        }
+        if let Some(ref buffer) = self.indices {
+            window.encoder.update_buffer(buffer, indices, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Vertex buffer, to bind in the pipeline data. Panics if nothing was
+    /// uploaded yet.
+    pub fn vertex_buffer(&self) -> gfx::handle::Buffer<Resources, V> {
+        self.vertices.clone().expect("stream buffer not uploaded")
+    }
+
+    /// Slice drawing the uploaded indices `start..end`, relative to the vertex
+    /// `base_vertex`. Panics if nothing was uploaded yet.
+    pub fn slice(
+        &self,
+        start: usize,
+        end: usize,
+        base_vertex: usize,
+    ) -> Slice<Resources> {
+        Slice {
+            start: start as u32,
+            end: end as u32,
+            base_vertex: base_vertex as u32,
+            instances: None,
+            buffer: IndexBuffer::Index32(
+                self.indices.clone().expect("stream buffer not uploaded"),
+            ),
+        }
+    }
+
+    fn reserve(
+        &mut self,
+        window: &mut PistonAppWindow,
+        vertex_count: usize,
+        index_count: usize,
+    ) -> Result<(), Box<dyn Error>> {
+        let factory = &mut window.factory;
+        if self.vertices.is_none() || vertex_count > self.vertex_capacity {
+            self.vertex_capacity = vertex_count.max(1).next_power_of_two();
+            self.vertices = Some(factory.create_buffer(
+                self.vertex_capacity,
+                gfx::buffer::Role::Vertex,
+                gfx::memory::Usage::Dynamic,
+                gfx::memory::Bind::empty(),
+            )?);
+            self.allocations += 1;
+        }
+        if self.indices.is_none() || index_count > self.index_capacity {
+            self.index_capacity = index_count.max(1).next_power_of_two();
+            self.indices = Some(factory.create_buffer(
+                self.index_capacity,
+                gfx::buffer::Role::Index,
+                gfx::memory::Usage::Dynamic,
+                gfx::memory::Bind::empty(),
+            )?);
+            self.allocations += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<V> Default for PistonStreamBuffer<V>
+where
+    V: gfx::traits::Pod + pso::buffer::Structure<gfx::format::Format>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
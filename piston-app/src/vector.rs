@@ -0,0 +1,303 @@
+//! Two-dimensional vector, mirroring Processing's `PVector`.
+
+use super::*;
+
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Two-dimensional vector with arithmetic operators, mirroring the `PVector`
+/// API used in the book.
+///
+/// Unlike `PVector`, methods such as `normalize()` or `limit()` return a new
+/// vector instead of modifying this one, so they can be chained. Converts
+/// to and from `Vec2d` without loss.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Vector2 {
+    pub x: Scalar,
+    pub y: Scalar,
+}
+
+impl Vector2 {
+    #[inline]
+    pub fn new(x: Scalar, y: Scalar) -> Self {
+        Vector2 { x: x, y: y }
+    }
+
+    #[inline]
+    pub fn zero() -> Self {
+        Vector2 { x: 0.0, y: 0.0 }
+    }
+
+    /// Unit vector pointing at `angle` radians.
+    #[inline]
+    pub fn from_angle(angle: Scalar) -> Self {
+        Vector2::new(angle.cos(), angle.sin())
+    }
+
+    /// Unit vector pointing in a random direction.
+    pub fn random2d<R: Rng>(rng: &mut R) -> Self {
+        Vector2::from_angle(rng.gen_range(0.0, consts::PI * 2.0))
+    }
+
+    #[inline]
+    pub fn mag(self) -> Scalar {
+        self.mag_sq().sqrt()
+    }
+
+    #[inline]
+    pub fn mag_sq(self) -> Scalar {
+        self.x * self.x + self.y * self.y
+    }
+
+    /// Same direction, unit length. The zero vector stays as is.
+    pub fn normalize(self) -> Self {
+        let mag = self.mag();
+        if mag > 0.0 {
+            self / mag
+        } else {
+            self
+        }
+    }
+
+    #[inline]
+    pub fn set_mag(self, mag: Scalar) -> Self {
+        self.normalize() * mag
+    }
+
+    pub fn limit(self, max: Scalar) -> Self {
+        if self.mag_sq() > max * max {
+            self.set_mag(max)
+        } else {
+            self
+        }
+    }
+
+    /// Angle of rotation in radians.
+    #[inline]
+    pub fn heading(self) -> Scalar {
+        self.y.atan2(self.x)
+    }
+
+    /// Rotated by `angle` radians.
+    pub fn rotate(self, angle: Scalar) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Vector2::new(self.x * cos - self.y * sin, self.x * sin + self.y * cos)
+    }
+
+    /// Linear interpolation towards `other`, by `amount` between 0 and 1.
+    #[inline]
+    pub fn lerp(self, other: Self, amount: Scalar) -> Self {
+        self + (other - self) * amount
+    }
+
+    #[inline]
+    pub fn dist(self, other: Self) -> Scalar {
+        (other - self).mag()
+    }
+
+    #[inline]
+    pub fn dot(self, other: Self) -> Scalar {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// Unsigned angle in radians between `a` and `b`, 0 if either is zero.
+    pub fn angle_between(a: Self, b: Self) -> Scalar {
+        let mags = a.mag() * b.mag();
+        if mags == 0.0 {
+            return 0.0;
+        }
+        (a.dot(b) / mags).clamp(-1.0, 1.0).acos()
+    }
+}
+
+impl From<Vec2d> for Vector2 {
+    #[inline]
+    fn from(vec: Vec2d) -> Self {
+        Vector2::new(vec[0], vec[1])
+    }
+}
+
+impl From<Vector2> for Vec2d {
+    #[inline]
+    fn from(vec: Vector2) -> Self {
+        [vec.x, vec.y]
+    }
+}
+
+impl Add for Vector2 {
+    type Output = Vector2;
+
+    #[inline]
+    fn add(self, other: Vector2) -> Vector2 {
+        Vector2::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Vector2 {
+    type Output = Vector2;
+
+    #[inline]
+    fn sub(self, other: Vector2) -> Vector2 {
+        Vector2::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+impl Mul<Scalar> for Vector2 {
+    type Output = Vector2;
+
+    #[inline]
+    fn mul(self, scalar: Scalar) -> Vector2 {
+        Vector2::new(self.x * scalar, self.y * scalar)
+    }
+}
+
+impl Mul<Vector2> for Scalar {
+    type Output = Vector2;
+
+    #[inline]
+    fn mul(self, vec: Vector2) -> Vector2 {
+        vec * self
+    }
+}
+
+impl Div<Scalar> for Vector2 {
+    type Output = Vector2;
+
+    #[inline]
+    fn div(self, scalar: Scalar) -> Vector2 {
+        Vector2::new(self.x / scalar, self.y / scalar)
+    }
+}
+
+impl Neg for Vector2 {
+    type Output = Vector2;
+
+    #[inline]
+    fn neg(self) -> Vector2 {
+        Vector2::new(-self.x, -self.y)
+    }
+}
+
+impl AddAssign for Vector2 {
+    #[inline]
+    fn add_assign(&mut self, other: Vector2) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Vector2 {
+    #[inline]
+    fn sub_assign(&mut self, other: Vector2) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign<Scalar> for Vector2 {
+    #[inline]
+    fn mul_assign(&mut self, scalar: Scalar) {
+        *self = *self * scalar;
+    }
+}
+
+impl DivAssign<Scalar> for Vector2 {
+    #[inline]
+    fn div_assign(&mut self, scalar: Scalar) {
+        *self = *self / scalar;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const EPSILON: Scalar = 1e-9;
+
+    pub(crate) fn assert_close(a: Vector2, b: Vector2) {
+        assert!(a.dist(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Vector2::new(1.0, 2.0);
+        let b = Vector2::new(3.0, -4.0);
+        assert_eq!(a + b, Vector2::new(4.0, -2.0));
+        assert_eq!(a - b, Vector2::new(-2.0, 6.0));
+        assert_eq!(a * 2.0, Vector2::new(2.0, 4.0));
+        assert_eq!(2.0 * a, a * 2.0);
+        assert_eq!(b / 2.0, Vector2::new(1.5, -2.0));
+        assert_eq!(-a, Vector2::new(-1.0, -2.0));
+        assert_eq!(a.dot(b), -5.0);
+
+        let mut c = a;
+        c += b;
+        c -= a;
+        c *= 2.0;
+        c /= 4.0;
+        assert_eq!(c, b / 2.0);
+    }
+
+    #[test]
+    fn converts_to_and_from_vec2d() {
+        let v: Vec2d = Vector2::new(1.5, -2.5).into();
+        assert_eq!(v, [1.5, -2.5]);
+        assert_eq!(Vector2::from(v), Vector2::new(1.5, -2.5));
+    }
+
+    #[test]
+    fn magnitude_and_normalize() {
+        let v = Vector2::new(3.0, 4.0);
+        assert_eq!(v.mag_sq(), 25.0);
+        assert_eq!(v.mag(), 5.0);
+        assert_close(v.normalize(), Vector2::new(0.6, 0.8));
+        assert_close(v.set_mag(10.0), Vector2::new(6.0, 8.0));
+        assert_eq!(Vector2::zero().normalize(), Vector2::zero());
+    }
+
+    #[test]
+    fn limit() {
+        let v = Vector2::new(3.0, 4.0);
+        assert_close(v.limit(2.5), Vector2::new(1.5, 2.0));
+        assert_eq!(v.limit(5.0), v);
+        assert_eq!(v.limit(10.0), v);
+    }
+
+    #[test]
+    fn heading_and_rotate() {
+        assert_eq!(Vector2::new(1.0, 0.0).heading(), 0.0);
+        assert!((Vector2::new(0.0, 2.0).heading() - consts::FRAC_PI_2).abs() < EPSILON);
+        assert!((Vector2::new(-1.0, 0.0).heading() - consts::PI).abs() < EPSILON);
+        assert_close(
+            Vector2::from_angle(consts::FRAC_PI_2),
+            Vector2::new(0.0, 1.0),
+        );
+        assert_close(
+            Vector2::new(1.0, 0.0).rotate(consts::FRAC_PI_2),
+            Vector2::new(0.0, 1.0),
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!((Vector2::random2d(&mut rng).mag() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn lerp_and_dist() {
+        let a = Vector2::new(0.0, 0.0);
+        let b = Vector2::new(10.0, -20.0);
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 0.5), Vector2::new(5.0, -10.0));
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert_eq!(Vector2::new(1.0, 1.0).dist(Vector2::new(4.0, 5.0)), 5.0);
+    }
+
+    #[test]
+    fn angle_between_is_clamped() {
+        let a = Vector2::new(1.0, 0.0);
+        assert!(
+            (Vector2::angle_between(a, Vector2::new(0.0, 3.0)) - consts::FRAC_PI_2).abs()
+                < EPSILON
+        );
+        // Rounding can push the cosine of parallel vectors past 1.
+        let v = Vector2::new(0.1, 0.7);
+        assert_eq!(Vector2::angle_between(v, v * 3.0), 0.0);
+        assert!((Vector2::angle_between(v, -v) - consts::PI).abs() < EPSILON);
+        assert_eq!(Vector2::angle_between(a, Vector2::zero()), 0.0);
+    }
+}